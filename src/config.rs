@@ -1,26 +1,49 @@
+use crate::organizer::is_invalid_in_name;
+use crate::{
+    ConflictStrategy, DateSource, ExifDateTag, ExifTimezone, Locale, PhotoOrganizer,
+    ResolutionTier, TakeoutSidecar, TransferMode, UnicodeNormalization,
+};
+use chrono::DateTime;
 use color_eyre::eyre::{bail, eyre, Result, WrapErr};
 use directories::ProjectDirs;
+use glob::Pattern;
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
 use std::ffi::OsString;
-use std::path::PathBuf;
-use viperus::{Format, Viperus};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use viperus::{AdapterResult, ConfigAdapter, Format, Map, Viperus};
 
 /// Loads the configuration options.
 ///
 /// The configuration can be set via command line arguments or via
-/// config file using the TOML format. The command line arguments have
-/// precedence over the configuration file in cases where both are
-/// specified.
+/// config file. The file format is taken from its extension, `json`,
+/// `yaml` or `yml`, and `toml`, falling back to TOML. Every option can
+/// also be set with an environment variable named like its TOML key in
+/// upper case and prefixed with `MEDIA_ORGANIZER_`, for example
+/// `MEDIA_ORGANIZER_MEDIA_SRC`. When an option is set in more than one
+/// place the command line arguments win, then the environment variables
+/// and then the configuration file.
 ///
 /// The available configuration options are:
 ///
 /// - Config file: file to load configuration from.
 ///     - cmd line long: --config-file
 ///     - cmd line short: -c
-///   Defaults to:
-///     - Linux: /home/ainara/.config/media-organizer/config.toml
-///     - Windows: C:\\Users\\Ainara\\AppData\\Roaming\\adn\\media-organizer\\config\\config.toml
-///     - Mac: /Users/Ainara/Library/Application Support/dev.adn.media-organizer/config.toml",
-/// - Media source: Source directory with media files to organize.
+///     - Defaults to:
+///       - Linux: /home/ainara/.config/media-organizer/config.toml
+///       - Windows: C:\\Users\\Ainara\\AppData\\Roaming\\adn\\media-organizer\\config\\config.toml
+///       - Mac: /Users/Ainara/Library/Application Support/dev.adn.media-organizer/config.toml",
+/// - Profile: Name of a `[profiles.<name>]` section of the config file
+///   whose options are merged over the rest of the file.
+///     - cmd line long: --profile
+///     - toml: profile
+/// - Media source: Source directory with media files to organize. It
+///   can be repeated, or a TOML array, to organize several sources in
+///   the same run.
 ///     - cmd line long: --media-src
 ///     - cmd short: -m
 ///     - toml: media_src
@@ -32,6 +55,304 @@ use viperus::{Format, Viperus};
 ///     - cmd line long: --videos-dst
 ///     - cmd short: -v
 ///     - toml: videos_dst
+/// - Audio destination: Directory where audio files will be moved and
+///   organized.
+///     - cmd line long: --audio-dst
+///     - cmd short: -a
+///     - toml: audio_dst
+/// - Misc destination: Directory where the files that no other organizer
+///   handles are moved.
+///     - cmd line long: --misc-dst
+///     - toml: misc_dst
+/// - Destination overrides: Map from extension to the directory where
+///   the files with it are organized, instead of the destination of
+///   their organizer.
+///     - toml: `[destination_overrides]` section, e.g. `png = '/screenshots'`
+/// - Folder format: Template of the photos directory structure, using
+///   %Y for the year, %m for the month number, %B for the month name and
+///   %b for the abbreviated month name. Defaults to `%Y/%m - %B`.
+///     - cmd line long: --folder-format
+///     - toml: folder_format
+/// - Photo extensions: Comma separated extensions of the files organized
+///   as photos, replacing the default `jpeg,jpg,png,cr2,nef,arw,dng`.
+///     - cmd line long: --photo-extensions
+///     - toml: photo_extensions
+/// - Photo filename regex: Regex to take the date of the photos from
+///   their name, with `year` and `month` named groups, e.g.
+///   `^DSC(?P<year>\d{4})(?P<month>\d{2})`. Replaces the default ones.
+///     - cmd line long: --photo-filename-regex
+///     - toml: photo_filename_regex
+/// - Video filename regex: Regex to take the date of the videos from
+///   their name, with `year` and `month` named groups. Replaces the
+///   default one.
+///     - cmd line long: --video-filename-regex
+///     - toml: video_filename_regex
+/// - Exif date tags: Comma separated exif tags the photo date is taken
+///   from, the first present is used. Defaults to
+///   `DateTimeOriginal,DateTimeDigitized,DateTime`.
+///     - cmd line long: --exif-date-tags
+///     - toml: exif_date_tags
+/// - Date source order: Sources the photo dates are taken from, in the
+///   order they are tried, separated by commas, any of exif, filename,
+///   sidecar (XMP), mtime and birthtime (creation time, where the
+///   platform tracks it). Defaults to `exif,filename,sidecar`.
+///     - cmd line long: --date-source-order
+///     - toml: date_source_order
+/// - Takeout sidecar: When the date of the Google Takeout JSON sidecar
+///   of a photo, `photo.jpg.json`, is used: first, before the exif, last,
+///   after the exif, name and XMP sidecar, or ignore. Defaults to first.
+///     - cmd line long: --takeout-sidecar
+///     - toml: takeout_sidecar
+/// - Exif timezone: normalize to convert the exif dates to UTC with their
+///   offset tag, like `OffsetTimeOriginal`, or ignore to take them as the
+///   local time of the camera. Defaults to ignore.
+///     - cmd line long: --exif-timezone
+///     - toml: exif_timezone
+/// - Locale: Language of the month names, one of en, es, fr, de or pt.
+///     - cmd line long: --locale
+///     - toml: locale
+/// - Respect date folders: Keep source folders named like a date, e.g.
+///   `2019-07-trip`, together under the date of the folder.
+///     - cmd line long: --respect-date-folders
+///     - toml: respect_date_folders
+/// - Keep live photos: Organize the `.mov` video of a Live Photo into
+///   the destination of its `.heic` or `.jpg` photo.
+///     - cmd line long: --keep-live-photos
+///     - toml: keep_live_photos
+/// - Flatten: Give every file a name of its own in its destination
+///   directory, appending ` (1)`, ` (2)`, etc. to the taken ones, no
+///   matter the source folder. It can't be used with respect date
+///   folders.
+///     - cmd line long: --flatten
+///     - toml: flatten
+/// - Preserve structure: Recreate the folders of the files inside
+///   their media source under their date directory, `Trip/day1` ends up
+///   in `2019/01 - January/Trip/day1`.
+///     - cmd line long: --preserve-structure
+///     - toml: preserve_structure
+/// - Group by location: Group the photos inside their date directory by
+///   the country of their GPS exif tags, e.g. `2020/04 - April/Spain`.
+///     - cmd line long: --group-by-location
+///     - toml: group_by_location
+/// - Group by city: Group the photos inside their date directory, and
+///   country when grouping by location, by the nearest city to their GPS
+///   exif tags, e.g. `2020/04 - April/Madrid`.
+///     - cmd line long: --group-by-city
+///     - toml: group_by_city
+/// - Group by camera: Group the photos inside their date directory by
+///   the camera model of their exif, e.g. `2020/04 - April/Canon EOS R`.
+///     - cmd line long: --group-by-camera
+///     - toml: group_by_camera
+/// - Group bursts: Group the shots of a burst, like
+///   `IMG_0001_BURST001.jpg`, inside their date directory in
+///   `Bursts/<id>`, e.g. `2020/04 - April/Bursts/IMG_0001`.
+///     - cmd line long: --group-bursts
+///     - toml: group_bursts
+/// - Fix orientation: Rotate JPEGs to the orientation of their exif
+///   `Orientation` tag when moving them, and reset the tag.
+///     - cmd line long: --fix-orientation
+///     - toml: fix_orientation
+/// - Video group by month: Organize the videos by month inside the year,
+///   like the photos, e.g. `2020/08 - August`.
+///     - cmd line long: --video-group-by-month
+///     - toml: video_group_by_month
+/// - Use mtime fallback: Organize the photos, videos and audio files
+///   whose date can't be taken from their metadata nor the name by their
+///   modification time.
+///     - cmd line long: --use-mtime-fallback
+///     - toml: use_mtime_fallback
+/// - Unknown date: Move the photos, videos and audio files without a date
+///   to a folder of their destination, instead of leaving them in the
+///   source.
+///     - cmd line long: --unknown-date
+///     - toml: unknown_date
+/// - Unknown date dir: Name of the folder of each destination where the
+///   files without a date are moved, setting it also moves them. Defaults
+///   to `unknown-date`.
+///     - cmd line long: --unknown-date-dir
+///     - toml: unknown_date_dir
+/// - Rename by date: Prefix the name of photos and videos with their
+///   `YYYYMM` date, like `201901_camera.jpg`.
+///     - cmd line long: --rename-by-date
+///     - toml: rename_by_date
+/// - Group by resolution: Group the videos inside the year by resolution.
+///     - cmd line long: --group-by-resolution
+///     - toml: group_by_resolution
+/// - Resolution tiers: Comma separated `name=lines` tiers used to group
+///   videos by resolution, defaults to `4K=2160,HD=720,SD=0`.
+///     - cmd line long: --resolution-tiers
+///     - toml: resolution_tiers
+/// - Unknown placeholder: Name of the folder where the photos go when
+///   grouped by location, city or camera and they don't have it, and the
+///   videos when grouped by a resolution that can't be read or doesn't
+///   reach any tier. Videos default to `unknown`, photos to staying in
+///   the folder above.
+///     - cmd line long: --unknown-placeholder
+///     - toml: unknown_placeholder
+/// - Also flat: Directory where a link to every organized file is created.
+///     - cmd line long: --also-flat
+///     - toml: also_flat
+/// - Min size: Files smaller than this are not organized. Accepts the
+///   suffixes K, M and G.
+///     - cmd line long: --min-size
+///     - toml: min_size
+/// - Max size: Files bigger than this are not organized. Accepts the
+///   suffixes K, M and G.
+///     - cmd line long: --max-size
+///     - toml: max_size
+/// - Modified after: Only files modified after this RFC3339 date are
+///   organized.
+///     - cmd line long: --modified-after
+///     - toml: modified_after
+/// - Modified before: Only files modified before this RFC3339 date are
+///   organized.
+///     - cmd line long: --modified-before
+///     - toml: modified_before
+/// - Exclude: Glob patterns of the files and directories that are not
+///   organized, matched against their name and their path relative to
+///   the media source. It can be repeated, or a TOML array.
+///     - cmd line long: --exclude
+///     - toml: exclude
+/// - Copy: Copy the files instead of moving them.
+///     - cmd line long: --copy
+///     - toml: copy
+/// - Hardlink: Hardlink the files instead of moving them, falling back to
+///   a copy when the link can't be created.
+///     - cmd line long: --hardlink
+///     - toml: hardlink
+/// - On conflict: What to do when a file with the same name already
+///   exists in the destination: skip, overwrite, rename or error.
+///   Defaults to error.
+///     - cmd line long: --on-conflict
+///     - toml: on_conflict
+/// - Dedup: Remove, or skip when copying, files identical to the one with
+///   the same name in the destination.
+///     - cmd line long: --dedup
+///     - toml: dedup
+/// - Trash duplicates: Send the files whose name is taken in the
+///   destination by a different file to the trash, instead of erroring
+///   or skipping them.
+///     - cmd line long: --trash-duplicates
+///     - toml: trash_duplicates
+/// - Normalize extension: Lowercase the extension of the destination
+///   file names, `.JPG` becomes `.jpg`.
+///     - cmd line long: --normalize-extension
+///     - toml: normalize_extension
+/// - Sanitize paths: Replace the characters FAT and exFAT don't allow,
+///   like `:` or `?`, in the destination names and trim their trailing
+///   dots and spaces.
+///     - cmd line long: --sanitize-paths
+///     - toml: sanitize_paths
+/// - Near duplicate distance: Photos whose perceptual hash differs in at
+///   most this many bits, out of 64, from a photo in the destination go
+///   to `duplicates-review` for review.
+///     - cmd line long: --near-duplicate-distance
+///     - toml: near_duplicate_distance
+/// - Min resolution: Photos smaller than this, as `WxH`, are skipped, like
+///   thumbnails or web images.
+///     - cmd line long: --min-resolution
+///     - toml: min_resolution
+/// - Normalize unicode: Unicode form, nfc or nfd, the destination file
+///   names are normalized to.
+///     - cmd line long: --normalize-unicode
+///     - toml: normalize_unicode
+/// - Organize newest first: Organize the most recently modified files first.
+///     - cmd line long: --organize-newest-first
+///     - toml: organize_newest_first
+/// - Min free space: Abort when moving a file would leave less than this
+///   free in the destination. Accepts the suffixes K, M and G.
+///     - cmd line long: --min-free-space
+///     - toml: min_free_space
+/// - Jobs: Number of files organized at the same time. Defaults to the
+///   number of CPUs, or 1 when interactive or organizing the newest files
+///   first.
+///     - cmd line long: --jobs
+///     - toml: jobs
+/// - Retries: Times a transfer failing with a transient IO error, like a
+///   timeout, is retried. Defaults to 3.
+///     - cmd line long: --retries
+///     - toml: retries
+/// - Retry delay: Wait before the first retry, doubled for each of the
+///   next ones. Accepts the suffixes ms and s, defaults to 500ms.
+///     - cmd line long: --retry-delay
+///     - toml: retry_delay
+/// - Journal: File where the moves are recorded to be undone.
+///     - cmd line long: --journal
+///     - toml: journal
+///     - Defaults to:
+///       - Linux: /home/ainara/.local/share/media-organizer/journal.tsv
+///       - Windows: C:\\Users\\Ainara\\AppData\\Roaming\\adn\\media-organizer\\data\\journal.tsv
+///       - Mac: /Users/Ainara/Library/Application Support/dev.adn.media-organizer/journal.tsv
+/// - Report JSON: File where a JSON report of the run is written, with
+///   the date, organizer, destination and outcome of every file.
+///     - cmd line long: --report-json
+///     - toml: report_json
+/// - Manifest: File where a CSV inventory of the moved files is written,
+///   with their original and new path, date, organizer, size and SHA-256.
+///     - cmd line long: --manifest
+///     - toml: manifest
+/// - Undo: Move back the files recorded in the journal instead of
+///   organizing.
+///     - cmd line long: --undo
+///     - toml: undo
+/// - Find duplicates: Print the groups of files of the media source with
+///   the same content instead of organizing.
+///     - cmd line long: --find-duplicates
+///     - toml: find_duplicates
+/// - Prune empty: Remove the directories of the media source left empty.
+///     - cmd line long: --prune-empty
+///     - toml: prune_empty
+/// - No recurse: Only organize the files directly in the media source,
+///   leaving its subdirectories untouched.
+///     - cmd line long: --no-recurse
+///     - toml: no_recurse
+/// - Skip empty: Leave the empty files in the media source, counting them
+///   apart in the summary.
+///     - cmd line long: --skip-empty
+///     - toml: skip_empty
+/// - Verify: Compare the hash of each file before and after transferring
+///   it, reporting the ones that differ as corrupted.
+///     - cmd line long: --verify
+///     - toml: verify
+/// - Watch: Keep running and organize the files that appear in the media
+///   source.
+///     - cmd line long: --watch
+///     - toml: watch
+/// - Preview: Print where each file would be organized, grouped by
+///   destination directory, without organizing anything.
+///     - cmd line subcommand: preview
+/// - Plan: Write where each file would be organized to a JSON file,
+///   without organizing anything.
+///     - cmd line subcommand: plan <FILE>
+/// - Apply: Organize the files as written by plan.
+///     - cmd line subcommand: apply <FILE>
+/// - Create dirs: Create the destination directories that don't exist
+///   instead of failing.
+///     - cmd line long: --create-dirs
+///     - toml: create_dirs
+/// - Dry run: Print what would be moved without moving anything.
+///     - cmd line long: --dry-run
+///     - toml: dry_run
+/// - Interactive: Ask before transferring each file. It can't be used
+///   with dry run.
+///     - cmd line long: --interactive
+///     - toml: interactive
+/// - Extract motion video: Extract the video embedded in motion photos
+///   into the videos destination.
+///     - cmd line long: --extract-motion-video
+///     - toml: extract_motion_video
+/// - Quiet: Only log warnings and errors, and show no progress.
+///     - cmd line long: --quiet
+///     - cmd line short: -q
+///     - toml: quiet
+/// - Verbose: Log more details, can be repeated. The `RUST_LOG`
+///   environment variable overrides it.
+///     - cmd line long: --verbose
+///     - toml: verbose, as the number of repetitions
+/// - Log file: File where the warnings and errors are also logged, and
+///   the details of verbose mode.
+///     - cmd line long: --log-file
+///     - toml: log_file
 /// - No load default config file: Do not load the config file from the default location.
 ///     - cmd line long: --no-load-default-config-file
 pub fn get_config<I, T>(cmd_args: I) -> Result<Config>
@@ -39,43 +360,343 @@ where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
 {
+    let env_vars = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    get_config_with_env(cmd_args, env_vars)
+}
+
+fn get_config_with_env<I, T, E>(cmd_args: I, env_vars: E) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+    E: IntoIterator<Item = (String, String)>,
+{
+    let env_vars: Vec<(String, String)> = env_vars.into_iter().collect();
+    let expand = |path: String| expand_path(&path, &env_vars);
     let mut v = Viperus::new();
-    let should_load_default_config_file = load_claps(&mut v, cmd_args)
+    let cmd_line = load_claps(&mut v, cmd_args, env_vars.iter().cloned())
         .wrap_err_with(|| eyre!("failed to load command line arguments"))?;
 
-    let config_file_loaded = match v.get::<String>("config_file") {
-        Some(config_file) => {
-            if let Err(e) = v.load_file(&config_file, Format::TOML) {
-                bail!("failed to load config file '{}': {}", config_file, e);
-            }
-            true
+    let mut config_file = v.get::<String>("config_file").map(expand).transpose()?;
+    if config_file.is_none() && cmd_line.load_default_config_file {
+        config_file = get_default_config_file();
+    }
+    let mut file_options = serde_json::Map::new();
+    if let Some(config_file) = &config_file {
+        if let Err(e) = v.load_file(config_file, config_file_format(config_file)) {
+            bail!("failed to load config file '{}': {}", config_file, e);
         }
-        None => false,
+        file_options = read_config_file(config_file)?;
+    }
+    if let Some(name) = v.get::<String>("profile") {
+        let profile = select_profile(&file_options, &name)?;
+        if let Err(e) = v.load_adapter(&mut ProfileAdapter(profile.clone())) {
+            bail!("failed to load profile '{}': {}", name, e);
+        }
+        file_options.extend(profile);
+    }
+
+    let media_srcs = list_option(
+        cmd_line.media_srcs,
+        &v,
+        "media_src",
+        &file_options,
+        &cmd_line.env_keys,
+    )?
+    .into_iter()
+    .map(expand)
+    .collect::<Result<Vec<_>>>()?;
+    let mut config_builder = ConfigBuilder::new(media_srcs);
+
+    config_builder = match v.get::<String>("photos_dst") {
+        Some(dir) => config_builder.with_photos_dst(expand(dir)?),
+        None => config_builder,
     };
 
-    if !config_file_loaded && should_load_default_config_file {
-        if let Some(config_file) = get_default_config_file() {
-            if let Err(e) = v.load_file(&config_file, Format::TOML) {
-                bail!("failed to load config file '{}': {}", config_file, e);
-            }
+    config_builder = match v.get::<String>("videos_dst") {
+        Some(dir) => config_builder.with_videos_dst(expand(dir)?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("audio_dst") {
+        Some(dir) => config_builder.with_audio_dst(expand(dir)?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("misc_dst") {
+        Some(dir) => config_builder.with_misc_dst(expand(dir)?),
+        None => config_builder,
+    };
+
+    config_builder = config_builder.with_destination_overrides(
+        destination_overrides(&file_options)?
+            .into_iter()
+            .map(|(extension, dir)| Ok((extension, expand(dir)?)))
+            .collect::<Result<_>>()?,
+    );
+
+    config_builder = match v.get::<String>("folder_format") {
+        Some(folder_format) => config_builder.with_folder_format(folder_format),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("photo_extensions") {
+        Some(extensions) => config_builder.with_photo_extensions(
+            parse_extensions(&extensions).wrap_err("invalid photo extensions")?,
+        ),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("photo_filename_regex") {
+        Some(regex) => config_builder.with_photo_filename_regex(
+            parse_filename_regex(&regex).wrap_err("invalid photo filename regex")?,
+        ),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("video_filename_regex") {
+        Some(regex) => config_builder.with_video_filename_regex(
+            parse_filename_regex(&regex).wrap_err("invalid video filename regex")?,
+        ),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("exif_date_tags") {
+        Some(tags) => config_builder
+            .with_exif_date_tags(parse_exif_date_tags(&tags).wrap_err("invalid exif date tags")?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("date_source_order") {
+        Some(sources) => config_builder
+            .with_date_sources(parse_date_sources(&sources).wrap_err("invalid date source order")?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("takeout_sidecar") {
+        Some(order) => config_builder.with_takeout_sidecar(parse_takeout_sidecar(&order)?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("exif_timezone") {
+        Some(timezone) => config_builder.with_exif_timezone(parse_exif_timezone(&timezone)?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("locale") {
+        Some(locale) => config_builder.with_locale(parse_locale(&locale)?),
+        None => config_builder,
+    };
+
+    config_builder = config_builder
+        .with_respect_date_folders(v.get::<bool>("respect_date_folders").unwrap_or(false));
+    config_builder =
+        config_builder.with_keep_live_photos(v.get::<bool>("keep_live_photos").unwrap_or(false));
+    config_builder = config_builder.with_flatten(v.get::<bool>("flatten").unwrap_or(false));
+    config_builder = config_builder
+        .with_preserve_structure(v.get::<bool>("preserve_structure").unwrap_or(false));
+    config_builder =
+        config_builder.with_group_by_location(v.get::<bool>("group_by_location").unwrap_or(false));
+    config_builder =
+        config_builder.with_group_by_city(v.get::<bool>("group_by_city").unwrap_or(false));
+    config_builder =
+        config_builder.with_group_by_camera(v.get::<bool>("group_by_camera").unwrap_or(false));
+    config_builder =
+        config_builder.with_group_bursts(v.get::<bool>("group_bursts").unwrap_or(false));
+    config_builder =
+        config_builder.with_fix_orientation(v.get::<bool>("fix_orientation").unwrap_or(false));
+    config_builder = config_builder
+        .with_video_group_by_month(v.get::<bool>("video_group_by_month").unwrap_or(false));
+    config_builder = config_builder
+        .with_use_mtime_fallback(v.get::<bool>("use_mtime_fallback").unwrap_or(false));
+    config_builder =
+        config_builder.with_unknown_date(v.get::<bool>("unknown_date").unwrap_or(false));
+    config_builder = match v.get::<String>("unknown_date_dir") {
+        Some(dir) => config_builder.with_unknown_date_dir(PathBuf::from(dir)),
+        None => config_builder,
+    };
+    config_builder =
+        config_builder.with_rename_by_date(v.get::<bool>("rename_by_date").unwrap_or(false));
+
+    config_builder = match (
+        v.get::<bool>("group_by_resolution").unwrap_or(false),
+        v.get::<String>("resolution_tiers"),
+    ) {
+        (true, Some(tiers)) => config_builder.with_resolution_tiers(
+            parse_resolution_tiers(&tiers).wrap_err("invalid resolution tiers")?,
+        ),
+        (true, None) => config_builder.with_resolution_tiers(ResolutionTier::defaults()),
+        (false, Some(_)) => bail!("resolution tiers require group by resolution"),
+        (false, None) => config_builder,
+    };
+    config_builder = match v.get::<String>("unknown_placeholder") {
+        Some(placeholder) => config_builder.with_unknown_placeholder(placeholder),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("also_flat") {
+        Some(dir) => config_builder.with_flat_dst(expand(dir)?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("min_size") {
+        Some(size) => config_builder.with_min_size(parse_size(&size).wrap_err("invalid min size")?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("max_size") {
+        Some(size) => config_builder.with_max_size(parse_size(&size).wrap_err("invalid max size")?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("modified_after") {
+        Some(time) => config_builder
+            .with_modified_after(parse_time(&time).wrap_err("invalid modified after date")?),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("modified_before") {
+        Some(time) => config_builder
+            .with_modified_before(parse_time(&time).wrap_err("invalid modified before date")?),
+        None => config_builder,
+    };
+
+    let exclude = list_option(
+        cmd_line.excludes,
+        &v,
+        "exclude",
+        &file_options,
+        &cmd_line.env_keys,
+    )?;
+    config_builder = config_builder.with_exclude(
+        exclude
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<_, _>>()
+            .wrap_err("invalid exclude pattern")?,
+    );
+
+    config_builder = match v.get::<String>("on_conflict") {
+        Some(strategy) => {
+            config_builder.with_conflict_strategy(parse_conflict_strategy(&strategy)?)
         }
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("journal").or_else(get_default_journal_file) {
+        Some(file) => config_builder.with_journal(PathBuf::from(expand(file)?)),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("log_file") {
+        Some(file) => config_builder.with_log_file(PathBuf::from(expand(file)?)),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("report_json") {
+        Some(file) => config_builder.with_report_json(PathBuf::from(expand(file)?)),
+        None => config_builder,
+    };
+
+    config_builder = match v.get::<String>("manifest") {
+        Some(file) => config_builder.with_manifest(PathBuf::from(expand(file)?)),
+        None => config_builder,
+    };
+
+    config_builder = config_builder.with_undo(v.get::<bool>("undo").unwrap_or(false));
+    config_builder =
+        config_builder.with_find_duplicates(v.get::<bool>("find_duplicates").unwrap_or(false));
+
+    config_builder = config_builder.with_prune_empty(v.get::<bool>("prune_empty").unwrap_or(false));
+    config_builder = config_builder.with_no_recurse(v.get::<bool>("no_recurse").unwrap_or(false));
+    config_builder = config_builder.with_skip_empty(v.get::<bool>("skip_empty").unwrap_or(false));
+    config_builder = config_builder.with_quiet(v.get::<bool>("quiet").unwrap_or(false));
+    config_builder = config_builder.with_verify(v.get::<bool>("verify").unwrap_or(false));
+    config_builder = config_builder.with_watch(v.get::<bool>("watch").unwrap_or(false));
+    config_builder = config_builder.with_preview(cmd_line.preview);
+    if let Some(plan) = cmd_line.plan {
+        config_builder = config_builder.with_plan(PathBuf::from(expand(plan)?));
+    }
+    if let Some(apply) = cmd_line.apply {
+        config_builder = config_builder.with_apply(PathBuf::from(expand(apply)?));
     }
+    config_builder = config_builder.with_create_dirs(v.get::<bool>("create_dirs").unwrap_or(false));
 
-    let mut config_builder = match v.get::<String>("media_src") {
-        Some(dir) => ConfigBuilder::new(dir),
-        None => bail!("media source is required"),
+    config_builder = match number_option(&v, "verbose", &file_options)? {
+        Some(verbose) if verbose < 0 => bail!("verbose can't be negative"),
+        Some(verbose) => config_builder.with_verbosity(verbose as u8),
+        None => config_builder,
     };
 
-    config_builder = match v.get::<String>("photos_dst") {
-        Some(dir) => config_builder.with_photos_dst(dir),
+    config_builder = config_builder.with_dedup(v.get::<bool>("dedup").unwrap_or(false));
+    config_builder =
+        config_builder.with_trash_duplicates(v.get::<bool>("trash_duplicates").unwrap_or(false));
+    config_builder = config_builder
+        .with_normalize_extension(v.get::<bool>("normalize_extension").unwrap_or(false));
+    config_builder =
+        config_builder.with_sanitize_paths(v.get::<bool>("sanitize_paths").unwrap_or(false));
+
+    if let Some(distance) = number_option(&v, "near_duplicate_distance", &file_options)? {
+        config_builder = config_builder
+            .with_near_duplicate_distance(u32::try_from(distance).unwrap_or(u32::MAX));
+    }
+
+    config_builder = match v.get::<String>("min_resolution") {
+        Some(resolution) => {
+            let (width, height) = parse_resolution(&resolution)?;
+            config_builder.with_min_resolution(width, height)
+        }
         None => config_builder,
     };
 
-    config_builder = match v.get::<String>("videos_dst") {
-        Some(dir) => config_builder.with_videos_dst(dir),
+    config_builder = match v.get::<String>("normalize_unicode") {
+        Some(form) => {
+            config_builder.with_unicode_normalization(parse_unicode_normalization(&form)?)
+        }
+        None => config_builder,
+    };
+
+    config_builder =
+        config_builder.with_newest_first(v.get::<bool>("organize_newest_first").unwrap_or(false));
+
+    config_builder = match v.get::<String>("min_free_space") {
+        Some(size) => config_builder
+            .with_min_free_space(parse_size(&size).wrap_err("invalid min free space")?),
+        None => config_builder,
+    };
+
+    if let Some(jobs) = number_option(&v, "jobs", &file_options)? {
+        config_builder = config_builder.with_jobs(usize::try_from(jobs).unwrap_or(0));
+    }
+
+    if let Some(retries) = number_option(&v, "retries", &file_options)? {
+        config_builder = config_builder
+            .with_retries(u32::try_from(retries).wrap_err("retries can't be negative")?);
+    }
+
+    config_builder = match v.get::<String>("retry_delay") {
+        Some(delay) => {
+            config_builder.with_retry_delay(parse_duration(&delay).wrap_err("invalid retry delay")?)
+        }
         None => config_builder,
     };
 
+    match (
+        v.get::<bool>("copy").unwrap_or(false),
+        v.get::<bool>("hardlink").unwrap_or(false),
+    ) {
+        (true, true) => bail!("only one of copy or hardlink can be set"),
+        (true, false) => config_builder = config_builder.with_transfer_mode(TransferMode::Copy),
+        (false, true) => config_builder = config_builder.with_transfer_mode(TransferMode::Hardlink),
+        (false, false) => (),
+    }
+
+    config_builder = config_builder.with_dry_run(v.get::<bool>("dry_run").unwrap_or(false));
+    config_builder = config_builder.with_interactive(v.get::<bool>("interactive").unwrap_or(false));
+
+    config_builder = config_builder
+        .with_extract_motion_video(v.get::<bool>("extract_motion_video").unwrap_or(false));
+
     config_builder.build()
 }
 
@@ -83,35 +704,118 @@ where
 /// the what's specified in the configuration file.
 #[derive(Debug)]
 pub struct Config {
-    pub media_src: PathBuf,
+    pub media_src: Vec<PathBuf>,
     pub photos_dst: PathBuf,
     pub videos_dst: PathBuf,
+    pub audio_dst: Option<PathBuf>,
+    pub misc_dst: Option<PathBuf>,
+    pub flat_dst: Option<PathBuf>,
+    pub destination_overrides: HashMap<String, PathBuf>,
+    pub folder_format: Option<String>,
+    pub photo_extensions: Option<Vec<String>>,
+    pub photo_filename_regex: Option<Regex>,
+    pub video_filename_regex: Option<Regex>,
+    pub exif_date_tags: Option<Vec<ExifDateTag>>,
+    pub date_sources: Option<Vec<DateSource>>,
+    pub takeout_sidecar: Option<TakeoutSidecar>,
+    pub exif_timezone: Option<ExifTimezone>,
+    pub locale: Locale,
+    pub respect_date_folders: bool,
+    pub keep_live_photos: bool,
+    pub flatten: bool,
+    pub preserve_structure: bool,
+    pub group_by_location: bool,
+    pub group_by_city: bool,
+    pub group_by_camera: bool,
+    pub group_bursts: bool,
+    pub fix_orientation: bool,
+    pub video_group_by_month: bool,
+    pub use_mtime_fallback: bool,
+    pub unknown_date_dir: Option<PathBuf>,
+    pub rename_by_date: bool,
+    pub resolution_tiers: Option<Vec<ResolutionTier>>,
+    pub unknown_placeholder: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+    pub exclude: Vec<Pattern>,
+    pub extract_motion_video: bool,
+    pub dry_run: bool,
+    pub interactive: bool,
+    pub unicode_normalization: Option<UnicodeNormalization>,
+    pub transfer_mode: TransferMode,
+    pub conflict_strategy: ConflictStrategy,
+    pub dedup: bool,
+    pub trash_duplicates: bool,
+    pub normalize_extension: bool,
+    pub sanitize_paths: bool,
+    pub near_duplicate_distance: Option<u32>,
+    pub min_resolution: Option<(u32, u32)>,
+    pub verbosity: u8,
+    pub prune_empty: bool,
+    pub no_recurse: bool,
+    pub skip_empty: bool,
+    pub quiet: bool,
+    pub verify: bool,
+    pub watch: bool,
+    pub preview: bool,
+    /// File where the plan is written instead of organizing.
+    pub plan: Option<PathBuf>,
+    /// File with the plan to organize the files by.
+    pub apply: Option<PathBuf>,
+    pub journal: Option<PathBuf>,
+    pub report_json: Option<PathBuf>,
+    pub log_file: Option<PathBuf>,
+    pub manifest: Option<PathBuf>,
+    pub undo: bool,
+    pub find_duplicates: bool,
+    pub newest_first: bool,
+    pub min_free_space: Option<u64>,
+    pub jobs: usize,
+    pub retries: u32,
+    pub retry_delay: Duration,
 }
 
 impl Config {
-    /// Creates a new Config object. It validates that the given path point
-    /// to existing directories and that at least one of photos_dst_str or
-    /// videos_dst_str are not empty.
+    /// Starts building a config for the given media sources without going
+    /// through the command line, the config file and the environment.
     ///
     /// # Examples
     ///
     /// ```
-    /// let valid_dir = PathBuf::from(file!()).parent().unwrap().to_string();
-    /// let config = Config::new(valid_dir, valid_dir, valid_dir);
-    /// assert!(config.is_ok());
+    /// use the_media_organizer::config::Config;
+    ///
+    /// let media_src = tempfile::tempdir()?;
+    /// let photos_dst = tempfile::tempdir()?;
+    /// let config = Config::builder(vec![media_src.path().to_str().unwrap().to_owned()])
+    ///     .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+    ///     .with_dry_run(true)
+    ///     .build()?;
+    /// assert!(config.dry_run);
+    /// # Ok::<(), color_eyre::Report>(())
     /// ```
+    pub fn builder(media_src: Vec<String>) -> ConfigBuilder {
+        ConfigBuilder::new(media_src)
+    }
+
+    /// Creates a new Config object. It validates that the given paths point
+    /// to existing directories and that at least one of photos_dst_str,
+    /// videos_dst_str or audio_dst_str are not empty.
     fn new(
-        media_src_str: String,
+        media_src_strs: Vec<String>,
         photos_dst_str: String,
         videos_dst_str: String,
+        audio_dst_str: String,
+        misc_dst_str: String,
     ) -> Result<Config> {
-        let media_src = PathBuf::from(media_src_str);
-        if !media_src.is_dir() {
-            bail!("media source dir doesn't exist");
+        let media_src: Vec<PathBuf> = media_src_strs.into_iter().map(PathBuf::from).collect();
+        if let Some(dir) = media_src.iter().find(|dir| !dir.is_dir()) {
+            bail!("media source dir {:?} doesn't exist", dir);
         }
 
-        if photos_dst_str.is_empty() && videos_dst_str.is_empty() {
-            bail!("at least one of photos_dst or videos_dst shouldn't be empty");
+        if photos_dst_str.is_empty() && videos_dst_str.is_empty() && audio_dst_str.is_empty() {
+            bail!("at least one of photos_dst, videos_dst or audio_dst shouldn't be empty");
         }
 
         let photos_dst = if !photos_dst_str.is_empty() {
@@ -134,190 +838,2751 @@ impl Config {
             PathBuf::new()
         };
 
+        let audio_dst = if !audio_dst_str.is_empty() {
+            let path = PathBuf::from(audio_dst_str);
+            if !path.is_dir() {
+                bail!("audio destination dir doesn't exist");
+            }
+            Some(path)
+        } else {
+            None
+        };
+
+        let misc_dst = if !misc_dst_str.is_empty() {
+            let path = PathBuf::from(misc_dst_str);
+            if !path.is_dir() {
+                bail!("misc destination dir doesn't exist");
+            }
+            Some(path)
+        } else {
+            None
+        };
+
+        // Organizing into a directory inside the source would find the
+        // moved files again.
+        let dsts = [
+            Some(&photos_dst),
+            Some(&videos_dst),
+            audio_dst.as_ref(),
+            misc_dst.as_ref(),
+        ];
+        for dst in dsts.into_iter().flatten() {
+            if dst.as_os_str().is_empty() {
+                continue;
+            }
+            let dst = dst.canonicalize()?;
+            for src in &media_src {
+                if dst.starts_with(src.canonicalize()?) {
+                    bail!(
+                        "destination dir {:?} can't be inside the media source dir {:?}",
+                        dst,
+                        src
+                    );
+                }
+            }
+        }
+
+        // Absolute, so the logs and the destinations don't depend on the
+        // working directory.
+        let media_src = media_src
+            .into_iter()
+            .map(absolute_path)
+            .collect::<Result<Vec<_>>>()?;
+        let photos_dst = absolute_path(photos_dst)?;
+        let videos_dst = absolute_path(videos_dst)?;
+        let audio_dst = audio_dst.map(absolute_path).transpose()?;
+        let misc_dst = misc_dst.map(absolute_path).transpose()?;
+
         Ok(Config {
             media_src,
             photos_dst,
             videos_dst,
+            audio_dst,
+            misc_dst,
+            flat_dst: None,
+            destination_overrides: HashMap::new(),
+            folder_format: None,
+            photo_extensions: None,
+            photo_filename_regex: None,
+            video_filename_regex: None,
+            exif_date_tags: None,
+            date_sources: None,
+            takeout_sidecar: None,
+            exif_timezone: None,
+            locale: Locale::En,
+            respect_date_folders: false,
+            keep_live_photos: false,
+            flatten: false,
+            preserve_structure: false,
+            group_by_location: false,
+            group_by_city: false,
+            group_by_camera: false,
+            group_bursts: false,
+            fix_orientation: false,
+            video_group_by_month: false,
+            use_mtime_fallback: false,
+            unknown_date_dir: None,
+            rename_by_date: false,
+            resolution_tiers: None,
+            unknown_placeholder: None,
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+            exclude: Vec::new(),
+            extract_motion_video: false,
+            dry_run: false,
+            interactive: false,
+            unicode_normalization: None,
+            transfer_mode: TransferMode::Move,
+            conflict_strategy: ConflictStrategy::Error,
+            dedup: false,
+            trash_duplicates: false,
+            normalize_extension: false,
+            sanitize_paths: false,
+            near_duplicate_distance: None,
+            min_resolution: None,
+            verbosity: 0,
+            prune_empty: false,
+            no_recurse: false,
+            skip_empty: false,
+            quiet: false,
+            verify: false,
+            watch: false,
+            preview: false,
+            plan: None,
+            apply: None,
+            journal: None,
+            report_json: None,
+            log_file: None,
+            manifest: None,
+            undo: false,
+            find_duplicates: false,
+            newest_first: false,
+            min_free_space: None,
+            jobs: 1,
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
         })
     }
 }
 
-struct ConfigBuilder {
-    media_src_str: String,
+/// Builds a [`Config`] from code, see [`Config::builder`]. It runs the
+/// same validations as [`get_config`].
+pub struct ConfigBuilder {
+    media_src_strs: Vec<String>,
     photos_dst_str: String,
     videos_dst_str: String,
+    audio_dst_str: String,
+    misc_dst_str: String,
+    flat_dst_str: String,
+    destination_overrides: HashMap<String, String>,
+    folder_format: Option<String>,
+    photo_extensions: Option<Vec<String>>,
+    photo_filename_regex: Option<Regex>,
+    video_filename_regex: Option<Regex>,
+    exif_date_tags: Option<Vec<ExifDateTag>>,
+    date_sources: Option<Vec<DateSource>>,
+    takeout_sidecar: Option<TakeoutSidecar>,
+    exif_timezone: Option<ExifTimezone>,
+    locale: Locale,
+    respect_date_folders: bool,
+    keep_live_photos: bool,
+    flatten: bool,
+    preserve_structure: bool,
+    group_by_location: bool,
+    group_by_city: bool,
+    group_by_camera: bool,
+    group_bursts: bool,
+    fix_orientation: bool,
+    video_group_by_month: bool,
+    use_mtime_fallback: bool,
+    unknown_date: bool,
+    unknown_date_dir: Option<PathBuf>,
+    rename_by_date: bool,
+    resolution_tiers: Option<Vec<ResolutionTier>>,
+    unknown_placeholder: Option<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    exclude: Vec<Pattern>,
+    extract_motion_video: bool,
+    dry_run: bool,
+    interactive: bool,
+    unicode_normalization: Option<UnicodeNormalization>,
+    transfer_mode: TransferMode,
+    conflict_strategy: ConflictStrategy,
+    dedup: bool,
+    trash_duplicates: bool,
+    normalize_extension: bool,
+    sanitize_paths: bool,
+    near_duplicate_distance: Option<u32>,
+    min_resolution: Option<(u32, u32)>,
+    verbosity: u8,
+    prune_empty: bool,
+    no_recurse: bool,
+    skip_empty: bool,
+    quiet: bool,
+    verify: bool,
+    watch: bool,
+    preview: bool,
+    plan: Option<PathBuf>,
+    apply: Option<PathBuf>,
+    create_dirs: bool,
+    journal: Option<PathBuf>,
+    report_json: Option<PathBuf>,
+    log_file: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+    undo: bool,
+    find_duplicates: bool,
+    newest_first: bool,
+    min_free_space: Option<u64>,
+    jobs: Option<usize>,
+    retries: u32,
+    retry_delay: Duration,
 }
 
 impl ConfigBuilder {
-    fn new(media_src_str: String) -> ConfigBuilder {
+    pub fn new(media_src_strs: Vec<String>) -> ConfigBuilder {
         ConfigBuilder {
-            media_src_str,
+            media_src_strs,
             photos_dst_str: "".to_owned(),
             videos_dst_str: "".to_owned(),
+            audio_dst_str: "".to_owned(),
+            misc_dst_str: "".to_owned(),
+            flat_dst_str: "".to_owned(),
+            destination_overrides: HashMap::new(),
+            folder_format: None,
+            photo_extensions: None,
+            photo_filename_regex: None,
+            video_filename_regex: None,
+            exif_date_tags: None,
+            date_sources: None,
+            takeout_sidecar: None,
+            exif_timezone: None,
+            locale: Locale::En,
+            respect_date_folders: false,
+            keep_live_photos: false,
+            flatten: false,
+            preserve_structure: false,
+            group_by_location: false,
+            group_by_city: false,
+            group_by_camera: false,
+            group_bursts: false,
+            fix_orientation: false,
+            video_group_by_month: false,
+            use_mtime_fallback: false,
+            unknown_date: false,
+            unknown_date_dir: None,
+            rename_by_date: false,
+            resolution_tiers: None,
+            unknown_placeholder: None,
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+            exclude: Vec::new(),
+            extract_motion_video: false,
+            dry_run: false,
+            interactive: false,
+            unicode_normalization: None,
+            transfer_mode: TransferMode::Move,
+            conflict_strategy: ConflictStrategy::Error,
+            dedup: false,
+            trash_duplicates: false,
+            normalize_extension: false,
+            sanitize_paths: false,
+            near_duplicate_distance: None,
+            min_resolution: None,
+            verbosity: 0,
+            prune_empty: false,
+            no_recurse: false,
+            skip_empty: false,
+            quiet: false,
+            verify: false,
+            watch: false,
+            preview: false,
+            plan: None,
+            apply: None,
+            create_dirs: false,
+            journal: None,
+            report_json: None,
+            log_file: None,
+            manifest: None,
+            undo: false,
+            find_duplicates: false,
+            newest_first: false,
+            min_free_space: None,
+            jobs: None,
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
         }
     }
 
-    fn with_photos_dst(mut self, photos_dst_str: String) -> ConfigBuilder {
+    pub fn with_photos_dst(mut self, photos_dst_str: String) -> ConfigBuilder {
         self.photos_dst_str = photos_dst_str;
         self
     }
 
-    fn with_videos_dst(mut self, videos_dst_str: String) -> ConfigBuilder {
+    pub fn with_videos_dst(mut self, videos_dst_str: String) -> ConfigBuilder {
         self.videos_dst_str = videos_dst_str;
         self
     }
 
-    fn build(self) -> Result<Config> {
-        Config::new(self.media_src_str, self.photos_dst_str, self.videos_dst_str)
+    pub fn with_audio_dst(mut self, audio_dst_str: String) -> ConfigBuilder {
+        self.audio_dst_str = audio_dst_str;
+        self
     }
-}
-
-fn get_default_config_file() -> Option<String> {
-    let config_dir = match ProjectDirs::from("dev", "adn", "media-organizer")
-        .map(|dirs: ProjectDirs| dirs.config_dir().to_owned())
-    {
-        Some(config_dir) => config_dir,
-        None => return None,
-    };
 
-    if !config_dir.is_dir() {
-        return None;
+    pub fn with_misc_dst(mut self, misc_dst_str: String) -> ConfigBuilder {
+        self.misc_dst_str = misc_dst_str;
+        self
     }
 
-    let config_file = config_dir.join("config.toml");
-
-    if !config_file.is_file() {
-        return None;
+    pub fn with_flat_dst(mut self, flat_dst_str: String) -> ConfigBuilder {
+        self.flat_dst_str = flat_dst_str;
+        self
     }
 
-    config_file.to_str().map(|s| s.to_owned())
-}
+    /// Directories by extension, with or without the dot, that replace
+    /// the destination of the files with it.
+    pub fn with_destination_overrides(
+        mut self,
+        destination_overrides: HashMap<String, String>,
+    ) -> ConfigBuilder {
+        self.destination_overrides = destination_overrides;
+        self
+    }
 
-fn load_claps<I, T>(v: &mut Viperus, cmd_args: I) -> Result<bool>
-where
-    I: IntoIterator<Item = T>,
-    T: Into<OsString> + Clone,
-{
-    let matches = clap::App::new("media-organizer")
-        .arg(
-            clap::Arg::with_name("config_file")
-                .short("c")
-                .long("config-file")
-                .value_name("FILE")
-                .long_help(
-                    "\
-File to load configuration from. Defaults to:
-- Linux: /home/ainara/.config/media-organizer/config.toml
-- Windows: C:\\Users\\Ainara\\AppData\\Roaming\\adn\\media-organizer\\config\\config.toml
-- Mac: /Users/Ainara/Library/Application Support/dev.adn.media-organizer/config.toml",
-                )
-                .takes_value(true),
-        )
-        .arg(
-            clap::Arg::with_name("media_src")
-                .short("m")
-                .long("media-src")
-                .value_name("DIRECTORY")
-                .help("Source directory with media files to organize")
-                .takes_value(true),
-        )
-        .arg(
-            clap::Arg::with_name("photos_dst")
-                .short("p")
-                .long("photos-dst")
-                .value_name("DIRECTORY")
-                .help("Directory where photos will be moved and organized")
-                .takes_value(true),
-        )
-        .arg(
-            clap::Arg::with_name("videos_dst")
-                .short("v")
-                .long("videos-dst")
-                .value_name("DIRECTORY")
-                .help("Directory where videos will be moved and organized")
-                .takes_value(true),
-        )
-        .arg(
-            clap::Arg::with_name("no_load_default_config_file")
-                .long("no-load-default-config-file")
-                .help("Do not load the config file from the default location"),
-        )
-        .get_matches_from(cmd_args);
+    pub fn with_folder_format(mut self, folder_format: String) -> ConfigBuilder {
+        self.folder_format = Some(folder_format);
+        self
+    }
 
-    let no_load_default_config = matches.is_present("no_load_default_config_file");
-    if let Err(e) = v.load_clap(matches) {
-        bail!("{}", e);
+    pub fn with_photo_extensions(mut self, extensions: Vec<String>) -> ConfigBuilder {
+        self.photo_extensions = Some(extensions);
+        self
     }
-    Ok(!no_load_default_config)
-}
 
-#[cfg(test)]
-mod tests {
+    pub fn with_photo_filename_regex(mut self, regex: Regex) -> ConfigBuilder {
+        self.photo_filename_regex = Some(regex);
+        self
+    }
 
-    use super::*;
-    use std::fs;
-    use tempfile::tempdir;
+    pub fn with_video_filename_regex(mut self, regex: Regex) -> ConfigBuilder {
+        self.video_filename_regex = Some(regex);
+        self
+    }
 
-    #[test]
-    fn load_config_from_file() {
-        let config_file_dir = tempdir().unwrap();
-        let config_file_path = config_file_dir.path().join("config.toml");
-        let photos_dst = tempdir().unwrap();
-        let videos_dst = tempdir().unwrap();
-        let media_src = tempdir().unwrap();
+    pub fn with_exif_date_tags(mut self, tags: Vec<ExifDateTag>) -> ConfigBuilder {
+        self.exif_date_tags = Some(tags);
+        self
+    }
 
-        fs::write(
-            &config_file_path,
-            format!(
-                "photos_dst='{}'\nmedia_src='{}'\nvideos_dst='{}'",
-                photos_dst.path().to_str().unwrap(),
-                media_src.path().to_str().unwrap(),
-                videos_dst.path().to_str().unwrap(),
-            ),
-        )
-        .unwrap();
-        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
-        assert_eq!(config.media_src, media_src.path());
-        assert_eq!(config.photos_dst, photos_dst.path());
-        assert_eq!(config.videos_dst, videos_dst.path());
+    pub fn with_date_sources(mut self, sources: Vec<DateSource>) -> ConfigBuilder {
+        self.date_sources = Some(sources);
+        self
     }
 
-    #[test]
-    fn load_config_from_cmd_line_args() {
-        let photos_dst = tempdir().unwrap();
-        let videos_dst = tempdir().unwrap();
-        let media_src = tempdir().unwrap();
+    pub fn with_takeout_sidecar(mut self, takeout_sidecar: TakeoutSidecar) -> ConfigBuilder {
+        self.takeout_sidecar = Some(takeout_sidecar);
+        self
+    }
 
-        let config = get_config(vec![
-            "self",
-            "-m",
-            media_src.path().to_str().unwrap(),
-            "-p",
-            photos_dst.path().to_str().unwrap(),
-            "-v",
-            videos_dst.path().to_str().unwrap(),
-        ])
-        .unwrap();
-        assert_eq!(config.media_src, media_src.path());
-        assert_eq!(config.photos_dst, photos_dst.path());
-        assert_eq!(config.videos_dst, videos_dst.path());
+    pub fn with_exif_timezone(mut self, exif_timezone: ExifTimezone) -> ConfigBuilder {
+        self.exif_timezone = Some(exif_timezone);
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> ConfigBuilder {
+        self.locale = locale;
+        self
+    }
+
+    pub fn with_respect_date_folders(mut self, respect_date_folders: bool) -> ConfigBuilder {
+        self.respect_date_folders = respect_date_folders;
+        self
+    }
+
+    pub fn with_keep_live_photos(mut self, keep_live_photos: bool) -> ConfigBuilder {
+        self.keep_live_photos = keep_live_photos;
+        self
+    }
+
+    pub fn with_flatten(mut self, flatten: bool) -> ConfigBuilder {
+        self.flatten = flatten;
+        self
+    }
+
+    pub fn with_preserve_structure(mut self, preserve_structure: bool) -> ConfigBuilder {
+        self.preserve_structure = preserve_structure;
+        self
+    }
+
+    pub fn with_group_by_location(mut self, group_by_location: bool) -> ConfigBuilder {
+        self.group_by_location = group_by_location;
+        self
+    }
+
+    pub fn with_group_by_city(mut self, group_by_city: bool) -> ConfigBuilder {
+        self.group_by_city = group_by_city;
+        self
+    }
+
+    pub fn with_group_by_camera(mut self, group_by_camera: bool) -> ConfigBuilder {
+        self.group_by_camera = group_by_camera;
+        self
+    }
+
+    pub fn with_group_bursts(mut self, group_bursts: bool) -> ConfigBuilder {
+        self.group_bursts = group_bursts;
+        self
+    }
+
+    pub fn with_fix_orientation(mut self, fix_orientation: bool) -> ConfigBuilder {
+        self.fix_orientation = fix_orientation;
+        self
+    }
+
+    pub fn with_video_group_by_month(mut self, video_group_by_month: bool) -> ConfigBuilder {
+        self.video_group_by_month = video_group_by_month;
+        self
+    }
+
+    pub fn with_use_mtime_fallback(mut self, use_mtime_fallback: bool) -> ConfigBuilder {
+        self.use_mtime_fallback = use_mtime_fallback;
+        self
+    }
+
+    pub fn with_unknown_date(mut self, unknown_date: bool) -> ConfigBuilder {
+        self.unknown_date = unknown_date;
+        self
+    }
+
+    pub fn with_unknown_date_dir(mut self, unknown_date_dir: PathBuf) -> ConfigBuilder {
+        self.unknown_date_dir = Some(unknown_date_dir);
+        self
+    }
+
+    pub fn with_rename_by_date(mut self, rename_by_date: bool) -> ConfigBuilder {
+        self.rename_by_date = rename_by_date;
+        self
+    }
+
+    pub fn with_resolution_tiers(mut self, tiers: Vec<ResolutionTier>) -> ConfigBuilder {
+        self.resolution_tiers = Some(tiers);
+        self
+    }
+
+    pub fn with_unknown_placeholder(mut self, unknown_placeholder: String) -> ConfigBuilder {
+        self.unknown_placeholder = Some(unknown_placeholder);
+        self
+    }
+
+    pub fn with_min_size(mut self, min_size: u64) -> ConfigBuilder {
+        self.min_size = Some(min_size);
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> ConfigBuilder {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    pub fn with_modified_after(mut self, time: SystemTime) -> ConfigBuilder {
+        self.modified_after = Some(time);
+        self
+    }
+
+    pub fn with_modified_before(mut self, time: SystemTime) -> ConfigBuilder {
+        self.modified_before = Some(time);
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: Vec<Pattern>) -> ConfigBuilder {
+        self.exclude = exclude;
+        self
+    }
+
+    pub fn with_extract_motion_video(mut self, extract_motion_video: bool) -> ConfigBuilder {
+        self.extract_motion_video = extract_motion_video;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> ConfigBuilder {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_interactive(mut self, interactive: bool) -> ConfigBuilder {
+        self.interactive = interactive;
+        self
+    }
+
+    pub fn with_unicode_normalization(mut self, form: UnicodeNormalization) -> ConfigBuilder {
+        self.unicode_normalization = Some(form);
+        self
+    }
+
+    pub fn with_transfer_mode(mut self, transfer_mode: TransferMode) -> ConfigBuilder {
+        self.transfer_mode = transfer_mode;
+        self
+    }
+
+    pub fn with_conflict_strategy(mut self, conflict_strategy: ConflictStrategy) -> ConfigBuilder {
+        self.conflict_strategy = conflict_strategy;
+        self
+    }
+
+    pub fn with_journal(mut self, journal: PathBuf) -> ConfigBuilder {
+        self.journal = Some(journal);
+        self
+    }
+
+    pub fn with_report_json(mut self, report_json: PathBuf) -> ConfigBuilder {
+        self.report_json = Some(report_json);
+        self
+    }
+
+    pub fn with_log_file(mut self, log_file: PathBuf) -> ConfigBuilder {
+        self.log_file = Some(log_file);
+        self
+    }
+
+    pub fn with_manifest(mut self, manifest: PathBuf) -> ConfigBuilder {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    pub fn with_undo(mut self, undo: bool) -> ConfigBuilder {
+        self.undo = undo;
+        self
+    }
+
+    pub fn with_find_duplicates(mut self, find_duplicates: bool) -> ConfigBuilder {
+        self.find_duplicates = find_duplicates;
+        self
+    }
+
+    pub fn with_prune_empty(mut self, prune_empty: bool) -> ConfigBuilder {
+        self.prune_empty = prune_empty;
+        self
+    }
+
+    pub fn with_no_recurse(mut self, no_recurse: bool) -> ConfigBuilder {
+        self.no_recurse = no_recurse;
+        self
+    }
+
+    pub fn with_skip_empty(mut self, skip_empty: bool) -> ConfigBuilder {
+        self.skip_empty = skip_empty;
+        self
+    }
+
+    pub fn with_quiet(mut self, quiet: bool) -> ConfigBuilder {
+        self.quiet = quiet;
+        self
+    }
+
+    pub fn with_verify(mut self, verify: bool) -> ConfigBuilder {
+        self.verify = verify;
+        self
+    }
+
+    pub fn with_watch(mut self, watch: bool) -> ConfigBuilder {
+        self.watch = watch;
+        self
+    }
+
+    pub fn with_preview(mut self, preview: bool) -> ConfigBuilder {
+        self.preview = preview;
+        self
+    }
+
+    pub fn with_plan(mut self, plan: PathBuf) -> ConfigBuilder {
+        self.plan = Some(plan);
+        self
+    }
+
+    pub fn with_apply(mut self, apply: PathBuf) -> ConfigBuilder {
+        self.apply = Some(apply);
+        self
+    }
+
+    pub fn with_create_dirs(mut self, create_dirs: bool) -> ConfigBuilder {
+        self.create_dirs = create_dirs;
+        self
+    }
+
+    pub fn with_verbosity(mut self, verbosity: u8) -> ConfigBuilder {
+        self.verbosity = verbosity;
+        self
+    }
+
+    pub fn with_dedup(mut self, dedup: bool) -> ConfigBuilder {
+        self.dedup = dedup;
+        self
+    }
+
+    pub fn with_trash_duplicates(mut self, trash_duplicates: bool) -> ConfigBuilder {
+        self.trash_duplicates = trash_duplicates;
+        self
+    }
+
+    pub fn with_normalize_extension(mut self, normalize_extension: bool) -> ConfigBuilder {
+        self.normalize_extension = normalize_extension;
+        self
+    }
+
+    pub fn with_sanitize_paths(mut self, sanitize_paths: bool) -> ConfigBuilder {
+        self.sanitize_paths = sanitize_paths;
+        self
+    }
+
+    pub fn with_near_duplicate_distance(mut self, distance: u32) -> ConfigBuilder {
+        self.near_duplicate_distance = Some(distance);
+        self
+    }
+
+    pub fn with_min_resolution(mut self, width: u32, height: u32) -> ConfigBuilder {
+        self.min_resolution = Some((width, height));
+        self
+    }
+
+    pub fn with_newest_first(mut self, newest_first: bool) -> ConfigBuilder {
+        self.newest_first = newest_first;
+        self
+    }
+
+    pub fn with_min_free_space(mut self, min_free_space: u64) -> ConfigBuilder {
+        self.min_free_space = Some(min_free_space);
+        self
+    }
+
+    pub fn with_jobs(mut self, jobs: usize) -> ConfigBuilder {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> ConfigBuilder {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> ConfigBuilder {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.media_src_strs.is_empty() {
+            bail!("media source is required");
+        }
+        if self.jobs == Some(0) {
+            bail!("jobs should be at least 1");
+        }
+        if self
+            .near_duplicate_distance
+            .is_some_and(|distance| distance > 64)
+        {
+            bail!("near duplicate distance should be between 0 and 64");
+        }
+        if let Some(folder_format) = &self.folder_format {
+            if folder_format.split('/').all(|dir| dir.trim().is_empty()) {
+                bail!("folder format can't be empty");
+            }
+        }
+        if let Some(dir) = &self.unknown_date_dir {
+            let name = dir.to_string_lossy();
+            if name.trim().is_empty() || name == "." || name == ".." {
+                bail!("unknown date dir can't be empty");
+            }
+            if name.contains(std::path::is_separator) {
+                bail!("unknown date dir should be a folder name, without path separators");
+            }
+        }
+        if self.flatten && self.respect_date_folders {
+            bail!("flatten and respect date folders can't be used together");
+        }
+        if self.interactive && self.dry_run {
+            bail!("interactive and dry run can't be used together");
+        }
+        if self.extract_motion_video && self.videos_dst_str.is_empty() {
+            bail!("extracting motion videos requires videos_dst");
+        }
+
+        if self.create_dirs {
+            for dst in [
+                &self.photos_dst_str,
+                &self.videos_dst_str,
+                &self.audio_dst_str,
+                &self.misc_dst_str,
+            ]
+            .into_iter()
+            .chain(self.destination_overrides.values())
+            {
+                if !dst.is_empty() {
+                    fs::create_dir_all(dst)
+                        .wrap_err_with(|| format!("failed to create destination dir {}", dst))?;
+                }
+            }
+        }
+
+        if let (Some(min_size), Some(max_size)) = (self.min_size, self.max_size) {
+            if min_size > max_size {
+                bail!("min size can't be bigger than max size");
+            }
+        }
+
+        let flat_dst = if !self.flat_dst_str.is_empty() {
+            let path = PathBuf::from(self.flat_dst_str);
+            if !path.is_dir() {
+                bail!("flat destination dir doesn't exist");
+            }
+            Some(absolute_path(path)?)
+        } else {
+            None
+        };
+        let mut destination_overrides = HashMap::new();
+        for (extension, dir) in self.destination_overrides {
+            let extension = extension.trim().trim_start_matches('.').to_lowercase();
+            if extension.is_empty() {
+                bail!("destination override extension can't be empty");
+            }
+            let path = PathBuf::from(dir);
+            if !path.is_dir() {
+                bail!("destination override dir {:?} doesn't exist", path);
+            }
+            destination_overrides.insert(extension, absolute_path(path)?);
+        }
+        let unknown_date_dir = match self.unknown_date_dir {
+            Some(dir) => Some(dir),
+            None if self.unknown_date => Some(PathBuf::from(DEFAULT_UNKNOWN_DATE_DIR)),
+            None => None,
+        };
+
+        let unknown_placeholder = match &self.unknown_placeholder {
+            Some(placeholder) => {
+                let placeholder = parse_unknown_placeholder(placeholder)?;
+                let tiers = self.resolution_tiers.iter().flatten();
+                if tiers
+                    .map(|tier| &tier.name)
+                    .any(|name| *name == placeholder)
+                {
+                    bail!(
+                        "unknown placeholder {:?} is the name of a resolution tier",
+                        placeholder
+                    );
+                }
+                Some(placeholder)
+            }
+            None => None,
+        };
+
+        Ok(Config {
+            flat_dst,
+            destination_overrides,
+            folder_format: self.folder_format,
+            photo_extensions: self.photo_extensions,
+            photo_filename_regex: self.photo_filename_regex,
+            video_filename_regex: self.video_filename_regex,
+            exif_date_tags: self.exif_date_tags,
+            date_sources: self.date_sources,
+            takeout_sidecar: self.takeout_sidecar,
+            exif_timezone: self.exif_timezone,
+            locale: self.locale,
+            respect_date_folders: self.respect_date_folders,
+            keep_live_photos: self.keep_live_photos,
+            flatten: self.flatten,
+            preserve_structure: self.preserve_structure,
+            group_by_location: self.group_by_location,
+            group_by_city: self.group_by_city,
+            group_by_camera: self.group_by_camera,
+            group_bursts: self.group_bursts,
+            fix_orientation: self.fix_orientation,
+            video_group_by_month: self.video_group_by_month,
+            use_mtime_fallback: self.use_mtime_fallback,
+            unknown_date_dir,
+            rename_by_date: self.rename_by_date,
+            resolution_tiers: self.resolution_tiers,
+            unknown_placeholder,
+            min_size: self.min_size,
+            max_size: self.max_size,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+            exclude: self.exclude,
+            extract_motion_video: self.extract_motion_video,
+            dry_run: self.dry_run,
+            interactive: self.interactive,
+            unicode_normalization: self.unicode_normalization,
+            transfer_mode: self.transfer_mode,
+            conflict_strategy: self.conflict_strategy,
+            dedup: self.dedup,
+            trash_duplicates: self.trash_duplicates,
+            normalize_extension: self.normalize_extension,
+            sanitize_paths: self.sanitize_paths,
+            near_duplicate_distance: self.near_duplicate_distance,
+            min_resolution: self.min_resolution,
+            verbosity: self.verbosity,
+            prune_empty: self.prune_empty,
+            no_recurse: self.no_recurse,
+            skip_empty: self.skip_empty,
+            quiet: self.quiet,
+            verify: self.verify,
+            watch: self.watch,
+            preview: self.preview,
+            plan: self.plan.map(absolute_path).transpose()?,
+            apply: self.apply.map(absolute_path).transpose()?,
+            journal: self.journal.map(absolute_path).transpose()?,
+            report_json: self.report_json.map(absolute_path).transpose()?,
+            log_file: self.log_file.map(absolute_path).transpose()?,
+            manifest: self.manifest.map(absolute_path).transpose()?,
+            undo: self.undo,
+            find_duplicates: self.find_duplicates,
+            newest_first: self.newest_first,
+            min_free_space: self.min_free_space,
+            // Answering the prompts of several files at once would be
+            // confusing, so interactive runs default to one job. Several
+            // jobs only keep the newest first order within each
+            // destination dir.
+            jobs: self.jobs.unwrap_or_else(|| {
+                if self.interactive || self.newest_first {
+                    1
+                } else {
+                    thread::available_parallelism().map_or(1, |jobs| jobs.get())
+                }
+            }),
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+            ..Config::new(
+                self.media_src_strs,
+                self.photos_dst_str,
+                self.videos_dst_str,
+                self.audio_dst_str,
+                self.misc_dst_str,
+            )?
+        })
+    }
+}
+
+/// Parses a size in bytes. The suffixes K, M and G (case insensitive
+/// and optionally followed by B) multiply the value by powers of 1024.
+/// For example `20K` is 20480 bytes.
+fn parse_size(size: &str) -> Result<u64> {
+    let size = size.trim().to_uppercase();
+    let size = size.strip_suffix('B').unwrap_or(&size);
+    let (number, multiplier) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1024),
+        Some('M') => (&size[..size.len() - 1], 1024 * 1024),
+        Some('G') => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    let number: u64 = number
+        .trim()
+        .parse()
+        .wrap_err_with(|| format!("'{}' is not a valid size", size))?;
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| eyre!("'{}' is too big", size))
+}
+
+/// Parses a resolution given as `WxH`, like `640x480`.
+fn parse_resolution(resolution: &str) -> Result<(u32, u32)> {
+    let invalid = || eyre!("'{}' is not a valid resolution, e.g. 640x480", resolution);
+    let (width, height) = resolution
+        .trim()
+        .to_lowercase()
+        .split_once('x')
+        .map(|(width, height)| (width.trim().parse(), height.trim().parse()))
+        .ok_or_else(invalid)?;
+    Ok((
+        width.map_err(|_| invalid())?,
+        height.map_err(|_| invalid())?,
+    ))
+}
+
+/// The path made absolute against the working directory, or the empty
+/// path of a disabled destination.
+fn absolute_path(path: PathBuf) -> Result<PathBuf> {
+    if path.as_os_str().is_empty() {
+        return Ok(path);
+    }
+    std::path::absolute(&path).wrap_err_with(|| format!("failed to resolve path {:?}", path))
+}
+
+/// Expands a leading `~` to the home directory and `$VAR` or `${VAR}` to
+/// the value of the environment variable, failing when it isn't set.
+fn expand_path(path: &str, env_vars: &[(String, String)]) -> Result<String> {
+    let var = |name: &str| {
+        env_vars
+            .iter()
+            .find(|(var_name, _)| var_name == name)
+            .map(|(_, value)| value)
+    };
+    shellexpand::full_with_context(
+        path,
+        || var("HOME"),
+        |name| var(name).map(Some).ok_or(env::VarError::NotPresent),
+    )
+    .map(|path| path.into_owned())
+    .wrap_err_with(|| format!("failed to expand path '{}'", path))
+}
+
+/// Parses a duration, in seconds with the suffix s or in milliseconds
+/// with ms. For example `500ms` or `2s`.
+fn parse_duration(duration: &str) -> Result<Duration> {
+    let duration = duration.trim().to_lowercase();
+    let (number, millis) = if let Some(number) = duration.strip_suffix("ms") {
+        (number, 1)
+    } else if let Some(number) = duration.strip_suffix('s') {
+        (number, 1000)
+    } else {
+        bail!("'{}' should end with ms or s", duration);
+    };
+    let number: u64 = number
+        .trim()
+        .parse()
+        .wrap_err_with(|| format!("'{}' is not a valid duration", duration))?;
+    let millis = number
+        .checked_mul(millis)
+        .ok_or_else(|| eyre!("'{}' is too long", duration))?;
+    Ok(Duration::from_millis(millis))
+}
+
+/// Parses an RFC3339 date, like `2020-04-07T14:30:00+02:00`.
+fn parse_time(time: &str) -> Result<SystemTime> {
+    let time = DateTime::parse_from_rfc3339(time.trim())
+        .wrap_err_with(|| format!("'{}' is not a valid RFC3339 date", time))?;
+    Ok(time.into())
+}
+
+fn parse_locale(locale: &str) -> Result<Locale> {
+    match locale.trim().to_lowercase().as_str() {
+        "en" => Ok(Locale::En),
+        "es" => Ok(Locale::Es),
+        "fr" => Ok(Locale::Fr),
+        "de" => Ok(Locale::De),
+        "pt" => Ok(Locale::Pt),
+        _ => bail!(
+            "invalid locale '{}', should be one of en, es, fr, de or pt",
+            locale
+        ),
+    }
+}
+
+fn parse_unicode_normalization(form: &str) -> Result<UnicodeNormalization> {
+    match form.trim().to_lowercase().as_str() {
+        "nfc" => Ok(UnicodeNormalization::Nfc),
+        "nfd" => Ok(UnicodeNormalization::Nfd),
+        _ => bail!(
+            "invalid unicode normalization '{}', should be nfc or nfd",
+            form
+        ),
+    }
+}
+
+/// Parses comma separated resolution tiers, `name=lines`, for example
+/// `4K=2160,HD=720,SD=0`.
+fn parse_resolution_tiers(tiers: &str) -> Result<Vec<ResolutionTier>> {
+    tiers
+        .split(',')
+        .map(|tier| {
+            let (name, lines) = tier
+                .split_once('=')
+                .ok_or_else(|| eyre!("tier '{}' should be name=lines", tier))?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+                bail!("invalid tier name '{}'", name);
+            }
+            let lines = lines
+                .trim()
+                .parse()
+                .wrap_err_with(|| format!("invalid lines in tier '{}'", tier))?;
+            Ok(ResolutionTier::new(name, lines))
+        })
+        .collect()
+}
+
+/// Replaces the characters of the unknown placeholder that aren't valid
+/// in folder names with `_` and trims the surrounding spaces and dots,
+/// which Windows doesn't allow at the end.
+fn parse_unknown_placeholder(placeholder: &str) -> Result<String> {
+    let placeholder = placeholder
+        .chars()
+        .map(|c| if is_invalid_in_name(c) { '_' } else { c })
+        .collect::<String>()
+        .trim_matches(|c| c == ' ' || c == '.')
+        .to_owned();
+    if placeholder.is_empty() {
+        bail!("unknown placeholder can't be empty");
+    }
+    if is_reserved_name(&placeholder) || placeholder == PhotoOrganizer::BURSTS_DIR {
+        bail!("unknown placeholder {:?} is a reserved name", placeholder);
+    }
+    Ok(placeholder)
+}
+
+/// If Windows doesn't allow the name for files and folders, like `CON`
+/// or `nul.txt`.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    ["CON", "PRN", "AUX", "NUL"]
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+        || ["COM", "LPT"].iter().any(|prefix| {
+            stem.len() == 4
+                && stem.is_char_boundary(3)
+                && stem[..3].eq_ignore_ascii_case(prefix)
+                && matches!(stem.as_bytes()[3], b'1'..=b'9')
+        })
+}
+
+/// Parses comma separated file extensions, with or without the leading
+/// dot. At least one is required.
+fn parse_extensions(extensions: &str) -> Result<Vec<String>> {
+    let extensions: Vec<String> = extensions
+        .split(',')
+        .map(|extension| extension.trim().trim_start_matches('.').to_owned())
+        .filter(|extension| !extension.is_empty())
+        .collect();
+    if extensions.is_empty() {
+        bail!("at least one extension is required");
+    }
+    Ok(extensions)
+}
+
+/// Parses a regex to take dates from file names, which must have the
+/// `year` and `month` named groups.
+fn parse_filename_regex(regex: &str) -> Result<Regex> {
+    let regex = Regex::new(regex)?;
+    for group in ["year", "month"] {
+        if !regex.capture_names().any(|name| name == Some(group)) {
+            bail!("the regex doesn't have a '{}' named group", group);
+        }
+    }
+    Ok(regex)
+}
+
+/// Parses comma separated exif date tags, case insensitive. `CreateDate`
+/// is accepted as `DateTimeDigitized`.
+fn parse_exif_date_tags(tags: &str) -> Result<Vec<ExifDateTag>> {
+    tags.split(',')
+        .map(|tag| match tag.trim().to_lowercase().as_str() {
+            "datetimeoriginal" => Ok(ExifDateTag::DateTimeOriginal),
+            "datetimedigitized" | "createdate" => Ok(ExifDateTag::DateTimeDigitized),
+            "datetime" => Ok(ExifDateTag::DateTime),
+            _ => bail!(
+                "invalid exif date tag '{}', should be DateTimeOriginal, DateTimeDigitized or DateTime",
+                tag
+            ),
+        })
+        .collect()
+}
+
+fn parse_date_sources(sources: &str) -> Result<Vec<DateSource>> {
+    sources
+        .split(',')
+        .map(|source| match source.trim().to_lowercase().as_str() {
+            "exif" => Ok(DateSource::Exif),
+            "filename" => Ok(DateSource::Filename),
+            "sidecar" => Ok(DateSource::Sidecar),
+            "mtime" => Ok(DateSource::Mtime),
+            "birthtime" => Ok(DateSource::Birthtime),
+            _ => bail!(
+                "invalid date source '{}', should be exif, filename, sidecar, mtime or birthtime",
+                source
+            ),
+        })
+        .collect()
+}
+
+fn parse_takeout_sidecar(order: &str) -> Result<TakeoutSidecar> {
+    match order.trim().to_lowercase().as_str() {
+        "first" => Ok(TakeoutSidecar::First),
+        "last" => Ok(TakeoutSidecar::Last),
+        "ignore" => Ok(TakeoutSidecar::Ignore),
+        _ => bail!(
+            "invalid takeout sidecar order '{}', should be first, last or ignore",
+            order
+        ),
+    }
+}
+
+fn parse_exif_timezone(timezone: &str) -> Result<ExifTimezone> {
+    match timezone.trim().to_lowercase().as_str() {
+        "normalize" => Ok(ExifTimezone::Normalize),
+        "ignore" => Ok(ExifTimezone::Ignore),
+        _ => bail!(
+            "invalid exif timezone '{}', should be normalize or ignore",
+            timezone
+        ),
+    }
+}
+
+fn parse_conflict_strategy(strategy: &str) -> Result<ConflictStrategy> {
+    match strategy.trim().to_lowercase().as_str() {
+        "skip" => Ok(ConflictStrategy::Skip),
+        "overwrite" => Ok(ConflictStrategy::Overwrite),
+        "rename" => Ok(ConflictStrategy::Rename),
+        "error" => Ok(ConflictStrategy::Error),
+        _ => bail!(
+            "invalid conflict strategy '{}', should be skip, overwrite, rename or error",
+            strategy
+        ),
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("dev", "adn", "media-organizer")
+        .map(|dirs: ProjectDirs| dirs.config_dir().join("config.toml"))
+}
+
+fn get_default_config_file() -> Option<String> {
+    let config_file = default_config_path()?;
+
+    if !config_file.is_file() {
+        return None;
+    }
+
+    config_file.to_str().map(|s| s.to_owned())
+}
+
+/// If the command line asks for it with `--init-config`, writes a
+/// commented default config file, to the `--config-file` path or the
+/// default location, and returns its path. An existing file is only
+/// overwritten with `--force`.
+pub fn init_config<I, T>(cmd_args: I) -> Result<Option<PathBuf>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = app().get_matches_from(cmd_args);
+    if !matches.is_present("init_config") {
+        return Ok(None);
+    }
+
+    let config_file = match matches.value_of("config_file") {
+        Some(config_file) => PathBuf::from(config_file),
+        None => default_config_path().ok_or_else(|| eyre!("failed to find the config dir"))?,
+    };
+    write_default_config(&config_file, matches.is_present("force"))?;
+    Ok(Some(config_file))
+}
+
+fn write_default_config(config_file: &Path, force: bool) -> Result<()> {
+    if config_file.exists() && !force {
+        bail!(
+            "config file {:?} already exists, use --force to overwrite it",
+            config_file
+        );
+    }
+    if let Some(config_dir) = config_file.parent() {
+        fs::create_dir_all(config_dir).wrap_err("failed to create the config dir")?;
+    }
+    fs::write(config_file, DEFAULT_CONFIG)
+        .wrap_err_with(|| format!("failed to write config file {:?}", config_file))
+}
+
+/// Commented config file written by [`init_config`].
+const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+fn get_default_journal_file() -> Option<String> {
+    let data_dir = ProjectDirs::from("dev", "adn", "media-organizer")
+        .map(|dirs: ProjectDirs| dirs.data_dir().to_owned())?;
+
+    data_dir.join("journal.tsv").to_str().map(|s| s.to_owned())
+}
+
+/// Format of a config file, taken from its extension. Files with
+/// other extensions are read as TOML.
+fn config_file_format(config_file: &str) -> Format {
+    let extension = Path::new(config_file)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("json") => Format::JSON,
+        Some("yaml") | Some("yml") => Format::YAML,
+        _ => Format::TOML,
+    }
+}
+
+/// Prefix of the environment variables options are taken from.
+const ENV_PREFIX: &str = "MEDIA_ORGANIZER_";
+
+/// Folder the files without a date are moved to with `--unknown-date`.
+const DEFAULT_UNKNOWN_DATE_DIR: &str = "unknown-date";
+
+/// Options whose value is a number, viperus panics when it isn't.
+const NUMBERS: [&str; 4] = ["verbose", "near_duplicate_distance", "jobs", "retries"];
+
+/// Options without a value.
+const FLAGS: [&str; 33] = [
+    "extract_motion_video",
+    "dry_run",
+    "interactive",
+    "organize_newest_first",
+    "copy",
+    "hardlink",
+    "respect_date_folders",
+    "keep_live_photos",
+    "flatten",
+    "preserve_structure",
+    "group_by_location",
+    "group_by_city",
+    "group_by_camera",
+    "group_bursts",
+    "fix_orientation",
+    "video_group_by_month",
+    "use_mtime_fallback",
+    "unknown_date",
+    "rename_by_date",
+    "group_by_resolution",
+    "dedup",
+    "trash_duplicates",
+    "normalize_extension",
+    "sanitize_paths",
+    "undo",
+    "find_duplicates",
+    "prune_empty",
+    "no_recurse",
+    "skip_empty",
+    "quiet",
+    "verify",
+    "watch",
+    "create_dirs",
+];
+
+/// Reads the options of a config file.
+fn read_config_file(config_file: &str) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let content = fs::read_to_string(config_file)
+        .wrap_err_with(|| format!("failed to read config file '{}'", config_file))?;
+    let config: serde_json::Value = match config_file_format(config_file) {
+        Format::JSON => serde_json::from_str(&content)?,
+        Format::YAML => serde_yaml::from_str(&content)?,
+        _ => toml::from_str(&content)?,
+    };
+    match config {
+        serde_json::Value::Object(options) => Ok(options),
+        _ => bail!("config file '{}' should be a map of options", config_file),
+    }
+}
+
+/// Directories of the `[destination_overrides]` section of a config
+/// file, by extension.
+fn destination_overrides(
+    file_options: &serde_json::Map<String, serde_json::Value>,
+) -> Result<HashMap<String, String>> {
+    let overrides = match file_options.get("destination_overrides") {
+        Some(serde_json::Value::Object(overrides)) => overrides,
+        Some(_) => bail!("destination_overrides should be a map of extensions to dirs"),
+        None => return Ok(HashMap::new()),
+    };
+    overrides
+        .iter()
+        .map(|(extension, dir)| match dir.as_str() {
+            Some(dir) => Ok((extension.clone(), dir.to_owned())),
+            None => bail!("destination override of '{}' should be a dir", extension),
+        })
+        .collect()
+}
+
+/// Options of the `[profiles.<name>]` section of a config file.
+fn select_profile(
+    file_options: &serde_json::Map<String, serde_json::Value>,
+    name: &str,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let profiles = match file_options.get("profiles") {
+        Some(serde_json::Value::Object(profiles)) => profiles,
+        _ => bail!(
+            "profile '{}' not found, the config file has no profiles",
+            name
+        ),
+    };
+    match profiles.get(name) {
+        Some(serde_json::Value::Object(profile)) => Ok(profile.clone()),
+        Some(_) => bail!("profile '{}' should be a map of options", name),
+        None => bail!(
+            "profile '{}' not found, the available profiles are: {}",
+            name,
+            profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Loads the options of a profile in viperus, over the ones of the
+/// config file. Arrays are skipped like viperus does with the file, they
+/// are read by [`list_option`].
+struct ProfileAdapter(serde_json::Map<String, serde_json::Value>);
+
+impl ConfigAdapter for ProfileAdapter {
+    fn parse(&mut self) -> AdapterResult<()> {
+        Ok(())
+    }
+
+    fn get_map(&self) -> Map {
+        let mut map = Map::new();
+        for (key, value) in &self.0 {
+            match value {
+                serde_json::Value::String(value) => {
+                    map.add(key, value.clone());
+                }
+                serde_json::Value::Bool(value) => {
+                    map.add(key, *value);
+                }
+                serde_json::Value::Number(value) => {
+                    if let Some(value) = value.as_i64().and_then(|n| i32::try_from(n).ok()) {
+                        map.add(key, value);
+                    }
+                }
+                _ => (),
+            }
+        }
+        map
+    }
+}
+
+/// What the command line tells besides the options loaded in viperus.
+struct CmdLine {
+    load_default_config_file: bool,
+    media_srcs: Vec<String>,
+    excludes: Vec<String>,
+    preview: bool,
+    /// File of the plan subcommand.
+    plan: Option<String>,
+    /// File of the apply subcommand.
+    apply: Option<String>,
+    /// Options set with an environment variable.
+    env_keys: Vec<String>,
+}
+
+/// Value of a number option. The strings of the config file are parsed
+/// here, reading one that isn't a number makes viperus panic. The
+/// environment variables are parsed by [`load_claps`] and the command
+/// line arguments by clap.
+fn number_option(
+    v: &Viperus,
+    key: &str,
+    file_options: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Option<i32>> {
+    if let Some(serde_json::Value::String(value)) = file_options.get(key) {
+        value
+            .parse::<i32>()
+            .wrap_err_with(|| format!("invalid number in {}", key))?;
+    }
+    Ok(v.get::<i32>(key))
+}
+
+/// Values of an option that can be repeated. Viperus only knows the
+/// first of the repeated arguments and skips the arrays of the config
+/// file, so they are read here.
+fn list_option(
+    cmd_values: Vec<String>,
+    v: &Viperus,
+    key: &str,
+    file_options: &serde_json::Map<String, serde_json::Value>,
+    env_keys: &[String],
+) -> Result<Vec<String>> {
+    if !cmd_values.is_empty() {
+        return Ok(cmd_values);
+    }
+    let file_values = match file_options.get(key) {
+        Some(serde_json::Value::Array(values)) => Some(
+            values
+                .iter()
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(str::to_owned)
+                        .ok_or_else(|| eyre!("{} should be a list of strings", key))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        _ => None,
+    };
+    // The array of a profile replaces the value of the rest of the file,
+    // which viperus still has.
+    if let Some(value) = v.get::<String>(key) {
+        if file_values.is_none() || env_keys.iter().any(|env_key| env_key == key) {
+            return Ok(vec![value]);
+        }
+    }
+    Ok(file_values.unwrap_or_default())
+}
+
+fn load_claps<I, T, E>(v: &mut Viperus, cmd_args: I, env_vars: E) -> Result<CmdLine>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+    E: IntoIterator<Item = (String, String)>,
+{
+    let matches = app().get_matches_from(cmd_args);
+
+    let no_load_default_config = matches.is_present("no_load_default_config_file");
+    let values = |name| {
+        matches
+            .values_of(name)
+            .map(|values| values.map(str::to_owned).collect())
+            .unwrap_or_default()
+    };
+    let media_srcs = values("media_src");
+    let excludes = values("exclude");
+    let preview = matches.subcommand_name() == Some("preview");
+    let subcommand_file = |name| {
+        matches
+            .subcommand_matches(name)
+            .and_then(|matches| matches.value_of("file"))
+            .map(str::to_owned)
+    };
+    let plan = subcommand_file("plan");
+    let apply = subcommand_file("apply");
+    // Environment variables are added as overrides, which viperus
+    // prefers over the config file, unless the command line has them.
+    let mut env_keys = Vec::new();
+    for (name, value) in env_vars {
+        let key = match name.strip_prefix(ENV_PREFIX) {
+            Some(key) => key.to_lowercase(),
+            None => continue,
+        };
+        if matches.is_present(&key) {
+            continue;
+        }
+        if FLAGS.contains(&key.as_str()) {
+            let value: bool = value
+                .parse()
+                .wrap_err_with(|| format!("invalid boolean in {}", name))?;
+            v.add(&key, value);
+        } else if NUMBERS.contains(&key.as_str()) {
+            let value: i32 = value
+                .parse()
+                .wrap_err_with(|| format!("invalid number in {}", name))?;
+            v.add(&key, value);
+        } else {
+            v.add(&key, value);
+        }
+        env_keys.push(key);
+    }
+
+    // Flags have no value, viperus only sees them when they are added
+    // explicitly.
+    for flag in FLAGS {
+        if matches.is_present(flag) {
+            v.add(flag, true);
+        }
+    }
+    let verbose = matches.occurrences_of("verbose");
+    if verbose > 0 {
+        v.add("verbose", verbose as i32);
+    }
+    if let Err(e) = v.load_clap(matches) {
+        bail!("{}", e);
+    }
+    Ok(CmdLine {
+        load_default_config_file: !no_load_default_config,
+        media_srcs,
+        excludes,
+        preview,
+        plan,
+        apply,
+        env_keys,
+    })
+}
+
+/// Command line arguments.
+fn app() -> clap::App<'static, 'static> {
+    clap::App::new("media-organizer")
+        .arg(
+            clap::Arg::with_name("config_file")
+                .short("c")
+                .long("config-file")
+                .value_name("FILE")
+                .long_help(
+                    "\
+File to load configuration from. Defaults to:
+- Linux: /home/ainara/.config/media-organizer/config.toml
+- Windows: C:\\Users\\Ainara\\AppData\\Roaming\\adn\\media-organizer\\config\\config.toml
+- Mac: /Users/Ainara/Library/Application Support/dev.adn.media-organizer/config.toml",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Profile of the config file merged over the rest of it")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("media_src")
+                .short("m")
+                .long("media-src")
+                .value_name("DIRECTORY")
+                .help("Source directory with media files to organize, can be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            clap::Arg::with_name("photos_dst")
+                .short("p")
+                .long("photos-dst")
+                .value_name("DIRECTORY")
+                .help("Directory where photos will be moved and organized")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("videos_dst")
+                .short("v")
+                .long("videos-dst")
+                .value_name("DIRECTORY")
+                .help("Directory where videos will be moved and organized")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("audio_dst")
+                .short("a")
+                .long("audio-dst")
+                .value_name("DIRECTORY")
+                .help("Directory where audio files will be moved and organized")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("misc_dst")
+                .long("misc-dst")
+                .value_name("DIRECTORY")
+                .help("Directory where the files that no other organizer handles are moved")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("folder_format")
+                .long("folder-format")
+                .value_name("TEMPLATE")
+                .help("Template of the photos directory structure, defaults to '%Y/%m - %B'")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("photo_extensions")
+                .long("photo-extensions")
+                .value_name("EXTENSIONS")
+                .help("Comma separated extensions of the photos, defaults to 'jpeg,jpg,png,cr2,nef,arw,dng'")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("photo_filename_regex")
+                .long("photo-filename-regex")
+                .value_name("REGEX")
+                .help("Regex with year and month named groups to take the date from photo names")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("video_filename_regex")
+                .long("video-filename-regex")
+                .value_name("REGEX")
+                .help("Regex with year and month named groups to take the date from video names")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("exif_date_tags")
+                .long("exif-date-tags")
+                .value_name("TAGS")
+                .help("Exif tags the photo date is taken from, defaults to 'DateTimeOriginal,DateTimeDigitized,DateTime'")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("date_source_order")
+                .long("date-source-order")
+                .value_name("SOURCES")
+                .help("Sources the photo date is taken from, in order, defaults to 'exif,filename,sidecar'")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("takeout_sidecar")
+                .long("takeout-sidecar")
+                .value_name("ORDER")
+                .possible_values(&["first", "last", "ignore"])
+                .help("When the date of Google Takeout JSON sidecars is used, defaults to first")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("exif_timezone")
+                .long("exif-timezone")
+                .value_name("MODE")
+                .possible_values(&["normalize", "ignore"])
+                .help("If the exif dates are converted to UTC with their offset tag, defaults to ignore")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("locale")
+                .long("locale")
+                .value_name("LOCALE")
+                .possible_values(&["en", "es", "fr", "de", "pt"])
+                .help("Language of the month names")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("respect_date_folders")
+                .long("respect-date-folders")
+                .help("Keep source folders named like a date, e.g. 2019-07-trip, together"),
+        )
+        .arg(
+            clap::Arg::with_name("keep_live_photos")
+                .long("keep-live-photos")
+                .help("Organize the video of Live Photos next to their photo"),
+        )
+        .arg(
+            clap::Arg::with_name("flatten")
+                .long("flatten")
+                .conflicts_with("respect_date_folders")
+                .help("Give every file a name of its own in its destination, whatever its source folder"),
+        )
+        .arg(
+            clap::Arg::with_name("preserve_structure")
+                .long("preserve-structure")
+                .help("Keep the source folders of the files under their date folder, e.g. 2019/01 - January/Trip/day1"),
+        )
+        .arg(
+            clap::Arg::with_name("group_by_location")
+                .long("group-by-location")
+                .help(
+                    "Group the photos by the country of their GPS tags, e.g. 2020/04 - April/Spain",
+                ),
+        )
+        .arg(
+            clap::Arg::with_name("group_by_city")
+                .long("group-by-city")
+                .help("Group the photos by the city nearest to their GPS tags, e.g. 2020/04 - April/Madrid"),
+        )
+        .arg(
+            clap::Arg::with_name("group_by_camera")
+                .long("group-by-camera")
+                .help("Group the photos by the camera model of their exif, e.g. 2020/04 - April/Canon EOS R"),
+        )
+        .arg(
+            clap::Arg::with_name("group_bursts")
+                .long("group-bursts")
+                .help("Group the shots of bursts inside the date, e.g. 2020/04 - April/Bursts/IMG_0001"),
+        )
+        .arg(
+            clap::Arg::with_name("fix_orientation")
+                .long("fix-orientation")
+                .help("Rotate JPEGs to the orientation of their exif and reset it"),
+        )
+        .arg(
+            clap::Arg::with_name("video_group_by_month")
+                .long("video-group-by-month")
+                .help("Organize the videos by month inside the year, e.g. 2020/08 - August"),
+        )
+        .arg(
+            clap::Arg::with_name("use_mtime_fallback")
+                .long("use-mtime-fallback")
+                .help("Organize photos, videos and audio without a date in the metadata or the name by their modification time"),
+        )
+        .arg(
+            clap::Arg::with_name("unknown_date")
+                .long("unknown-date")
+                .help("Move the files without a date to the unknown date dir of their destination"),
+        )
+        .arg(
+            clap::Arg::with_name("unknown_date_dir")
+                .long("unknown-date-dir")
+                .value_name("NAME")
+                .help("Folder of each destination where the files without a date are moved, defaults to unknown-date")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("rename_by_date")
+                .long("rename-by-date")
+                .help("Prefix the name of photos and videos with their YYYYMM date"),
+        )
+        .arg(
+            clap::Arg::with_name("group_by_resolution")
+                .long("group-by-resolution")
+                .help("Group the videos inside the year by resolution, e.g. 2020/4K/"),
+        )
+        .arg(
+            clap::Arg::with_name("resolution_tiers")
+                .long("resolution-tiers")
+                .value_name("TIERS")
+                .help("Resolution tiers, defaults to '4K=2160,HD=720,SD=0'")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("unknown_placeholder")
+                .long("unknown-placeholder")
+                .value_name("NAME")
+                .help("Folder of the files without what they are grouped by, like a camera or resolution")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("also_flat")
+                .long("also-flat")
+                .value_name("DIRECTORY")
+                .help("Directory where a link to every organized file is created")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("min_size")
+                .long("min-size")
+                .value_name("SIZE")
+                .help("Files smaller than this are not organized, e.g. 20K, 5M")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("max_size")
+                .long("max-size")
+                .value_name("SIZE")
+                .help("Files bigger than this are not organized, e.g. 20K, 5M")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("modified_after")
+                .long("modified-after")
+                .value_name("RFC3339")
+                .help("Only files modified after this date are organized")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("modified_before")
+                .long("modified-before")
+                .value_name("RFC3339")
+                .help("Only files modified before this date are organized")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("exclude")
+                .long("exclude")
+                .value_name("PATTERN")
+                .help("Glob pattern of the files and directories that are not organized, can be repeated")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            clap::Arg::with_name("extract_motion_video")
+                .long("extract-motion-video")
+                .help("Extract the video embedded in motion photos into the videos destination"),
+        )
+        .arg(
+            clap::Arg::with_name("copy")
+                .long("copy")
+                .help("Copy the files instead of moving them"),
+        )
+        .arg(
+            clap::Arg::with_name("hardlink")
+                .long("hardlink")
+                .conflicts_with("copy")
+                .help("Hardlink the files instead of moving them, copying them if it fails"),
+        )
+        .arg(
+            clap::Arg::with_name("on_conflict")
+                .long("on-conflict")
+                .value_name("STRATEGY")
+                .possible_values(&["skip", "overwrite", "rename", "error"])
+                .help("What to do when a file with the same name exists in the destination")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("journal")
+                .long("journal")
+                .value_name("FILE")
+                .help("File where the moves are recorded to be undone")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("log_file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("File where the warnings and errors are also logged, and the details of --verbose")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("report_json")
+                .long("report-json")
+                .value_name("FILE")
+                .help("File where a JSON report of every file organized is written")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .help("File where a CSV inventory of the moved files is written")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("undo")
+                .long("undo")
+                .help("Move back the files recorded in the journal instead of organizing"),
+        )
+        .arg(
+            clap::Arg::with_name("find_duplicates")
+                .long("find-duplicates")
+                .help("Print the groups of files of the media source with the same content instead of organizing"),
+        )
+        .arg(
+            clap::Arg::with_name("prune_empty")
+                .long("prune-empty")
+                .help("Remove the directories of the media source left empty"),
+        )
+        .arg(
+            clap::Arg::with_name("no_recurse")
+                .long("no-recurse")
+                .help("Only organize the files directly in the media source, not its subdirectories"),
+        )
+        .arg(
+            clap::Arg::with_name("skip_empty")
+                .long("skip-empty")
+                .help("Leave the empty files in the media source"),
+        )
+        .arg(
+            clap::Arg::with_name("quiet")
+                .long("quiet")
+                .short("q")
+                .help("Only log warnings and errors, and show no progress"),
+        )
+        .arg(
+            clap::Arg::with_name("verify")
+                .long("verify")
+                .help("Check that every file has the same content after transferring it"),
+        )
+        .arg(
+            clap::Arg::with_name("watch")
+                .long("watch")
+                .help("Keep running and organize the files that appear in the media source"),
+        )
+        .arg(
+            clap::Arg::with_name("create_dirs")
+                .long("create-dirs")
+                .help("Create the destination directories that do not exist"),
+        )
+        .arg(
+            clap::Arg::with_name("verbose")
+                .long("verbose")
+                .multiple(true)
+                .help("Log more details, can be repeated"),
+        )
+        .arg(
+            clap::Arg::with_name("dedup")
+                .long("dedup")
+                .help("Remove files identical to the one with the same name in the destination"),
+        )
+        .arg(
+            clap::Arg::with_name("trash_duplicates")
+                .long("trash-duplicates")
+                .help("Send files whose name is taken by a different file in the destination to the trash"),
+        )
+        .arg(
+            clap::Arg::with_name("normalize_extension")
+                .long("normalize-extension")
+                .help("Lowercase the extension of the destination file names"),
+        )
+        .arg(
+            clap::Arg::with_name("sanitize_paths")
+                .long("sanitize-paths")
+                .help("Replace the characters FAT and exFAT don't allow in the destination names"),
+        )
+        .arg(
+            clap::Arg::with_name("near_duplicate_distance")
+                .long("near-duplicate-distance")
+                .value_name("BITS")
+                .help("Move photos similar to one in the destination to duplicates-review")
+                .validator(|distance| match distance.parse::<i32>() {
+                    Ok(distance) if (0..=64).contains(&distance) => Ok(()),
+                    _ => Err("should be a number between 0 and 64".to_owned()),
+                })
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("min_resolution")
+                .long("min-resolution")
+                .value_name("WxH")
+                .help("Skip the photos smaller than the given resolution, e.g. 640x480")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("normalize_unicode")
+                .long("normalize-unicode")
+                .value_name("FORM")
+                .possible_values(&["nfc", "nfd"])
+                .help("Normalize the destination file names to the given unicode form")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("organize_newest_first")
+                .long("organize-newest-first")
+                .help("Organize the most recently modified files first"),
+        )
+        .arg(
+            clap::Arg::with_name("jobs")
+                .long("jobs")
+                .value_name("N")
+                .help("Number of files organized at the same time, defaults to the number of CPUs")
+                .validator(|jobs| match jobs.parse::<i32>() {
+                    Ok(jobs) if jobs >= 1 => Ok(()),
+                    _ => Err("should be a number bigger than 0".to_owned()),
+                })
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("retries")
+                .long("retries")
+                .value_name("N")
+                .help("Times a transfer failing with a transient IO error is retried, defaults to 3")
+                .validator(|retries| match retries.parse::<u32>() {
+                    Ok(_) => Ok(()),
+                    _ => Err("should be a positive number".to_owned()),
+                })
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("retry_delay")
+                .long("retry-delay")
+                .value_name("DURATION")
+                .help("Wait before the first retry, doubled for the next ones, e.g. 500ms or 2s")
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("min_free_space")
+                .long("min-free-space")
+                .value_name("SIZE")
+                .help(
+                    "Abort when a move would leave less than this free in the destination, e.g. 5G",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            clap::Arg::with_name("dry_run")
+                .long("dry-run")
+                .help("Print what would be moved without moving anything"),
+        )
+        .arg(
+            clap::Arg::with_name("interactive")
+                .long("interactive")
+                .conflicts_with("dry_run")
+                .help("Ask before transferring each file"),
+        )
+        .arg(
+            clap::Arg::with_name("no_load_default_config_file")
+                .long("no-load-default-config-file")
+                .help("Do not load the config file from the default location"),
+        )
+        .arg(
+            clap::Arg::with_name("init_config")
+                .long("init-config")
+                .help("Write a commented default config file, to --config-file or the default location, and exit"),
+        )
+        .arg(
+            clap::Arg::with_name("force")
+                .long("force")
+                .requires("init_config")
+                .help("Overwrite the config file written by --init-config"),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("preview")
+                .about("Print where each file would be organized without organizing it"),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("plan")
+                .about("Write where each file would be organized to a JSON file without organizing it")
+                .arg(
+                    clap::Arg::with_name("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("File where the plan is written"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("apply")
+                .about("Organize the files as written by plan")
+                .arg(
+                    clap::Arg::with_name("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("File with the plan"),
+                ),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn load_config_from_file() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let photos_dst = tempdir().unwrap();
+        let videos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                "photos_dst='{}'\nmedia_src='{}'\nvideos_dst='{}'",
+                photos_dst.path().to_str().unwrap(),
+                media_src.path().to_str().unwrap(),
+                videos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, photos_dst.path());
+        assert_eq!(config.videos_dst, videos_dst.path());
+    }
+
+    #[test]
+    fn load_config_from_json_file() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.json");
+        let photos_dst = tempdir().unwrap();
+        let videos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                r#"{{"photos_dst": "{}", "media_src": "{}", "videos_dst": "{}"}}"#,
+                photos_dst.path().to_str().unwrap(),
+                media_src.path().to_str().unwrap(),
+                videos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, photos_dst.path());
+        assert_eq!(config.videos_dst, videos_dst.path());
+    }
+
+    #[test]
+    fn load_config_from_yaml_file() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.yml");
+        let photos_dst = tempdir().unwrap();
+        let videos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                "photos_dst: '{}'\nmedia_src: '{}'\nvideos_dst: '{}'\n",
+                photos_dst.path().to_str().unwrap(),
+                media_src.path().to_str().unwrap(),
+                videos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, photos_dst.path());
+        assert_eq!(config.videos_dst, videos_dst.path());
+    }
+
+    #[test]
+    fn load_config_from_yaml_file_with_anchors() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.yaml");
+        let media_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                "media_src: '{}'\nphotos_dst: &media_dst '{}'\nvideos_dst: *media_dst\n",
+                media_src.path().to_str().unwrap(),
+                media_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, media_dst.path());
+        assert_eq!(config.videos_dst, media_dst.path());
+    }
+
+    #[test]
+    fn load_config_from_env() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let media_src = tempdir().unwrap();
+        let file_photos_dst = tempdir().unwrap();
+        let env_photos_dst = tempdir().unwrap();
+        let env_videos_dst = tempdir().unwrap();
+        let cmd_videos_dst = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                "media_src='{}'\nphotos_dst='{}'\nvideos_dst='{}'",
+                media_src.path().to_str().unwrap(),
+                file_photos_dst.path().to_str().unwrap(),
+                file_photos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let env_vars = vec![
+            (
+                "MEDIA_ORGANIZER_PHOTOS_DST".to_owned(),
+                env_photos_dst.path().to_str().unwrap().to_owned(),
+            ),
+            (
+                "MEDIA_ORGANIZER_VIDEOS_DST".to_owned(),
+                env_videos_dst.path().to_str().unwrap().to_owned(),
+            ),
+            ("MEDIA_ORGANIZER_DRY_RUN".to_owned(), "true".to_owned()),
+            ("PHOTOS_DST".to_owned(), "/ignored".to_owned()),
+        ];
+        let config = get_config_with_env(
+            vec![
+                "self",
+                "-c",
+                config_file_path.to_str().unwrap(),
+                "-v",
+                cmd_videos_dst.path().to_str().unwrap(),
+            ],
+            env_vars,
+        )
+        .unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, env_photos_dst.path());
+        assert_eq!(config.videos_dst, cmd_videos_dst.path());
+        assert!(config.dry_run);
+
+        let env_vars = vec![("MEDIA_ORGANIZER_DRY_RUN".to_owned(), "yes".to_owned())];
+        assert!(get_config_with_env(
+            vec!["self", "-c", config_file_path.to_str().unwrap()],
+            env_vars
+        )
+        .is_err());
+
+        let env_vars = vec![("MEDIA_ORGANIZER_JOBS".to_owned(), "abc".to_owned())];
+        assert!(get_config_with_env(
+            vec!["self", "-c", config_file_path.to_str().unwrap()],
+            env_vars
+        )
+        .is_err());
+        let env_vars = vec![("MEDIA_ORGANIZER_JOBS".to_owned(), "3".to_owned())];
+        let config = get_config_with_env(
+            vec!["self", "-c", config_file_path.to_str().unwrap()],
+            env_vars,
+        )
+        .unwrap();
+        assert_eq!(config.jobs, 3);
+    }
+
+    #[test]
+    fn load_expanded_paths() {
+        let home = tempdir().unwrap();
+        let media = tempdir().unwrap();
+        fs::create_dir(home.path().join("sub")).unwrap();
+        fs::create_dir(media.path().join("src")).unwrap();
+        let config_file_path = home.path().join("config.toml");
+        fs::write(&config_file_path, "videos_dst = '~/sub'").unwrap();
+        let env_vars = vec![
+            ("HOME".to_owned(), home.path().to_str().unwrap().to_owned()),
+            (
+                "MEDIA".to_owned(),
+                media.path().to_str().unwrap().to_owned(),
+            ),
+        ];
+
+        let config = get_config_with_env(
+            vec![
+                "self",
+                "-c",
+                "~/config.toml",
+                "-m",
+                "${MEDIA}/src",
+                "-p",
+                "~/sub",
+                "--journal",
+                "$MEDIA/journal.log",
+            ],
+            env_vars.clone(),
+        )
+        .unwrap();
+        assert_eq!(config.media_src, vec![media.path().join("src")]);
+        assert_eq!(config.photos_dst, home.path().join("sub"));
+        assert_eq!(config.videos_dst, home.path().join("sub"));
+        assert_eq!(config.journal, Some(media.path().join("journal.log")));
+
+        let err = get_config_with_env(vec!["self", "-m", "$UNSET/src", "-p", "~/sub"], env_vars)
+            .unwrap_err();
+        assert_eq!("failed to expand path '$UNSET/src'", err.to_string());
+    }
+
+    #[test]
+    fn load_config_from_cmd_line_args() {
+        let photos_dst = tempdir().unwrap();
+        let videos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "-v",
+            videos_dst.path().to_str().unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, photos_dst.path());
+        assert_eq!(config.videos_dst, videos_dst.path());
+    }
+
+    #[test]
+    fn missing_both_videos_and_photos_err() {
+        let media_src = tempdir().unwrap();
+        let err = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            "at least one of photos_dst, videos_dst or audio_dst shouldn't be empty",
+            err.to_string(),
+        )
+    }
+
+    #[test]
+    fn init_default_config() {
+        let config_dir = tempdir().unwrap();
+        let config_file = config_dir
+            .path()
+            .join("media-organizer")
+            .join("config.toml");
+        let args = vec!["self", "--init-config", "-c", config_file.to_str().unwrap()];
+
+        assert_eq!(
+            Some(config_file.clone()),
+            init_config(args.clone()).unwrap()
+        );
+        let content = fs::read_to_string(&config_file).unwrap();
+        assert!(content.parse::<toml::Value>().is_ok());
+        assert!(content.contains("# media_src = '/media-to-sort'"));
+
+        fs::write(&config_file, "media_src = '/mine'").unwrap();
+        assert!(init_config(args.clone()).is_err());
+        assert_eq!(
+            "media_src = '/mine'",
+            fs::read_to_string(&config_file).unwrap()
+        );
+
+        let mut args = args;
+        args.push("--force");
+        init_config(args).unwrap();
+        assert_eq!(content, fs::read_to_string(&config_file).unwrap());
+
+        assert_eq!(None, init_config(vec!["self", "-m", "/media"]).unwrap());
+    }
+
+    #[test]
+    fn load_multiple_media_srcs() {
+        let sd_card = tempdir().unwrap();
+        let backup = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            sd_card.path().to_str().unwrap(),
+            "--media-src",
+            backup.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.media_src, vec![sd_card.path(), backup.path()]);
+
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        fs::write(
+            &config_file_path,
+            format!(
+                "media_src=['{}', '{}']\nphotos_dst='{}'",
+                sd_card.path().to_str().unwrap(),
+                backup.path().to_str().unwrap(),
+                photos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(config.media_src, vec![sd_card.path(), backup.path()]);
+
+        let err = get_config(vec![
+            "self",
+            "-m",
+            sd_card.path().to_str().unwrap(),
+            "-m",
+            "/does/not/exist",
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap_err();
+        assert_eq!(
+            "media source dir \"/does/not/exist\" doesn't exist",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn destination_inside_source_err() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = media_src.path().join("photos");
+        fs::create_dir(&photos_dst).unwrap();
+
+        let err = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can't be inside the media source dir"));
+
+        assert!(get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-v",
+            media_src.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn destination_next_to_source() {
+        let dir = tempdir().unwrap();
+        let media_src = dir.path().join("media");
+        let photos_dst = dir.path().join("media-photos");
+        fs::create_dir(&media_src).unwrap();
+        fs::create_dir(&photos_dst).unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.to_str().unwrap(),
+            "-p",
+            photos_dst.to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.photos_dst, photos_dst);
+    }
+
+    #[test]
+    fn load_exclude() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+
+        fs::write(
+            &config_file_path,
+            format!(
+                "media_src='{}'\nphotos_dst='{}'\nexclude=['*.tmp', 'Trash']",
+                media_src.path().to_str().unwrap(),
+                photos_dst.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config = get_config(vec!["self", "-c", config_file_path.to_str().unwrap()]).unwrap();
+        assert_eq!(
+            vec!["*.tmp", "Trash"],
+            config
+                .exclude
+                .iter()
+                .map(|pattern| pattern.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(config.exclude[0].matches("foo.tmp"));
+
+        let config = get_config(vec![
+            "self",
+            "-c",
+            config_file_path.to_str().unwrap(),
+            "--exclude",
+            "*.bak",
+        ])
+        .unwrap();
+        assert_eq!(1, config.exclude.len());
+        assert!(config.exclude[0].matches("foo.bak"));
+
+        assert!(get_config(vec![
+            "self",
+            "-c",
+            config_file_path.to_str().unwrap(),
+            "--exclude",
+            "[",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn create_missing_destination_dirs() {
+        let media_src = tempdir().unwrap();
+        let dst = tempdir().unwrap();
+        let photos_dst = dst.path().join("media").join("photos");
+        let args = vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.to_str().unwrap(),
+            "--no-load-default-config-file",
+        ];
+
+        assert!(get_config(args.clone()).is_err());
+        assert!(!photos_dst.exists());
+
+        let mut args = args;
+        args.push("--create-dirs");
+        let config = get_config(args).unwrap();
+        assert!(photos_dst.is_dir());
+        assert_eq!(config.photos_dst, photos_dst);
+    }
+
+    #[test]
+    fn load_misc_dst() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+        let misc_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--misc-dst",
+            misc_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.misc_dst.unwrap(), misc_dst.path());
+    }
+
+    #[test]
+    fn build_config_from_code() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+        let media_src_str = media_src.path().to_str().unwrap().to_owned();
+
+        let config = Config::builder(vec![media_src_str.clone()])
+            .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+            .with_conflict_strategy(ConflictStrategy::Rename)
+            .with_jobs(4)
+            .build()
+            .unwrap();
+        assert_eq!(config.media_src, vec![media_src.path()]);
+        assert_eq!(config.photos_dst, photos_dst.path());
+        assert_eq!(config.conflict_strategy, ConflictStrategy::Rename);
+        assert_eq!(config.jobs, 4);
+
+        let config = Config::builder(vec![media_src_str.clone()])
+            .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+            .build()
+            .unwrap();
+        assert_eq!(config.jobs, thread::available_parallelism().unwrap().get());
+        let config = Config::builder(vec![media_src_str.clone()])
+            .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+            .with_interactive(true)
+            .build()
+            .unwrap();
+        assert_eq!(config.jobs, 1);
+        let config = Config::builder(vec![media_src_str.clone()])
+            .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+            .with_newest_first(true)
+            .build()
+            .unwrap();
+        assert_eq!(config.jobs, 1);
+
+        let err = Config::builder(vec![media_src_str.clone()])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            "at least one of photos_dst, videos_dst or audio_dst shouldn't be empty",
+            err.to_string()
+        );
+        let err = Config::builder(vec![media_src_str])
+            .with_photos_dst(photos_dst.path().to_str().unwrap().to_owned())
+            .with_jobs(0)
+            .build()
+            .unwrap_err();
+        assert_eq!("jobs should be at least 1", err.to_string());
+        let err = Config::builder(Vec::new()).build().unwrap_err();
+        assert_eq!("media source is required", err.to_string());
+    }
+
+    #[test]
+    fn load_profiles() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let media_src = tempdir().unwrap();
+        let alice_photos = tempdir().unwrap();
+        let bob_photos = tempdir().unwrap();
+        let bob_src = tempdir().unwrap();
+        fs::write(
+            &config_file_path,
+            format!(
+                "media_src='{}'\ndry_run=true\n\n\
+                 [profiles.alice]\nphotos_dst='{}'\n\n\
+                 [profiles.bob]\nphotos_dst='{}'\nmedia_src=['{}']\ndry_run=false\n",
+                media_src.path().to_str().unwrap(),
+                alice_photos.path().to_str().unwrap(),
+                bob_photos.path().to_str().unwrap(),
+                bob_src.path().to_str().unwrap(),
+            ),
+        )
+        .unwrap();
+        let config_file = config_file_path.to_str().unwrap();
+
+        let alice = get_config(vec!["self", "-c", config_file, "--profile", "alice"]).unwrap();
+        assert_eq!(alice.photos_dst, alice_photos.path());
+        assert_eq!(alice.media_src, vec![media_src.path()]);
+        assert!(alice.dry_run);
+
+        let bob = get_config(vec!["self", "-c", config_file, "--profile", "bob"]).unwrap();
+        assert_eq!(bob.photos_dst, bob_photos.path());
+        assert_eq!(bob.media_src, vec![bob_src.path()]);
+        assert!(!bob.dry_run);
+
+        let err = get_config(vec!["self", "-c", config_file, "--profile", "carol"]).unwrap_err();
+        assert_eq!(
+            "profile 'carol' not found, the available profiles are: alice, bob",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn load_destination_overrides() {
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+        let screenshots = tempdir().unwrap();
+        let write_config = |overrides: &str| {
+            fs::write(
+                &config_file_path,
+                format!(
+                    "media_src='{}'\nphotos_dst='{}'\n\n[destination_overrides]\n{}\n",
+                    media_src.path().to_str().unwrap(),
+                    photos_dst.path().to_str().unwrap(),
+                    overrides,
+                ),
+            )
+            .unwrap();
+        };
+        let config_file = config_file_path.to_str().unwrap();
+
+        write_config(&format!(
+            "'.PNG'='{}'",
+            screenshots.path().to_str().unwrap()
+        ));
+        let config = get_config(vec!["self", "-c", config_file]).unwrap();
+        assert_eq!(
+            HashMap::from([("png".to_owned(), screenshots.path().to_path_buf())]),
+            config.destination_overrides
+        );
+
+        write_config("png='/missing-screenshots'");
+        let err = get_config(vec!["self", "-c", config_file]).unwrap_err();
+        assert_eq!(
+            "destination override dir \"/missing-screenshots\" doesn't exist",
+            err.to_string()
+        );
+
+        write_config("png=1");
+        assert!(get_config(vec!["self", "-c", config_file]).is_err());
+    }
+
+    #[test]
+    fn load_relative_paths() {
+        let photos_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            "src/fixtures",
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert!(config.media_src[0].is_absolute());
+        assert_eq!(
+            config.media_src,
+            vec![env::current_dir().unwrap().join("src").join("fixtures")]
+        );
+
+        let err = get_config(vec![
+            "self",
+            "-m",
+            "src/fixtures",
+            "-p",
+            "./missing",
+            "--no-load-default-config-file",
+        ])
+        .unwrap_err();
+        assert_eq!("photos destination dir doesn't exist", err.to_string());
     }
 
     #[test]
-    fn missing_both_videos_and_photos_err() {
+    fn load_report_json() {
         let media_src = tempdir().unwrap();
-        let err = get_config(vec![
+        let photos_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
             "self",
             "-m",
             media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--report-json",
+            "report.json",
             "--no-load-default-config-file",
         ])
-        .unwrap_err();
+        .unwrap();
+        assert_eq!(
+            config.report_json.unwrap(),
+            env::current_dir().unwrap().join("report.json")
+        );
+        assert!(config.manifest.is_none());
+    }
+
+    #[test]
+    fn load_log_file() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
 
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--log-file",
+            "organizer.log",
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
         assert_eq!(
-            "at least one of photos_dst or videos_dst shouldn't be empty",
-            err.to_string(),
+            config.log_file.unwrap(),
+            env::current_dir().unwrap().join("organizer.log")
+        );
+    }
+
+    #[test]
+    fn interactive_with_dry_run_err() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+
+        let err = get_config_with_env(
+            vec![
+                "self",
+                "-m",
+                media_src.path().to_str().unwrap(),
+                "-p",
+                photos_dst.path().to_str().unwrap(),
+                "--interactive",
+                "--no-load-default-config-file",
+            ],
+            vec![("MEDIA_ORGANIZER_DRY_RUN".to_owned(), "true".to_owned())],
         )
+        .unwrap_err();
+        assert_eq!(
+            "interactive and dry run can't be used together",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn load_preview() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+            "preview",
+        ])
+        .unwrap();
+        assert!(config.preview);
+    }
+
+    #[test]
+    fn load_plan_and_apply() {
+        let media_src = tempdir().unwrap();
+        let photos_dst = tempdir().unwrap();
+        let args = |subcommand| {
+            vec![
+                "self".to_owned(),
+                "-m".to_owned(),
+                media_src.path().to_str().unwrap().to_owned(),
+                "-p".to_owned(),
+                photos_dst.path().to_str().unwrap().to_owned(),
+                "--no-load-default-config-file".to_owned(),
+                subcommand,
+                "plan.json".to_owned(),
+            ]
+        };
+        let plan_file = env::current_dir().unwrap().join("plan.json");
+
+        let config = get_config(args("plan".to_owned())).unwrap();
+        assert_eq!(Some(plan_file.clone()), config.plan);
+        assert_eq!(None, config.apply);
+
+        let config = get_config(args("apply".to_owned())).unwrap();
+        assert_eq!(Some(plan_file), config.apply);
+        assert_eq!(None, config.plan);
+    }
+
+    #[test]
+    fn load_audio_dst() {
+        let media_src = tempdir().unwrap();
+        let audio_dst = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-a",
+            audio_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.audio_dst.unwrap(), audio_dst.path());
+
+        assert!(get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-a",
+            "/does/not/exist",
+            "--no-load-default-config-file",
+        ])
+        .is_err());
     }
 
     #[test]
@@ -352,10 +3617,368 @@ mod tests {
             config_file_path.to_str().unwrap(),
         ])
         .unwrap();
-        assert_eq!(config.media_src, media_src_cmd.path());
+        assert_eq!(config.media_src, vec![media_src_cmd.path()]);
         assert_eq!(config.photos_dst, photos_dst_cmd.path());
         // videos_dst is not on the cmd line args and it's taken from the
         // config file.
         assert_eq!(config.videos_dst, videos_dst_file.path());
     }
+
+    #[test]
+    fn load_size_range() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--min-size",
+            "20K",
+            "--max-size",
+            "5M",
+        ])
+        .unwrap();
+        assert_eq!(config.min_size, Some(20 * 1024));
+        assert_eq!(config.max_size, Some(5 * 1024 * 1024));
+    }
+
+    #[test]
+    fn load_unknown_date_dir() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+        let load = |args: &[&str]| {
+            let mut cmd_args = vec![
+                "self",
+                "-m",
+                media_src.path().to_str().unwrap(),
+                "-p",
+                photos_dst.path().to_str().unwrap(),
+                "--no-load-default-config-file",
+            ];
+            cmd_args.extend(args);
+            get_config(cmd_args)
+        };
+
+        assert_eq!(load(&[]).unwrap().unknown_date_dir, None);
+        assert_eq!(
+            load(&["--unknown-date"]).unwrap().unknown_date_dir,
+            Some(PathBuf::from("unknown-date"))
+        );
+        assert_eq!(
+            load(&["--unknown-date-dir", "sin-fecha"])
+                .unwrap()
+                .unknown_date_dir,
+            Some(PathBuf::from("sin-fecha"))
+        );
+        assert_eq!(
+            "unknown date dir should be a folder name, without path separators",
+            load(&["--unknown-date-dir", "review/no-date"])
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "unknown date dir can't be empty",
+            load(&["--unknown-date-dir", ".."]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn load_retries() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.retries, 3);
+        assert_eq!(config.retry_delay, Duration::from_millis(500));
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--retries",
+            "5",
+            "--retry-delay",
+            "2s",
+        ])
+        .unwrap();
+        assert_eq!(config.retries, 5);
+        assert_eq!(config.retry_delay, Duration::from_secs(2));
+
+        let config_file_dir = tempdir().unwrap();
+        let config_file_path = config_file_dir.path().join("config.toml");
+        let load_file = |retries: &str| {
+            fs::write(&config_file_path, format!("retries = {}", retries)).unwrap();
+            get_config(vec![
+                "self",
+                "-m",
+                media_src.path().to_str().unwrap(),
+                "-p",
+                photos_dst.path().to_str().unwrap(),
+                "-c",
+                config_file_path.to_str().unwrap(),
+            ])
+        };
+        assert_eq!(load_file("4").unwrap().retries, 4);
+        assert_eq!(
+            "invalid number in retries",
+            load_file("'abc'").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn load_modified_after() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--modified-after",
+            "2020-04-07T14:30:00+02:00",
+        ])
+        .unwrap();
+        assert_eq!(
+            config.modified_after,
+            Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1586262600))
+        );
+        assert!(parse_time("2020-04-07").is_err());
+    }
+
+    #[test]
+    fn load_on_conflict() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--on-conflict",
+            "rename",
+        ])
+        .unwrap();
+        assert_eq!(config.conflict_strategy, ConflictStrategy::Rename);
+        assert!(parse_conflict_strategy("replace").is_err());
+    }
+
+    #[test]
+    fn load_takeout_sidecar() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--takeout-sidecar",
+            "last",
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.takeout_sidecar, Some(TakeoutSidecar::Last));
+        assert_eq!(
+            TakeoutSidecar::Ignore,
+            parse_takeout_sidecar(" Ignore").unwrap()
+        );
+        assert!(parse_takeout_sidecar("never").is_err());
+    }
+
+    #[test]
+    fn load_exif_timezone() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+
+        let config = get_config(vec![
+            "self",
+            "-m",
+            media_src.path().to_str().unwrap(),
+            "-p",
+            photos_dst.path().to_str().unwrap(),
+            "--exif-timezone",
+            "normalize",
+            "--no-load-default-config-file",
+        ])
+        .unwrap();
+        assert_eq!(config.exif_timezone, Some(ExifTimezone::Normalize));
+        assert_eq!(
+            ExifTimezone::Ignore,
+            parse_exif_timezone(" Ignore").unwrap()
+        );
+        assert!(parse_exif_timezone("utc").is_err());
+    }
+
+    #[test]
+    fn parse_tiers() {
+        assert_eq!(
+            vec![
+                ResolutionTier::new("FullHD", 1080),
+                ResolutionTier::new("Other", 0)
+            ],
+            parse_resolution_tiers("FullHD=1080, Other = 0").unwrap()
+        );
+        assert!(parse_resolution_tiers("HD").is_err());
+        assert!(parse_resolution_tiers("HD=lots").is_err());
+        assert!(parse_resolution_tiers("../HD=720").is_err());
+    }
+
+    #[test]
+    fn load_unknown_placeholder() {
+        let photos_dst = tempdir().unwrap();
+        let media_src = tempdir().unwrap();
+        let load = |args: &[&str]| {
+            let mut cmd_args = vec![
+                "self",
+                "-m",
+                media_src.path().to_str().unwrap(),
+                "-p",
+                photos_dst.path().to_str().unwrap(),
+                "--no-load-default-config-file",
+            ];
+            cmd_args.extend(args);
+            get_config(cmd_args)
+        };
+
+        assert_eq!(load(&[]).unwrap().unknown_placeholder, None);
+        assert_eq!(
+            load(&["--unknown-placeholder", " Sin resolución? "])
+                .unwrap()
+                .unknown_placeholder,
+            Some("Sin resolución_".to_owned())
+        );
+        assert_eq!(
+            "unknown placeholder can't be empty",
+            load(&["--unknown-placeholder", ".."])
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "unknown placeholder \"nul\" is a reserved name",
+            load(&["--unknown-placeholder", "nul"])
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "unknown placeholder \"Bursts\" is a reserved name",
+            load(&["--unknown-placeholder", "Bursts"])
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "unknown placeholder \"SD\" is the name of a resolution tier",
+            load(&["--group-by-resolution", "--unknown-placeholder", "SD"])
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn reserved_names() {
+        for name in ["CON", "nul", "Aux.jpg", "COM1", "lpt9.txt"] {
+            assert!(is_reserved_name(name), "{}", name);
+        }
+        for name in ["unknown", "CONSOLE", "COM0", "LPT10", "nul_"] {
+            assert!(!is_reserved_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn parse_photo_extensions() {
+        assert_eq!(
+            vec!["heic".to_owned(), "JPG".to_owned()],
+            parse_extensions("heic, .JPG,").unwrap()
+        );
+        assert!(parse_extensions(" , ").is_err());
+    }
+
+    #[test]
+    fn parse_filename_regexes() {
+        let regex = parse_filename_regex(r"^DSC(?P<year>\d{4})(?P<month>\d{2})").unwrap();
+        assert!(regex.is_match("DSC202004_01.jpg"));
+        assert!(parse_filename_regex(r"^DSC(?P<year>\d{4})(\d{2})").is_err());
+        assert!(parse_filename_regex(r"^DSC(?P<year>\d{4}").is_err());
+    }
+
+    #[test]
+    fn parse_date_tags() {
+        assert_eq!(
+            vec![ExifDateTag::DateTime, ExifDateTag::DateTimeDigitized],
+            parse_exif_date_tags("DateTime, createdate").unwrap()
+        );
+        assert!(parse_exif_date_tags("DateTimeOriginal,ModifyDate").is_err());
+    }
+
+    #[test]
+    fn parse_date_source_order() {
+        assert_eq!(
+            vec![
+                DateSource::Filename,
+                DateSource::Exif,
+                DateSource::Mtime,
+                DateSource::Sidecar,
+                DateSource::Birthtime
+            ],
+            parse_date_sources("filename, EXIF,mtime,sidecar,Birthtime").unwrap()
+        );
+        assert!(parse_date_sources("exif,gps").is_err());
+        assert!(parse_date_sources("").is_err());
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(parse_duration(" 1S ").unwrap(), Duration::from_secs(1));
+        assert!(parse_duration("500").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert_eq!(
+            "'18446744073709551615s' is too long",
+            parse_duration("18446744073709551615s")
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn parse_resolutions() {
+        assert_eq!(parse_resolution("640x480").unwrap(), (640, 480));
+        assert_eq!(parse_resolution(" 1920 X 1080 ").unwrap(), (1920, 1080));
+        assert!(parse_resolution("640").is_err());
+        assert!(parse_resolution("640x").is_err());
+        assert!(parse_resolution("wide").is_err());
+    }
+
+    #[test]
+    fn parse_sizes() {
+        assert_eq!(parse_size("100").unwrap(), 100);
+        assert_eq!(parse_size("20K").unwrap(), 20 * 1024);
+        assert_eq!(parse_size("20kb").unwrap(), 20 * 1024);
+        assert_eq!(parse_size("5M").unwrap(), 5 * 1024 * 1024);
+        assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("ten").is_err());
+        assert_eq!(
+            "'99999999999G' is too big",
+            parse_size("99999999999G").unwrap_err().to_string()
+        );
+    }
 }
@@ -1,9 +1,118 @@
+use crate::error::OrganizerError;
+use chrono::{DateTime, Datelike, Local};
 use color_eyre::eyre::{eyre, Result};
+use std::time::SystemTime;
+
+/// Language used for the month names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Es,
+    Fr,
+    De,
+    Pt,
+}
+
+impl Locale {
+    fn month_names(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Locale::Es => [
+                "Enero",
+                "Febrero",
+                "Marzo",
+                "Abril",
+                "Mayo",
+                "Junio",
+                "Julio",
+                "Agosto",
+                "Septiembre",
+                "Octubre",
+                "Noviembre",
+                "Diciembre",
+            ],
+            Locale::Fr => [
+                "Janvier",
+                "Février",
+                "Mars",
+                "Avril",
+                "Mai",
+                "Juin",
+                "Juillet",
+                "Août",
+                "Septembre",
+                "Octobre",
+                "Novembre",
+                "Décembre",
+            ],
+            Locale::De => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Locale::Pt => [
+                "Janeiro",
+                "Fevereiro",
+                "Março",
+                "Abril",
+                "Maio",
+                "Junho",
+                "Julho",
+                "Agosto",
+                "Setembro",
+                "Outubro",
+                "Novembro",
+                "Dezembro",
+            ],
+        }
+    }
+
+    fn abbreviated_month_names(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En => [
+                "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+            ],
+            Locale::Es => [
+                "Ene", "Feb", "Mar", "Abr", "May", "Jun", "Jul", "Ago", "Sep", "Oct", "Nov", "Dic",
+            ],
+            Locale::Fr => [
+                "Janv", "Févr", "Mars", "Avr", "Mai", "Juin", "Juil", "Août", "Sept", "Oct", "Nov",
+                "Déc",
+            ],
+            Locale::De => [
+                "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+            ],
+            Locale::Pt => [
+                "Jan", "Fev", "Mar", "Abr", "Mai", "Jun", "Jul", "Ago", "Set", "Out", "Nov", "Dez",
+            ],
+        }
+    }
+}
 
 /// A simple date structure that only contains the year and month.
-/// The components can be returned as strings. In the case of the
-/// months they are returned as `MM - Month Name`.
-/// [Self::get_month]
+/// The components can be returned as strings, see [Self::format].
 #[derive(Debug)]
 pub struct Date {
     year: u16,
@@ -11,42 +120,116 @@ pub struct Date {
 }
 
 impl Date {
-    pub fn new(year: u16, month: u8) -> Result<Date> {
-        if !(1..=12).contains(&month) {
-            return Err(eyre!(
-                "invalid month, should be between 1 and 12 got {}",
-                month
-            ));
-        }
-
-        if !(1839..=3000).contains(&year) {
-            return Err(eyre!(
-                "invalid year, should be between 1839 and 3000 got {}",
-                year
-            ));
+    pub fn new(year: u16, month: u8) -> Result<Date, OrganizerError> {
+        if !(1..=12).contains(&month) || !(1839..=3000).contains(&year) {
+            return Err(OrganizerError::InvalidDate { year, month });
         }
         Ok(Date { year, month })
     }
 
-    pub fn get_month(&self) -> String {
-        match self.month {
-            1 => String::from("01 - January"),
-            2 => String::from("02 - February"),
-            3 => String::from("03 - March"),
-            4 => String::from("04 - April"),
-            5 => String::from("05 - May"),
-            6 => String::from("06 - June"),
-            7 => String::from("07 - July"),
-            8 => String::from("08 - August"),
-            9 => String::from("09 - September"),
-            10 => String::from("10 - October"),
-            11 => String::from("11 - November"),
-            12 => String::from("12 - December"),
-            _ => String::from(""),
-        }
+    /// Date of a timestamp in the local time zone.
+    pub fn from_timestamp(time: SystemTime) -> Result<Date> {
+        let time = DateTime::<Local>::from(time);
+        Ok(Date::new(
+            u16::try_from(time.year()).map_err(|_| eyre!("invalid year {}", time.year()))?,
+            time.month() as u8,
+        )?)
     }
 
     pub fn get_year(&self) -> String {
         self.year.to_string()
     }
+
+    /// Formats the date with a strftime-like template. The supported
+    /// tokens are:
+    ///
+    /// - `%Y`: year, `2020`.
+    /// - `%m`: zero padded month, `04`.
+    /// - `%B`: month name in the locale, `April`.
+    /// - `%b`: abbreviated month name in the locale, `Apr`.
+    /// - `%%`: a literal `%`.
+    ///
+    /// Any other character is copied as is.
+    pub fn format(&self, template: &str, locale: Locale) -> String {
+        let month_index = usize::from(self.month - 1);
+        let mut formatted = String::with_capacity(template.len());
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                formatted.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => formatted.push_str(&self.year.to_string()),
+                Some('m') => formatted.push_str(&format!("{:02}", self.month)),
+                Some('B') => formatted.push_str(locale.month_names()[month_index]),
+                Some('b') => formatted.push_str(locale.abbreviated_month_names()[month_index]),
+                Some('%') => formatted.push('%'),
+                Some(other) => {
+                    formatted.push('%');
+                    formatted.push(other);
+                }
+                None => formatted.push('%'),
+            }
+        }
+        formatted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_date_err() {
+        assert!(matches!(
+            Date::new(2020, 13),
+            Err(OrganizerError::InvalidDate {
+                year: 2020,
+                month: 13
+            })
+        ));
+        assert!(matches!(
+            Date::new(1800, 1),
+            Err(OrganizerError::InvalidDate { year: 1800, .. })
+        ));
+    }
+
+    #[test]
+    fn format_month_folder() {
+        assert_eq!(
+            "04 - April",
+            Date::new(2020, 4).unwrap().format("%m - %B", Locale::En)
+        );
+        assert_eq!(
+            "12 - December",
+            Date::new(2020, 12).unwrap().format("%m - %B", Locale::En)
+        );
+    }
+
+    #[test]
+    fn format_month_names() {
+        let date = Date::new(2020, 4).unwrap();
+        assert_eq!("Abril", date.format("%B", Locale::Es));
+        assert_eq!("April", date.format("%B", Locale::En));
+        assert_eq!("Abr", date.format("%b", Locale::Es));
+        assert_eq!("Apr", date.format("%b", Locale::En));
+    }
+
+    #[test]
+    fn from_timestamp() {
+        // 2018-05-15T12:00:00Z, far enough from the month ends for any
+        // time zone.
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_526_385_600);
+        let date = Date::from_timestamp(time).unwrap();
+        assert_eq!("2018-05", date.format("%Y-%m", Locale::En));
+    }
+
+    #[test]
+    fn format_template() {
+        let date = Date::new(2019, 1).unwrap();
+        assert_eq!("2019/01 - Enero", date.format("%Y/%m - %B", Locale::Es));
+        assert_eq!("2019-01 100%", date.format("%Y-%m 100%%", Locale::En));
+        assert_eq!("%d 2019", date.format("%d %Y", Locale::En));
+    }
 }
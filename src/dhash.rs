@@ -0,0 +1,34 @@
+use color_eyre::eyre::{Result, WrapErr};
+use image::imageops::FilterType;
+use image::ImageReader;
+use std::path::Path;
+
+/// Returns the difference hash of an image. The image is shrunk to 9x8
+/// gray pixels and each bit tells if a pixel is darker than the one on
+/// its right, so re-compressed or resized copies get the same, or a
+/// very close, hash.
+pub fn dhash(path: &Path) -> Result<u64> {
+    let image = ImageReader::open(path)
+        .wrap_err("failed to open image")?
+        .with_guessed_format()
+        .wrap_err("failed to read image format")?
+        .decode()
+        .wrap_err("failed to decode image")?;
+    let small = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/// Number of bits that differ between two hashes.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
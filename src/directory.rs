@@ -1,21 +1,150 @@
+use glob::Pattern;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 /// Iterator over the files and subdirecotires of a given root
 /// directory. It uses a breath depth approach. It doesn't follow
 /// symlinks.
+///
+/// Files can be filtered by size with [`Self::with_min_size`] and
+/// [`Self::with_max_size`], and by modification time with
+/// [`Self::modified_after`] and [`Self::modified_before`]. Files and
+/// directories can be excluded with glob patterns, see
+/// [`Self::with_exclude`]. How deep the walk goes can be limited with
+/// [`Self::with_max_depth`].
 pub struct FilesIter {
-    dirs: Vec<PathBuf>,
+    root: PathBuf,
+    exclude: Vec<Pattern>,
+    dirs: Vec<(PathBuf, usize)>,
     files: Vec<PathBuf>,
+    max_depth: Option<usize>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
 }
 
 impl FilesIter {
-    pub fn new(dir: PathBuf) -> FilesIter {
+    /// Creates an iterator over the files under `dir`, which can be
+    /// anything that converts into a path like `&Path`, `&str` or a
+    /// `PathBuf`.
+    pub fn new(dir: impl Into<PathBuf>) -> FilesIter {
+        let dir = dir.into();
         FilesIter {
-            dirs: vec![dir],
+            root: dir.clone(),
+            exclude: Vec::new(),
+            dirs: vec![(dir, 0)],
             files: Vec::new(),
+            max_depth: None,
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
         }
     }
+
+    /// Only yield files with a size in bytes greater or equal than
+    /// `min_size`.
+    pub fn with_min_size(mut self, min_size: u64) -> FilesIter {
+        self.min_size = Some(min_size);
+        self
+    }
+
+    /// Only yield files with a size in bytes lower or equal than
+    /// `max_size`.
+    pub fn with_max_size(mut self, max_size: u64) -> FilesIter {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Only yield files modified after `time`.
+    pub fn modified_after(mut self, time: SystemTime) -> FilesIter {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Only yield files modified before `time`.
+    pub fn modified_before(mut self, time: SystemTime) -> FilesIter {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Only descends `max_depth` levels of subdirectories, with `0` only
+    /// the files directly in the root directory are yielded.
+    pub fn with_max_depth(mut self, max_depth: usize) -> FilesIter {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Skips the files and directories whose name, or path relative to
+    /// the root directory, matches any of the patterns. For example
+    /// `*.tmp` or `Trash/**`.
+    pub fn with_exclude(mut self, patterns: Vec<Pattern>) -> FilesIter {
+        self.exclude = patterns;
+        self
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        self.exclude.iter().any(|pattern| {
+            pattern.matches_path(relative)
+                || path
+                    .file_name()
+                    .is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+        })
+    }
+
+    /// If a file under the root directory would be yielded by the walk.
+    pub(crate) fn accepts(&self, file: &Path) -> bool {
+        file.is_file()
+            && !self.is_excluded(file)
+            && self.within_max_depth(file)
+            && self.matches_filters(file)
+    }
+
+    fn within_max_depth(&self, file: &Path) -> bool {
+        let max_depth = match self.max_depth {
+            Some(max_depth) => max_depth,
+            None => return true,
+        };
+        let relative = file.strip_prefix(&self.root).unwrap_or(file);
+        relative.components().count() <= max_depth + 1
+    }
+
+    fn matches_filters(&self, file: &Path) -> bool {
+        if self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.modified_after.is_none()
+            && self.modified_before.is_none()
+        {
+            return true;
+        }
+        let metadata = match fs::metadata(file) {
+            Ok(metadata) => metadata,
+            _ => return false,
+        };
+
+        let size = metadata.len();
+        if matches!(self.min_size, Some(min_size) if size < min_size) {
+            return false;
+        }
+        if matches!(self.max_size, Some(max_size) if size > max_size) {
+            return false;
+        }
+
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let modified = match metadata.modified() {
+            Ok(modified) => modified,
+            _ => return false,
+        };
+        if matches!(self.modified_after, Some(after) if modified <= after) {
+            return false;
+        }
+        !matches!(self.modified_before, Some(before) if modified >= before)
+    }
 }
 
 impl Iterator for FilesIter {
@@ -26,7 +155,7 @@ impl Iterator for FilesIter {
             return Some(file);
         }
 
-        while let Some(dir) = self.dirs.pop() {
+        while let Some((dir, depth)) = self.dirs.pop() {
             let dir_entries = match fs::read_dir(dir) {
                 Ok(entries) => entries,
                 _ => continue,
@@ -41,13 +170,21 @@ impl Iterator for FilesIter {
                 if path.read_link().is_ok() {
                     continue;
                 }
+                if self.is_excluded(&path) {
+                    continue;
+                }
                 if path.is_dir() {
-                    self.dirs.push(path);
+                    if self.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                        self.dirs.push((path, depth + 1));
+                    }
                     continue;
                 }
                 if !path.is_file() {
                     continue;
                 }
+                if !self.matches_filters(&path) {
+                    continue;
+                }
                 self.files.push(path);
             }
 
@@ -63,6 +200,7 @@ impl Iterator for FilesIter {
 mod tests {
 
     use super::*;
+    use std::time::Duration;
     use tempfile::TempDir;
 
     #[test]
@@ -73,11 +211,106 @@ mod tests {
         fs::DirBuilder::new().create(&dir_path).unwrap();
         fs::File::create(dir_path.join("file2.png")).unwrap();
 
-        let files_iter = FilesIter::new(src.path().to_owned());
+        let files_iter = FilesIter::new(src.path());
         let files: Vec<PathBuf> = files_iter.collect();
         assert_eq!(
             vec!(src.path().join("file1.png"), dir_path.join("file2.png")),
             files
         );
     }
+
+    #[test]
+    fn max_depth() {
+        let src = TempDir::new().unwrap();
+        fs::File::create(src.path().join("file1.png")).unwrap();
+        let dir_path = src.path().join("dir");
+        fs::DirBuilder::new()
+            .recursive(true)
+            .create(dir_path.join("nested"))
+            .unwrap();
+        fs::File::create(dir_path.join("file2.png")).unwrap();
+        fs::File::create(dir_path.join("nested").join("file3.png")).unwrap();
+
+        let files = FilesIter::new(src.path()).with_max_depth(0);
+        assert!(files.accepts(&src.path().join("file1.png")));
+        assert!(!files.accepts(&dir_path.join("file2.png")));
+        assert_eq!(
+            vec!(src.path().join("file1.png")),
+            files.collect::<Vec<_>>()
+        );
+
+        let mut files: Vec<PathBuf> = FilesIter::new(src.path()).with_max_depth(1).collect();
+        files.sort();
+        assert_eq!(
+            vec!(dir_path.join("file2.png"), src.path().join("file1.png")),
+            files
+        );
+    }
+
+    #[test]
+    fn exclude() {
+        let src = TempDir::new().unwrap();
+        fs::File::create(src.path().join("foo.tmp")).unwrap();
+        fs::File::create(src.path().join("foo.jpg")).unwrap();
+        let trash = src.path().join("Trash");
+        fs::DirBuilder::new().create(&trash).unwrap();
+        fs::File::create(trash.join("bar.jpg")).unwrap();
+
+        let files: Vec<PathBuf> = FilesIter::new(src.path().to_owned())
+            .with_exclude(vec![
+                Pattern::new("*.tmp").unwrap(),
+                Pattern::new("Trash").unwrap(),
+            ])
+            .collect();
+        assert_eq!(vec!(src.path().join("foo.jpg")), files);
+    }
+
+    #[test]
+    fn min_size() {
+        let src = TempDir::new().unwrap();
+        fs::write(src.path().join("under.jpg"), vec![0; 1023]).unwrap();
+        fs::write(src.path().join("exact.jpg"), vec![0; 1024]).unwrap();
+
+        let files: Vec<PathBuf> = FilesIter::new(src.path().to_owned())
+            .with_min_size(1024)
+            .collect();
+        assert_eq!(vec!(src.path().join("exact.jpg")), files);
+    }
+
+    #[test]
+    fn max_size() {
+        let src = TempDir::new().unwrap();
+        fs::write(src.path().join("exact.jpg"), vec![0; 1024]).unwrap();
+        fs::write(src.path().join("over.jpg"), vec![0; 1025]).unwrap();
+
+        let files: Vec<PathBuf> = FilesIter::new(src.path().to_str().unwrap())
+            .with_max_size(1024)
+            .collect();
+        assert_eq!(vec!(src.path().join("exact.jpg")), files);
+    }
+
+    #[test]
+    fn modified_window() {
+        let src = TempDir::new().unwrap();
+        let old = src.path().join("old.jpg");
+        fs::File::create(&old).unwrap();
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(an_hour_ago - Duration::from_secs(3600))
+            .unwrap();
+        fs::File::create(src.path().join("fresh.jpg")).unwrap();
+
+        let files: Vec<PathBuf> = FilesIter::new(src.path().to_owned())
+            .modified_after(an_hour_ago)
+            .collect();
+        assert_eq!(vec!(src.path().join("fresh.jpg")), files);
+
+        let files: Vec<PathBuf> = FilesIter::new(src.path())
+            .modified_before(an_hour_ago)
+            .collect();
+        assert_eq!(vec!(old), files);
+    }
 }
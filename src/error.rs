@@ -0,0 +1,40 @@
+use crate::ExifDateTag;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Errors of the organizers that library users may want to tell apart.
+/// They are returned inside [`color_eyre::Report`]s, usually with some
+/// context on top, and can be matched with
+/// [`Report::downcast_ref`](color_eyre::Report::downcast_ref) or by
+/// looking for them in [`Report::chain`](color_eyre::Report::chain).
+#[derive(Debug, Error)]
+pub enum OrganizerError {
+    /// The year or month of a date is out of range.
+    #[error("invalid date {year}-{month:02}, the year should be between 1839 and 3000 and the month between 1 and 12")]
+    InvalidDate { year: u16, month: u8 },
+    /// The photo has no exif.
+    #[error("photo has no exif")]
+    ExifMissing,
+    /// The exif of the photo doesn't have the tag.
+    #[error("exif {0:?} tag is missing")]
+    ExifTagMissing(ExifDateTag),
+    /// The file name doesn't match any of the date patterns.
+    #[error("file name doesn't have date format")]
+    FilenameNoDate,
+    /// A different file with the same name already exists in the
+    /// destination.
+    #[error("a file with the same name already exists in the destination path {0:?}")]
+    DestinationExists(PathBuf),
+    /// The transferred file doesn't have the content of its source.
+    #[error("verification failed, the content of {0:?} differs from its source")]
+    VerificationFailed(PathBuf),
+    /// A filesystem operation on a file failed.
+    #[error("failed to {action} {path:?}")]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
@@ -0,0 +1,252 @@
+/// Bounding box of a country, `(name, min latitude, max latitude, min
+/// longitude, max longitude)`. Boxes overlap near borders, see
+/// [`country`] for how the overlaps are resolved.
+type CountryBox = (&'static str, f64, f64, f64, f64);
+
+const COUNTRIES: [CountryBox; 24] = [
+    ("Portugal", 36.9, 42.2, -9.6, -6.2),
+    ("Switzerland", 45.8, 47.8, 5.9, 10.5),
+    ("Netherlands", 50.7, 53.6, 3.3, 7.2),
+    ("Belgium", 49.5, 51.5, 2.5, 6.4),
+    ("Denmark", 54.5, 57.8, 8.0, 12.7),
+    ("Austria", 46.4, 49.0, 9.5, 17.2),
+    ("Ireland", 51.4, 55.4, -10.5, -6.0),
+    ("Greece", 34.8, 41.8, 19.4, 28.3),
+    ("United Kingdom", 49.9, 60.9, -8.2, 1.8),
+    ("Spain", 36.0, 43.8, -9.3, 3.3),
+    ("Italy", 36.6, 47.1, 6.6, 18.5),
+    ("Germany", 47.3, 55.1, 5.9, 15.0),
+    ("France", 41.3, 51.1, -5.1, 9.6),
+    ("Japan", 24.0, 45.6, 122.9, 145.8),
+    ("Venezuela", 0.6, 12.2, -73.4, -59.8),
+    ("Colombia", -4.2, 12.5, -79.0, -66.9),
+    ("Mexico", 14.5, 32.7, -118.4, -86.7),
+    ("United States", 24.5, 49.4, -124.8, -66.9),
+    ("Canada", 41.7, 83.1, -141.0, -52.6),
+    ("Argentina", -55.1, -21.8, -73.6, -53.6),
+    ("Brazil", -33.8, 5.3, -74.0, -34.8),
+    ("Australia", -43.7, -10.7, 113.3, 153.6),
+    ("China", 18.2, 53.6, 73.5, 134.8),
+    ("India", 6.7, 35.5, 68.1, 97.4),
+];
+
+/// Returns the country of a location, in decimal degrees, or `None`
+/// when it's not in any of the known countries. Near one of the known
+/// cities it's the country of the city. Elsewhere it's the country,
+/// among the ones whose bounding box has the location, with the known
+/// city nearest to it, so locations far from any city and near a border
+/// might get the neighbour.
+pub fn country(latitude: f64, longitude: f64) -> Option<&'static str> {
+    if let Some((_, country, ..)) = nearest(latitude, longitude, |_| true)
+        .filter(|(_, distance)| *distance <= MAX_CITY_DISTANCE_KM)
+        .map(|(city, _)| city)
+    {
+        return Some(country);
+    }
+    let in_box = |country: &str| {
+        COUNTRIES
+            .iter()
+            .any(|(name, min_lat, max_lat, min_lon, max_lon)| {
+                *name == country
+                    && (*min_lat..=*max_lat).contains(&latitude)
+                    && (*min_lon..=*max_lon).contains(&longitude)
+            })
+    };
+    nearest(latitude, longitude, |(_, country, ..)| in_box(country))
+        .map(|((_, country, ..), _)| *country)
+}
+
+/// A city, `(name, country, latitude, longitude)`.
+type City = (&'static str, &'static str, f64, f64);
+
+const CITIES: [City; 94] = [
+    ("Madrid", "Spain", 40.42, -3.70),
+    ("Barcelona", "Spain", 41.39, 2.17),
+    ("Valencia", "Spain", 39.47, -0.38),
+    ("Seville", "Spain", 37.39, -5.99),
+    ("Bilbao", "Spain", 43.26, -2.93),
+    ("Malaga", "Spain", 36.72, -4.42),
+    ("Palma", "Spain", 39.57, 2.65),
+    ("Lisbon", "Portugal", 38.72, -9.14),
+    ("Porto", "Portugal", 41.15, -8.61),
+    ("Faro", "Portugal", 37.02, -7.93),
+    ("Paris", "France", 48.86, 2.35),
+    ("Lyon", "France", 45.76, 4.84),
+    ("Marseille", "France", 43.30, 5.37),
+    ("Bordeaux", "France", 44.84, -0.58),
+    ("Nice", "France", 43.70, 7.27),
+    ("London", "United Kingdom", 51.51, -0.13),
+    ("Manchester", "United Kingdom", 53.48, -2.24),
+    ("Edinburgh", "United Kingdom", 55.95, -3.19),
+    ("Dublin", "Ireland", 53.35, -6.26),
+    ("Amsterdam", "Netherlands", 52.37, 4.90),
+    ("Brussels", "Belgium", 50.85, 4.35),
+    ("Copenhagen", "Denmark", 55.68, 12.57),
+    ("Berlin", "Germany", 52.52, 13.40),
+    ("Munich", "Germany", 48.14, 11.58),
+    ("Hamburg", "Germany", 53.55, 9.99),
+    ("Frankfurt", "Germany", 50.11, 8.68),
+    ("Cologne", "Germany", 50.94, 6.96),
+    ("Zurich", "Switzerland", 47.38, 8.54),
+    ("Geneva", "Switzerland", 46.20, 6.14),
+    ("Vienna", "Austria", 48.21, 16.37),
+    ("Salzburg", "Austria", 47.80, 13.04),
+    ("Basel", "Switzerland", 47.56, 7.59),
+    ("Strasbourg", "France", 48.57, 7.75),
+    ("Rome", "Italy", 41.90, 12.50),
+    ("Milan", "Italy", 45.46, 9.19),
+    ("Venice", "Italy", 45.44, 12.32),
+    ("Florence", "Italy", 43.77, 11.26),
+    ("Naples", "Italy", 40.85, 14.27),
+    ("Athens", "Greece", 37.98, 23.73),
+    ("Prague", "Czechia", 50.08, 14.44),
+    ("Warsaw", "Poland", 52.23, 21.01),
+    ("Budapest", "Hungary", 47.50, 19.04),
+    ("Stockholm", "Sweden", 59.33, 18.07),
+    ("Oslo", "Norway", 59.91, 10.75),
+    ("Istanbul", "Turkey", 41.01, 28.98),
+    ("Moscow", "Russia", 55.76, 37.62),
+    ("Cairo", "Egypt", 30.04, 31.24),
+    ("Marrakesh", "Morocco", 31.63, -7.99),
+    ("Cape Town", "South Africa", -33.92, 18.42),
+    ("Nairobi", "Kenya", -1.29, 36.82),
+    ("New York", "United States", 40.71, -74.01),
+    ("Los Angeles", "United States", 34.05, -118.24),
+    ("San Francisco", "United States", 37.77, -122.42),
+    ("Chicago", "United States", 41.88, -87.63),
+    ("Miami", "United States", 25.76, -80.19),
+    ("Seattle", "United States", 47.61, -122.33),
+    ("Las Vegas", "United States", 36.17, -115.14),
+    ("Washington", "United States", 38.91, -77.04),
+    ("Boston", "United States", 42.36, -71.06),
+    ("Houston", "United States", 29.76, -95.37),
+    ("Dallas", "United States", 32.78, -96.80),
+    ("Toronto", "Canada", 43.65, -79.38),
+    ("Montreal", "Canada", 45.50, -73.57),
+    ("Vancouver", "Canada", 49.28, -123.12),
+    ("Mexico City", "Mexico", 19.43, -99.13),
+    ("Cancun", "Mexico", 21.16, -86.85),
+    ("Guadalajara", "Mexico", 20.67, -103.35),
+    ("Monterrey", "Mexico", 25.69, -100.32),
+    ("Tijuana", "Mexico", 32.51, -117.04),
+    ("Caracas", "Venezuela", 10.48, -66.90),
+    ("Maracaibo", "Venezuela", 10.65, -71.64),
+    ("Valencia (Venezuela)", "Venezuela", 10.16, -68.01),
+    ("Bogota", "Colombia", 4.71, -74.07),
+    ("Medellin", "Colombia", 6.24, -75.58),
+    ("Lima", "Peru", -12.05, -77.04),
+    ("Santiago", "Chile", -33.45, -70.67),
+    ("Buenos Aires", "Argentina", -34.60, -58.38),
+    ("Sao Paulo", "Brazil", -23.55, -46.63),
+    ("Rio de Janeiro", "Brazil", -22.91, -43.17),
+    ("Tokyo", "Japan", 35.68, 139.69),
+    ("Osaka", "Japan", 34.69, 135.50),
+    ("Kyoto", "Japan", 35.01, 135.77),
+    ("Beijing", "China", 39.90, 116.41),
+    ("Shanghai", "China", 31.23, 121.47),
+    ("Hong Kong", "Hong Kong", 22.32, 114.17),
+    ("Seoul", "South Korea", 37.57, 126.98),
+    ("Bangkok", "Thailand", 13.76, 100.50),
+    ("Singapore", "Singapore", 1.35, 103.82),
+    ("Delhi", "India", 28.70, 77.10),
+    ("Mumbai", "India", 19.08, 72.88),
+    ("Dubai", "United Arab Emirates", 25.20, 55.27),
+    ("Sydney", "Australia", -33.87, 151.21),
+    ("Melbourne", "Australia", -37.81, 144.96),
+    ("Auckland", "New Zealand", -36.85, 174.76),
+];
+
+/// Farthest a location can be from a city to be taken as in it.
+const MAX_CITY_DISTANCE_KM: f64 = 100.0;
+
+/// Mean radius of the Earth.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Returns the city nearest to a location, in decimal degrees, or `None`
+/// when none of the known cities is within 100 km.
+pub fn nearest_city(latitude: f64, longitude: f64) -> Option<&'static str> {
+    nearest(latitude, longitude, |_| true)
+        .filter(|(_, distance)| *distance <= MAX_CITY_DISTANCE_KM)
+        .map(|((name, ..), _)| *name)
+}
+
+/// Returns the city for which `filter` is true nearest to a location,
+/// with its distance in km.
+fn nearest(
+    latitude: f64,
+    longitude: f64,
+    filter: impl Fn(&City) -> bool,
+) -> Option<(&'static City, f64)> {
+    CITIES
+        .iter()
+        .filter(|city| filter(city))
+        .map(|city @ (_, _, city_lat, city_lon)| {
+            (city, distance_km(latitude, longitude, *city_lat, *city_lon))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Great-circle distance between two locations, with the haversine
+/// formula.
+fn distance_km(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
+    let (lat_a, lat_b) = (lat_a.to_radians(), lat_b.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (lon_b - lon_a).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn country_of_location() {
+        assert_eq!(Some("Spain"), country(40.41, -3.70));
+        assert_eq!(Some("Portugal"), country(38.72, -9.14));
+        assert_eq!(Some("Venezuela"), country(10.48, -66.90));
+        assert_eq!(None, country(0.0, -30.0));
+    }
+
+    #[test]
+    fn country_of_cities() {
+        for (name, country_name, latitude, longitude) in CITIES {
+            assert_eq!(Some(country_name), country(latitude, longitude), "{}", name);
+        }
+    }
+
+    #[test]
+    fn country_near_borders() {
+        // Como, next to the Swiss border.
+        assert_eq!(Some("Italy"), country(45.81, 9.09));
+        // Huelva, next to the Portuguese border.
+        assert_eq!(Some("Spain"), country(37.26, -6.95));
+        // Aachen, next to Belgium and the Netherlands.
+        assert_eq!(Some("Germany"), country(50.78, 6.08));
+        // Windsor, in the United States box.
+        assert_eq!(Some("Canada"), country(42.31, -83.04));
+        // Austin, in the Mexico box and far from any known city.
+        assert_eq!(Some("United States"), country(30.27, -97.74));
+        // Hermosillo, in the United States box and far from any known
+        // city.
+        assert_eq!(Some("Mexico"), country(29.07, -110.96));
+    }
+
+    #[test]
+    fn nearest_city_of_location() {
+        assert_eq!(Some("Madrid"), nearest_city(40.41, -3.70));
+        // Sintra, near Lisbon.
+        assert_eq!(Some("Lisbon"), nearest_city(38.80, -9.38));
+        assert_eq!(Some("Caracas"), nearest_city(10.50, -66.85));
+        assert_eq!(Some("Sydney"), nearest_city(-33.86, 151.21));
+        // Middle of the Atlantic.
+        assert_eq!(None, nearest_city(0.0, -30.0));
+    }
+
+    #[test]
+    fn distance_between_cities() {
+        // Madrid to Barcelona is about 505 km.
+        let distance = distance_km(40.42, -3.70, 41.39, 2.17);
+        assert!((495.0..515.0).contains(&distance), "{}", distance);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::organizer::{copy_file, RenameFn};
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use log::{error, info};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{self, Path, PathBuf};
+
+/// Log of the moved files that allows undoing them. Each line of the
+/// file is a move with the absolute paths of the file before and after
+/// it, `from<TAB>to`.
+pub struct Journal {
+    path: PathBuf,
+    rename: Box<RenameFn>,
+}
+
+impl Journal {
+    /// Creates a journal in the given file, which is created, along
+    /// with its directory, on the first move.
+    pub fn new(path: PathBuf) -> Journal {
+        Journal {
+            path,
+            rename: Box::new(|from, to| fs::rename(from, to)),
+        }
+    }
+
+    /// Appends a move to the journal.
+    pub(crate) fn record(&self, from: &Path, to: &Path) -> Result<()> {
+        let from = path::absolute(from).wrap_err("failed to get absolute source path")?;
+        let to = path::absolute(to).wrap_err("failed to get absolute destination path")?;
+        let line = match (from.to_str(), to.to_str()) {
+            (Some(from), Some(to)) if !format!("{}{}", from, to).contains(['\t', '\n']) => {
+                format!("{}\t{}\n", from, to)
+            }
+            _ => bail!("only unicode paths without tabs or new lines can be journaled"),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            if !dir.as_os_str().is_empty() && !dir.is_dir() {
+                fs::create_dir_all(dir).wrap_err("failed to create journal dir")?;
+            }
+        }
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .wrap_err("failed to open journal")?
+            .write_all(line.as_bytes())
+            .wrap_err("failed to write to journal")
+    }
+
+    /// Moves the journaled files back to where they were, the last
+    /// moved first, recreating their source directories. The moves that
+    /// can't be undone are reported and kept in the journal, so undoing
+    /// can be retried. Once all are undone the journal is removed.
+    pub fn undo(&self) -> Result<()> {
+        let content = fs::read_to_string(&self.path)
+            .wrap_err_with(|| format!("failed to read journal {:?}", self.path))?;
+
+        let mut pending: Vec<&str> = Vec::new();
+        for line in content.lines().rev().filter(|line| !line.is_empty()) {
+            if let Err(e) = self.undo_move(line) {
+                error!("{:?}", e);
+                pending.push(line);
+            }
+        }
+
+        if pending.is_empty() {
+            return fs::remove_file(&self.path).wrap_err("failed to remove journal");
+        }
+        pending.reverse();
+        fs::write(&self.path, format!("{}\n", pending.join("\n")))
+            .wrap_err("failed to update journal")?;
+        bail!("{} moves couldn't be undone", pending.len())
+    }
+
+    /// Renames the file back to its source. Renaming doesn't work across
+    /// filesystems, like when the file was moved from an SD card, in
+    /// which case it's copied back and then removed from the destination.
+    fn undo_move(&self, line: &str) -> Result<()> {
+        let (from, to) = line
+            .split_once('\t')
+            .ok_or_else(|| eyre!("invalid journal line {:?}", line))?;
+        let (from, to) = (Path::new(from), Path::new(to));
+        if from.exists() {
+            bail!("failed to move {:?} back, {:?} already exists", to, from);
+        }
+        if let Some(dir) = from.parent() {
+            if !dir.is_dir() {
+                fs::create_dir_all(dir)
+                    .wrap_err_with(|| format!("failed to recreate source dir {:?}", dir))?;
+            }
+        }
+        match (self.rename)(to, from) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                info!(
+                    "{:?} is in another filesystem than {:?}, copying and removing it instead",
+                    to, from
+                );
+                copy_file(to, from)
+                    .and_then(|()| {
+                        fs::remove_file(to).wrap_err("failed to remove file after copying it back")
+                    })
+                    .wrap_err_with(|| format!("failed to move {:?} back to {:?}", to, from))
+            }
+            Err(e) => {
+                Err(e).wrap_err_with(|| format!("failed to move {:?} back to {:?}", to, from))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn undo_across_filesystems() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let from = src.path().join("sd-card").join("photo.jpg");
+        let to = dst.path().join("photo.jpg");
+        fs::write(&to, "photo").unwrap();
+        let journal_path = dst.path().join("journal");
+        fs::write(
+            &journal_path,
+            format!("{}\t{}\n", from.to_str().unwrap(), to.to_str().unwrap()),
+        )
+        .unwrap();
+
+        let mut journal = Journal::new(journal_path.clone());
+        journal.rename = Box::new(|_, _| Err(io::ErrorKind::CrossesDevices.into()));
+        journal.undo().unwrap();
+
+        assert_eq!("photo", fs::read_to_string(&from).unwrap());
+        assert!(!to.exists());
+        assert!(!journal_path.exists());
+    }
+}
@@ -1,6 +1,28 @@
+pub mod config;
 mod date;
+mod dhash;
 mod directory;
+mod error;
+mod geo;
+mod journal;
+mod manifest;
+mod motion;
 mod organizer;
-pub use organizer::photos::PhotoOrganizer;
-pub use organizer::videos::VideoOrganizer;
-pub use organizer::{MediaTypeOrganizer, Organizer};
+mod orientation;
+mod plan;
+mod progress;
+pub use date::{Date, Locale};
+pub use error::OrganizerError;
+pub use journal::Journal;
+pub use manifest::write_manifest;
+pub use organizer::audio::AudioOrganizer;
+pub use organizer::fallback::FallbackOrganizer;
+pub use organizer::photos::{
+    DateSource, ExifDateTag, ExifTimezone, PhotoOrganizer, TakeoutSidecar,
+};
+pub use organizer::videos::{ResolutionTier, VideoOrganizer};
+pub use organizer::{
+    ConflictStrategy, Counts, FileReport, MediaTypeOrganizer, Organizer, OrganizerBuilder, Report,
+    TransferMode, UnicodeNormalization,
+};
+pub use plan::{Plan, PlannedMove};
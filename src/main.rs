@@ -1,42 +1,503 @@
-mod config;
-use ::the_media_organizer::{MediaTypeOrganizer, Organizer, PhotoOrganizer, VideoOrganizer};
+use ::the_media_organizer::{
+    config, write_manifest, AudioOrganizer, ConflictStrategy, ExifTimezone, FallbackOrganizer,
+    Journal, MediaTypeOrganizer, Organizer, PhotoOrganizer, Plan, TransferMode, VideoOrganizer,
+};
 use color_eyre::eyre::{bail, Result, WrapErr};
+use log::{info, LevelFilter, Log, Metadata, Record};
 use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How long a watched file has to stay unchanged to be organized.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Logs to stderr and appends the records up to its level to a file,
+/// with their time, for runs nobody watches like the ones of cron.
+struct FileLogger {
+    stderr: env_logger::Logger,
+    file: Mutex<fs::File>,
+    level: LevelFilter,
+}
+
+impl FileLogger {
+    fn init(stderr: env_logger::Logger, path: &Path, level: LevelFilter) -> Result<()> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("failed to open log file {:?}", path))?;
+        log::set_max_level(stderr.filter().max(level));
+        log::set_boxed_logger(Box::new(FileLogger {
+            stderr,
+            file: Mutex::new(file),
+            level,
+        }))
+        .wrap_err("failed to set logger")
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level || self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.stderr.log(record);
+        if record.level() > self.level {
+            return;
+        }
+        let time = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let mut file = self.file.lock().expect("log file lock poisoned");
+        // There is nowhere left to report failing to log.
+        let _ = writeln!(
+            file,
+            "{} {} {}: {}",
+            time,
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        let _ = self.file.lock().expect("log file lock poisoned").flush();
+    }
+}
 
 /// Loads the config and runs the organizers
 fn main() -> Result<(), color_eyre::Report> {
     color_eyre::install()?;
 
+    if let Some(config_file) =
+        config::init_config(env::args_os()).wrap_err("error writing default config")?
+    {
+        println!("Default config written to {:?}", config_file);
+        return Ok(());
+    }
+
     let config = config::get_config(env::args_os()).wrap_err("error getting config")?;
-    println!("Media Organizer configuration loaded");
+    let level = match config.verbosity {
+        _ if config.quiet => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+    let stderr = env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .parse_default_env()
+        .build();
+    match &config.log_file {
+        // Verbose mode logs its details to the file too, otherwise only
+        // the warnings and errors are.
+        Some(log_file) => FileLogger::init(
+            stderr,
+            log_file,
+            if config.verbosity > 0 {
+                level
+            } else {
+                LevelFilter::Warn
+            },
+        )?,
+        None => {
+            log::set_max_level(stderr.filter());
+            log::set_boxed_logger(Box::new(stderr)).wrap_err("failed to set logger")?;
+        }
+    }
+    info!("Media Organizer configuration loaded");
 
-    match config.media_src.to_str() {
-        Some(dir) => println!("Media source directory: {}", dir),
-        None => bail!("media source directory is not a valid unicode path"),
+    if config.undo {
+        let journal = match config.journal {
+            Some(journal) => journal,
+            None => bail!("undoing requires a journal"),
+        };
+        info!("Undoing the moves recorded in journal: {:?}", journal);
+        return Journal::new(journal).undo();
+    }
+
+    for media_src in &config.media_src {
+        info!("Media source directory: {}", media_src.display());
     }
     let mut organizers: Vec<Box<dyn MediaTypeOrganizer>> = Vec::new();
 
-    match config.photos_dst.to_str() {
-        Some(dir) => {
-            println!(
-                "Photo organizer enable, photos will be organized in directory: {}",
-                dir
-            );
-            organizers.push(Box::new(PhotoOrganizer::new(config.photos_dst)));
+    info!(
+        "Photo organizer enable, photos will be organized in directory: {}",
+        config.photos_dst.display()
+    );
+    let mut photo_organizer = PhotoOrganizer::new(config.photos_dst)
+        .with_locale(config.locale)
+        .with_respect_date_folders(config.respect_date_folders)
+        .with_group_by_location(config.group_by_location)
+        .with_group_by_city(config.group_by_city)
+        .with_group_by_camera(config.group_by_camera)
+        .with_group_bursts(config.group_bursts)
+        .with_mtime_fallback(config.use_mtime_fallback)
+        .with_rename_by_date(config.rename_by_date)
+        .with_fix_orientation(config.fix_orientation);
+    if config.fix_orientation {
+        info!("Rotated JPEGs will be re-encoded upright");
+    }
+    if config.group_bursts {
+        info!("The shots of bursts will be grouped together");
+    }
+    if config.group_by_camera {
+        info!("Photos will be grouped by the camera that took them");
+    }
+    if config.use_mtime_fallback {
+        info!(
+            "Photos, videos and audio without a date will be organized by their modification time"
+        );
+    }
+    if config.group_by_location {
+        info!("Photos will be grouped by the country they were taken in");
+    }
+    if config.group_by_city {
+        info!("Photos will be grouped by the city nearest to where they were taken");
+    }
+    if let Some(placeholder) = &config.unknown_placeholder {
+        photo_organizer = photo_organizer.with_unknown_placeholder(placeholder.clone());
+    }
+    if let Some(extensions) = config.photo_extensions {
+        info!(
+            "Photos with the extensions {:?} will be organized",
+            extensions
+        );
+        photo_organizer = photo_organizer.with_extensions(extensions);
+    }
+    if let Some(regex) = config.photo_filename_regex {
+        info!("Photos dates will be taken from names matching: {}", regex);
+        photo_organizer = photo_organizer.with_filename_regex(regex);
+    }
+    if let Some(sources) = config.date_sources {
+        info!("Photos dates will be taken from: {:?}", sources);
+        photo_organizer = photo_organizer.with_date_sources(sources);
+    }
+    if let Some(tags) = config.exif_date_tags {
+        info!("Photos dates will be taken from the exif tags: {:?}", tags);
+        photo_organizer = photo_organizer.with_exif_date_tags(tags);
+    }
+    if let Some(takeout_sidecar) = config.takeout_sidecar {
+        info!(
+            "Google Takeout sidecars dates will be used: {:?}",
+            takeout_sidecar
+        );
+        photo_organizer = photo_organizer.with_takeout_sidecar(takeout_sidecar);
+    }
+    if let Some(exif_timezone) = config.exif_timezone {
+        if exif_timezone == ExifTimezone::Normalize {
+            info!("Photos exif dates will be converted to UTC");
+        }
+        photo_organizer = photo_organizer.with_exif_timezone(exif_timezone);
+    }
+    if let Some(folder_format) = config.folder_format {
+        info!("Photos folder format: {}", folder_format);
+        photo_organizer = photo_organizer.with_folder_format(folder_format);
+    }
+    if let Some(distance) = config.near_duplicate_distance {
+        info!(
+            "Photos within {} bits of one in the destination will be moved to duplicates-review",
+            distance
+        );
+        photo_organizer = photo_organizer.with_near_duplicates(distance);
+    }
+    if let Some((width, height)) = config.min_resolution {
+        info!("Photos smaller than {}x{} will be skipped", width, height);
+        photo_organizer = photo_organizer.with_min_resolution(width, height);
+    }
+    if let Some(dir) = &config.unknown_date_dir {
+        photo_organizer = photo_organizer.with_unknown_date_dir(dir.clone());
+    }
+    photo_organizer =
+        photo_organizer.with_destination_overrides(config.destination_overrides.clone());
+    if config.extract_motion_video {
+        info!("Motion photo videos will be extracted to the videos directory");
+        photo_organizer = photo_organizer.with_motion_video_dst(config.videos_dst.clone());
+    }
+    organizers.push(Box::new(photo_organizer));
+
+    info!(
+        "Video organizer enable, videos will be organized in directory: {}",
+        config.videos_dst.display()
+    );
+    let mut video_organizer = VideoOrganizer::new(config.videos_dst)
+        .with_respect_date_folders(config.respect_date_folders)
+        .with_group_by_month(config.video_group_by_month)
+        .with_locale(config.locale)
+        .with_mtime_fallback(config.use_mtime_fallback)
+        .with_rename_by_date(config.rename_by_date);
+    if let Some(regex) = config.video_filename_regex {
+        info!("Videos dates will be taken from names matching: {}", regex);
+        video_organizer = video_organizer.with_filename_regex(regex);
+    }
+    if config.video_group_by_month {
+        info!("Videos will be organized by month");
+    }
+    if let Some(dir) = &config.unknown_date_dir {
+        video_organizer = video_organizer.with_unknown_date_dir(dir.clone());
+    }
+    if let Some(tiers) = config.resolution_tiers {
+        info!("Videos will be grouped by resolution");
+        video_organizer = video_organizer.with_resolution_tiers(tiers);
+    }
+    if let Some(placeholder) = &config.unknown_placeholder {
+        video_organizer = video_organizer.with_unknown_placeholder(placeholder.clone());
+    }
+    video_organizer =
+        video_organizer.with_destination_overrides(config.destination_overrides.clone());
+    organizers.push(Box::new(video_organizer));
+
+    if let Some(audio_dst) = config.audio_dst {
+        info!(
+            "Audio organizer enable, audio files will be organized in directory: {}",
+            audio_dst.display()
+        );
+        let mut audio_organizer = AudioOrganizer::new(audio_dst)
+            .with_locale(config.locale)
+            .with_mtime_fallback(config.use_mtime_fallback)
+            .with_destination_overrides(config.destination_overrides.clone());
+        if let Some(dir) = &config.unknown_date_dir {
+            audio_organizer = audio_organizer.with_unknown_date_dir(dir.clone());
         }
-        None => bail!("media source directory is not a valid unicode path"),
-    };
+        organizers.push(Box::new(audio_organizer));
+    }
+    // Last, so it only gets the files the other organizers don't.
+    if let Some(misc_dst) = config.misc_dst {
+        info!(
+            "Files no other organizer handles will be moved to directory: {}",
+            misc_dst.display()
+        );
+        organizers.push(Box::new(
+            FallbackOrganizer::new(misc_dst)
+                .with_destination_overrides(config.destination_overrides.clone()),
+        ));
+    }
+    for (extension, dir) in &config.destination_overrides {
+        info!(
+            "Files with the extension {} will be organized in {:?}",
+            extension, dir
+        );
+    }
+    if let Some(dir) = &config.unknown_date_dir {
+        info!(
+            "Files without a date will be moved to {:?} in their destination",
+            dir
+        );
+    }
+    if config.respect_date_folders {
+        info!("Folders named like a date will be kept together");
+    }
+    if config.rename_by_date {
+        info!("Photos and videos will be renamed with their date as prefix");
+    }
 
-    match config.videos_dst.to_str() {
-        Some(dir) => {
-            println!(
-                "Video organizer enable, videos will be organized in directory: {}",
-                dir
-            );
-            organizers.push(Box::new(VideoOrganizer::new(config.videos_dst)));
+    let mut organizer = Organizer::new(organizers)
+        .with_retries(config.retries, config.retry_delay)
+        .with_dry_run(config.dry_run)
+        .with_transfer_mode(config.transfer_mode)
+        .with_conflict_strategy(config.conflict_strategy)
+        .with_dedup(config.dedup)
+        .with_prune_empty(config.prune_empty)
+        .with_verify(config.verify);
+    if config.verify {
+        info!("Files will be verified after transferring them");
+    }
+    if config.prune_empty {
+        info!("Directories of the media source left empty will be removed");
+    }
+    if let Some(journal) = config.journal {
+        info!("Moves will be recorded in journal: {:?}", journal);
+        organizer = organizer.with_journal(Journal::new(journal));
+    }
+    match config.transfer_mode {
+        TransferMode::Move => (),
+        TransferMode::Copy => info!("Files will be copied, the source is left untouched"),
+        TransferMode::Hardlink => {
+            info!("Files will be hardlinked, the source is left untouched")
         }
-        None => bail!("media source directory is not a valid unicode path"),
     }
-    let organizer = Organizer::new(organizers);
-    organizer.organize(config.media_src)
+    match config.conflict_strategy {
+        ConflictStrategy::Error => (),
+        ConflictStrategy::Skip => {
+            info!("Files with a name taken in the destination are skipped")
+        }
+        ConflictStrategy::Overwrite => {
+            info!("Files with a name taken in the destination overwrite the existing file")
+        }
+        ConflictStrategy::Rename => {
+            info!("Files with a name taken in the destination are renamed")
+        }
+    }
+    if config.dedup {
+        info!("Files identical to the one with the same name in the destination will be treated as duplicates");
+    }
+    if config.trash_duplicates {
+        info!("Files whose name is taken in the destination will be sent to the trash");
+        organizer = organizer.with_trash_duplicates(true);
+    }
+    if config.dry_run {
+        info!("Dry run, no files will be moved");
+    }
+    if config.interactive {
+        organizer = organizer.with_interactive(Box::new(io::BufReader::new(io::stdin())));
+    } else if !config.quiet {
+        organizer = organizer.with_progress(true);
+    }
+
+    if let Some(flat_dst) = config.flat_dst {
+        info!(
+            "Organized files will also be linked in directory: {}",
+            flat_dst.display()
+        );
+        organizer = organizer.with_flat_dir(flat_dst);
+    }
+
+    if config.flatten {
+        info!(
+            "Files will get a name of their own in the destination, whatever their source folder"
+        );
+        organizer = organizer.with_flatten(true);
+    }
+
+    if config.preserve_structure {
+        info!("The source folders of the files will be kept under their date folder");
+        organizer = organizer.with_preserve_structure(true);
+    }
+
+    if config.keep_live_photos {
+        info!("Live Photo videos will be organized next to their photo");
+        organizer = organizer.with_keep_live_photos(true);
+    }
+
+    if config.normalize_extension {
+        info!("Destination file extensions will be lowercased");
+        organizer = organizer.with_normalize_extension(true);
+    }
+
+    if config.sanitize_paths {
+        info!("Destination names will be sanitized for FAT and exFAT");
+        organizer = organizer.with_sanitize_paths(true);
+    }
+
+    if let Some(form) = config.unicode_normalization {
+        info!("Destination file names will be normalized to {:?}", form);
+        organizer = organizer.with_unicode_normalization(form);
+    }
+
+    if config.jobs > 1 {
+        info!("{} files will be organized at the same time", config.jobs);
+        organizer = organizer.with_jobs(config.jobs);
+    }
+
+    if config.newest_first {
+        info!("The most recently modified files will be organized first");
+        organizer = organizer.with_newest_first(true);
+    }
+
+    if let Some(min_free_space) = config.min_free_space {
+        info!(
+            "The run will be aborted when less than {} bytes are free in the destination",
+            min_free_space
+        );
+        organizer = organizer.with_min_free_space(min_free_space);
+    }
+
+    if let Some(time) = config.modified_after {
+        organizer = organizer.with_modified_after(time);
+    }
+
+    if let Some(time) = config.modified_before {
+        organizer = organizer.with_modified_before(time);
+    }
+
+    if !config.exclude.is_empty() {
+        info!(
+            "Files matching {:?} will be ignored",
+            config
+                .exclude
+                .iter()
+                .map(|p| p.as_str())
+                .collect::<Vec<_>>()
+        );
+        organizer = organizer.with_exclude(config.exclude);
+    }
+
+    if config.no_recurse {
+        info!("Only the files directly in the media source will be organized");
+        organizer = organizer.with_no_recurse(true);
+    }
+
+    if config.skip_empty {
+        info!("Empty files will be left in the media source");
+        organizer = organizer.with_skip_empty(true);
+    }
+
+    if let Some(min_size) = config.min_size {
+        info!("Files smaller than {} bytes will be ignored", min_size);
+        organizer = organizer.with_min_size(min_size);
+    }
+
+    if let Some(max_size) = config.max_size {
+        info!("Files bigger than {} bytes will be ignored", max_size);
+        organizer = organizer.with_max_size(max_size);
+    }
+
+    if config.report_json.is_some() || config.manifest.is_some() {
+        organizer = organizer.with_file_reports(true);
+    }
+
+    if config.preview {
+        print!("{}", organizer.preview(&config.media_src));
+        return Ok(());
+    }
+
+    if config.find_duplicates {
+        let groups = organizer.find_duplicates(&config.media_src);
+        for group in &groups {
+            for file in group {
+                println!("{}", file.display());
+            }
+            println!();
+        }
+        println!("Found {} groups of identical files", groups.len());
+        return Ok(());
+    }
+
+    if let Some(plan_file) = &config.plan {
+        let plan = organizer.plan(&config.media_src);
+        plan.write(plan_file)?;
+        println!("Planned {} moves in {:?}", plan.moves.len(), plan_file);
+        return Ok(());
+    }
+
+    let report = match &config.apply {
+        Some(plan_file) => {
+            info!("Applying the plan: {:?}", plan_file);
+            organizer.apply(&Plan::read(plan_file)?)?
+        }
+        None => organizer.organize(&config.media_src)?,
+    };
+    println!("Done, {}", report);
+    if let Some(report_json) = &config.report_json {
+        let json = serde_json::to_string_pretty(&report).wrap_err("failed to serialize report")?;
+        fs::write(report_json, json)
+            .wrap_err_with(|| format!("failed to write report to {:?}", report_json))?;
+        info!("Report written to {:?}", report_json);
+    }
+    if let Some(manifest) = &config.manifest {
+        let rows = write_manifest(&report, manifest)?;
+        info!("Manifest of {} files written to {:?}", rows, manifest);
+    }
+
+    if config.watch {
+        info!("Watching the media source for new files, press Ctrl+C to stop");
+        // Watching only stops when the process is interrupted, which ends
+        // it without a summary.
+        organizer.watch(&config.media_src, WATCH_DEBOUNCE, &AtomicBool::new(false))?;
+    }
+    Ok(())
 }
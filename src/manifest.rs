@@ -0,0 +1,130 @@
+use crate::organizer::{file_hash, Report};
+use color_eyre::eyre::{Result, WrapErr};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Row of the manifest, one per moved file.
+#[derive(Serialize)]
+struct ManifestRow<'a> {
+    original_path: &'a Path,
+    new_path: &'a Path,
+    detected_date: Option<&'a str>,
+    organizer: Option<&'static str>,
+    size: u64,
+    sha256: String,
+}
+
+/// Writes a CSV inventory of the files moved in a run to `path`, with
+/// the columns `original_path,new_path,detected_date,organizer,size,sha256`.
+/// The size and hash are the ones of the file in the destination, or of
+/// the source one in dry runs. The report needs the details of every
+/// file, see [`crate::Organizer::with_file_reports`]. Returns the number
+/// of rows written.
+pub fn write_manifest(report: &Report, path: &Path) -> Result<usize> {
+    let mut writer = csv::Writer::from_path(path)
+        .wrap_err_with(|| format!("failed to create manifest {:?}", path))?;
+    let mut rows = 0;
+    for file in report.files.iter().filter(|file| file.outcome == "moved") {
+        let new_path = match &file.destination {
+            Some(destination) => destination,
+            None => continue,
+        };
+        let hashed = if new_path.exists() {
+            new_path
+        } else {
+            &file.path
+        };
+        let size = fs::metadata(hashed)
+            .wrap_err_with(|| format!("failed to read size of {:?}", hashed))?
+            .len();
+        let sha256 = file_hash(hashed)?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        writer
+            .serialize(ManifestRow {
+                original_path: &file.path,
+                new_path,
+                detected_date: file.date.as_deref(),
+                organizer: file.organizer,
+                size,
+                sha256,
+            })
+            .wrap_err("failed to write manifest row")?;
+        rows += 1;
+    }
+    writer.flush().wrap_err("failed to write manifest")?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Organizer, PhotoOrganizer, VideoOrganizer};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn write_manifest_of_moved_files() {
+        let src = TempDir::new().unwrap();
+        let photos_dst = TempDir::new().unwrap();
+        let videos_dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            src.path().join("20200829_205420.mp4"),
+        )
+        .unwrap();
+        fs::write(src.path().join("notes.txt"), "not media").unwrap();
+
+        let report = Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(photos_dst.path().to_path_buf())),
+            Box::new(VideoOrganizer::new(videos_dst.path().to_path_buf())),
+        ])
+        .with_file_reports(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+        let manifest = src.path().join("manifest.csv");
+        assert_eq!(2, write_manifest(&report, &manifest).unwrap());
+
+        let mut reader = csv::Reader::from_path(&manifest).unwrap();
+        assert_eq!(
+            vec![
+                "original_path",
+                "new_path",
+                "detected_date",
+                "organizer",
+                "size",
+                "sha256"
+            ],
+            reader.headers().unwrap().iter().collect::<Vec<_>>()
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(2, rows.len());
+        let camera = rows
+            .iter()
+            .find(|row| row[0] == *src.path().join("camera.jpg").to_str().unwrap())
+            .unwrap();
+        let new_path = photos_dst
+            .path()
+            .join("2019")
+            .join("01 - January")
+            .join("camera.jpg");
+        assert_eq!(new_path.to_str().unwrap(), &camera[1]);
+        assert_eq!("2019-01", &camera[2]);
+        assert_eq!("photos", &camera[3]);
+        assert_eq!(
+            fs::metadata(&new_path).unwrap().len().to_string(),
+            &camera[4]
+        );
+        assert_eq!(64, camera[5].len());
+        let video = rows
+            .iter()
+            .find(|row| row[0] == *src.path().join("20200829_205420.mp4").to_str().unwrap())
+            .unwrap();
+        assert_eq!("videos", &video[3]);
+        assert_ne!(&camera[5], &video[5]);
+    }
+}
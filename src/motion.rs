@@ -0,0 +1,105 @@
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+/// Samsung motion photos append this marker followed by the MP4 video.
+const SAMSUNG_MARKER: &[u8] = b"MotionPhoto_Data";
+
+/// Returns the MP4 video embedded in a motion photo, if any.
+///
+/// Motion photos are JPEG (or HEIC) files with a video appended after
+/// the image data. Samsung places the video right after a
+/// `MotionPhoto_Data` marker. Google declares the video size in the XMP
+/// metadata, either with the older `MicroVideoOffset` attribute or with
+/// the `MotionPhoto` container directory, both counting from the end of
+/// the file. If the markers are present but the size can't be read, the
+/// video is located by its `ftyp` box.
+pub fn find_motion_video(data: &[u8]) -> Option<&[u8]> {
+    if let Some(pos) = find(data, SAMSUNG_MARKER) {
+        let video = &data[pos + SAMSUNG_MARKER.len()..];
+        return if video.is_empty() { None } else { Some(video) };
+    }
+
+    if let Some(length) = xmp_video_length(data) {
+        if length > 0 && length < data.len() {
+            return Some(&data[data.len() - length..]);
+        }
+    }
+
+    if find(data, b"MicroVideo").is_none() && find(data, b"MotionPhoto").is_none() {
+        return None;
+    }
+    // The box size comes right before the `ftyp` type.
+    rfind(data, b"ftyp")
+        .filter(|pos| *pos >= 4)
+        .map(|pos| &data[pos - 4..])
+}
+
+fn xmp_video_length(data: &[u8]) -> Option<usize> {
+    static MICRO_VIDEO: OnceLock<Regex> = OnceLock::new();
+    static MOTION_PHOTO: OnceLock<Regex> = OnceLock::new();
+
+    let micro_video = MICRO_VIDEO.get_or_init(|| {
+        Regex::new(r#"MicroVideoOffset(?:="|>)(\d+)"#).expect("invalid micro video regex")
+    });
+    if let Some(captures) = micro_video.captures(data) {
+        return parse_length(&captures[1]);
+    }
+
+    let motion_photo = MOTION_PHOTO.get_or_init(|| {
+        Regex::new(r#"(?s)Item:Semantic="MotionPhoto".*?Item:Length="(\d+)""#)
+            .expect("invalid motion photo regex")
+    });
+    motion_photo
+        .captures(data)
+        .and_then(|captures| parse_length(&captures[1]))
+}
+
+fn parse_length(digits: &[u8]) -> Option<usize> {
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).rposition(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO: &[u8] = b"\x00\x00\x00\x18ftypmp42\x00\x00\x00\x00mp42isom";
+
+    #[test]
+    fn samsung_motion_photo() {
+        let data = [b"\xff\xd8jpeg data\xff\xd9".as_ref(), SAMSUNG_MARKER, VIDEO].concat();
+        assert_eq!(Some(VIDEO), find_motion_video(&data));
+    }
+
+    #[test]
+    fn google_micro_video() {
+        let xmp = format!(
+            r#"<x GCamera:MicroVideo="1" GCamera:MicroVideoOffset="{}"/>"#,
+            VIDEO.len()
+        );
+        let data = [b"\xff\xd8".as_ref(), xmp.as_bytes(), b"\xff\xd9", VIDEO].concat();
+        assert_eq!(Some(VIDEO), find_motion_video(&data));
+    }
+
+    #[test]
+    fn google_motion_photo() {
+        let xmp = format!(
+            r#"<x GCamera:MotionPhoto="1"><Item:Semantic="Primary"/><li Item:Semantic="MotionPhoto" Item:Length="{}"/></x>"#,
+            VIDEO.len()
+        );
+        let data = [b"\xff\xd8".as_ref(), xmp.as_bytes(), b"\xff\xd9", VIDEO].concat();
+        assert_eq!(Some(VIDEO), find_motion_video(&data));
+    }
+
+    #[test]
+    fn not_a_motion_photo() {
+        assert_eq!(None, find_motion_video(b"\xff\xd8jpeg data\xff\xd9"));
+    }
+}
@@ -1,138 +1,4083 @@
+pub mod audio;
+pub mod fallback;
 pub mod photos;
 pub mod videos;
+use crate::date::{Date, Locale};
 use crate::directory::FilesIter;
-use color_eyre::eyre::{eyre, Result, WrapErr};
+use crate::error::OrganizerError;
+use crate::journal::Journal;
+use crate::plan::{Plan, PlannedMove};
+use crate::progress::Progress;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
+use filetime::FileTime;
+use glob::Pattern;
+use log::{debug, error, info, warn};
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fmt;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, Write};
+use std::iter;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use unicode_normalization::UnicodeNormalization as _;
 
 /// Handler of media files. It determines what and how to organize.
-pub trait MediaTypeOrganizer {
+pub trait MediaTypeOrganizer: Send + Sync {
     /// If the media file should be organize.
     fn should_organize(&self, item: &Path) -> bool;
+    /// Name of the organizer in the [`Report`] of a run.
+    fn name(&self) -> &'static str;
     /// Destination directory where the media files should be moved to.
     fn destination_dir(&self, item: &Path) -> Result<PathBuf>;
+    /// Date the media file is organized by, for the [`FileReport`] of
+    /// a run. Defaults to none.
+    fn date(&self, _item: &Path) -> Option<Date> {
+        None
+    }
+    /// Name of the media file inside its destination directory. Defaults
+    /// to the original name.
+    fn destination_name(&self, item: &Path) -> Result<OsString> {
+        item.file_name()
+            .map(OsStr::to_os_string)
+            .ok_or_else(|| eyre!("failed to get file name"))
+    }
+    /// Why the media file is left in the source, counted as skipped,
+    /// instead of organized. Defaults to never.
+    fn skip_reason(&self, _item: &Path) -> Option<String> {
+        None
+    }
+    /// Called once the media file was moved, `item` being its new path.
+    fn after_move(&self, _item: &Path) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// If the item is in a folder whose name starts with a date, like
+/// `2019-07-trip` or `2019_07`, returns that date and the folder name.
+pub(crate) fn date_folder(item: &Path) -> Option<(Date, &OsStr)> {
+    static DATE_FOLDER_REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex =
+        DATE_FOLDER_REGEX.get_or_init(|| Regex::new(r"^(\d{4})[-_](\d{2})(?:[-_ ].*)?$").unwrap());
+
+    let folder = item.parent()?.file_name()?;
+    let name = folder.to_string_lossy();
+    let captures = regex.captures(&name)?;
+    let year: u16 = captures[1].parse().ok()?;
+    let month: u8 = captures[2].parse().ok()?;
+    Date::new(year, month).ok().map(|date| (date, folder))
+}
+
+/// Directory that replaces the destination of the organizers for the
+/// item, looked up by its lowercase extension.
+pub(crate) fn destination_override<'a>(
+    overrides: &'a HashMap<String, PathBuf>,
+    item: &Path,
+) -> Option<&'a Path> {
+    let extension = item.extension()?;
+    overrides
+        .iter()
+        .find(|(overridden, _)| extension.eq_ignore_ascii_case(overridden))
+        .map(|(_, dir)| dir.as_path())
+}
+
+/// If the character isn't allowed in names by some filesystems, like
+/// FAT and exFAT, or by Windows.
+pub(crate) fn is_invalid_in_name(c: char) -> bool {
+    c.is_control() || r#"<>:"/\|?*"#.contains(c)
+}
+
+/// Replaces the characters of the name that aren't valid with `_` and
+/// trims its trailing dots and spaces. Names that are not valid unicode
+/// are left untouched.
+fn sanitize_name(name: &OsStr) -> OsString {
+    let name = match name.to_str() {
+        Some(name) => name,
+        None => return name.to_os_string(),
+    };
+    let sanitized: String = name
+        .chars()
+        .map(|c| if is_invalid_in_name(c) { '_' } else { c })
+        .collect();
+    match sanitized.trim_end_matches([' ', '.']) {
+        "" => OsString::from("_"),
+        sanitized => OsString::from(sanitized),
+    }
+}
+
+/// Name of the item prefixed with its `YYYYMM` date, `camera.jpg` taken
+/// in January 2019 becomes `201901_camera.jpg`. Names that already have
+/// the prefix are kept.
+pub(crate) fn date_prefixed_name(item: &Path, date: Option<Date>) -> Result<OsString> {
+    let date = date.ok_or_else(|| eyre!("failed to get date to rename file"))?;
+    let name = item
+        .file_name()
+        .ok_or_else(|| eyre!("failed to get file name"))?;
+    let prefix = date.format("%Y%m_", Locale::En);
+    if name.to_string_lossy().starts_with(&prefix) {
+        return Ok(name.to_os_string());
+    }
+    let mut prefixed = OsString::from(prefix);
+    prefixed.push(name);
+    Ok(prefixed)
+}
+
+/// Unicode normalization form applied to the destination file names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeNormalization {
+    /// Canonical composition, commonly used on Linux and Windows.
+    Nfc,
+    /// Canonical decomposition, used by macOS.
+    Nfd,
+}
+
+/// How files are transferred to their destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    /// The file is moved, nothing is left in the source.
+    Move,
+    /// The file is copied, the source is left untouched.
+    Copy,
+    /// The file is hardlinked into the destination, the source is left
+    /// untouched. If the link can't be created, for example when the
+    /// destination is in a different filesystem, the file is copied.
+    Hardlink,
+}
+
+impl TransferMode {
+    fn verb(&self) -> &'static str {
+        match self {
+            TransferMode::Move => "move",
+            TransferMode::Copy => "copy",
+            TransferMode::Hardlink => "hardlink",
+        }
+    }
+}
+
+/// What to do when a file with the same name already exists in the
+/// destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// The file is left in the source.
+    Skip,
+    /// The existing file is replaced.
+    Overwrite,
+    /// ` (1)`, ` (2)`, etc. is appended to the file name, before the
+    /// extension, until a free name is found.
+    Rename,
+    /// The file is not organized and an error is reported.
+    Error,
+}
+
+/// Where a file goes, as decided by [`Organizer::destination_path`].
+#[derive(Debug, PartialEq, Eq)]
+enum Destination {
+    /// The file is transferred to this path.
+    Path(PathBuf),
+    /// The file is already where it would be transferred to.
+    Organized,
+    /// A file with the same name exists and the file is left in the
+    /// source.
+    Skip,
+    /// An identical file already exists at this path.
+    Duplicate(PathBuf),
+    /// A different file with the same name exists at this path and the
+    /// file is sent to the trash, see [`Organizer::with_trash_duplicates`].
+    Trash(PathBuf),
+}
+
+/// What happened to a file found in the media source, along with the
+/// name of the organizer that handled it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// Transferred into its destination, or it would be in dry run mode.
+    Moved(&'static str),
+    /// Left in the source on purpose, because its name is taken or it's
+    /// a duplicate.
+    Skipped(&'static str),
+    /// Left in the source because of an error.
+    Errored(&'static str),
+    /// Transferred, but the content in the destination differs from
+    /// the source, see [`Organizer::with_verify`].
+    Corrupted(&'static str),
+    /// Left in the source because it's empty, see
+    /// [`Organizer::with_skip_empty`].
+    Empty(&'static str),
+    /// No organizer handles the file.
+    Unhandled,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Moved(_) => "moved",
+            Outcome::Skipped(_) => "skipped",
+            Outcome::Errored(_) => "errored",
+            Outcome::Corrupted(_) => "corrupted",
+            Outcome::Empty(_) => "empty",
+            Outcome::Unhandled => "unhandled",
+        }
+    }
+
+    fn organizer(&self) -> Option<&'static str> {
+        match self {
+            Outcome::Moved(name)
+            | Outcome::Skipped(name)
+            | Outcome::Errored(name)
+            | Outcome::Corrupted(name)
+            | Outcome::Empty(name) => Some(name),
+            Outcome::Unhandled => None,
+        }
+    }
+}
+
+/// What happened to a single file, see [`Organizer::with_file_reports`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct FileReport {
+    /// Path of the file in the media source.
+    pub path: PathBuf,
+    /// Date the file is organized by, as `YYYY-MM`.
+    pub date: Option<String>,
+    /// [`MediaTypeOrganizer::name`] of the organizer that handled it.
+    pub organizer: Option<&'static str>,
+    /// Where it was transferred, or the identical file it duplicates.
+    pub destination: Option<PathBuf>,
+    /// One of `moved`, `skipped`, `errored`, `corrupted`, `empty` or
+    /// `unhandled`.
+    pub outcome: &'static str,
+}
+
+/// Number of files by what happened to them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub moved: usize,
+    pub skipped: usize,
+    pub errored: usize,
+    pub corrupted: usize,
+    pub empty: usize,
+}
+
+/// Summary of a run, see [`Organizer::organize`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Files moved, copied or hardlinked. In dry run mode the ones that
+    /// would be.
+    pub moved: usize,
+    /// Files left in the source because their name is taken in the
+    /// destination or they are duplicates.
+    pub skipped: usize,
+    /// Files left in the source because of an error.
+    pub errored: usize,
+    /// Files whose content in the destination doesn't match the source.
+    pub corrupted: usize,
+    /// Files left in the source because they are empty.
+    pub empty: usize,
+    /// Files that no organizer handles.
+    pub unhandled: usize,
+    /// The counts of each organizer by [`MediaTypeOrganizer::name`].
+    pub by_organizer: BTreeMap<&'static str, Counts>,
+    /// Every file sorted by path, only when enabled with
+    /// [`Organizer::with_file_reports`].
+    pub files: Vec<FileReport>,
+}
+
+impl Report {
+    fn add(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Moved(name) => {
+                self.moved += 1;
+                self.by_organizer.entry(name).or_default().moved += 1;
+            }
+            Outcome::Skipped(name) => {
+                self.skipped += 1;
+                self.by_organizer.entry(name).or_default().skipped += 1;
+            }
+            Outcome::Errored(name) => {
+                self.errored += 1;
+                self.by_organizer.entry(name).or_default().errored += 1;
+            }
+            Outcome::Corrupted(name) => {
+                self.corrupted += 1;
+                self.by_organizer.entry(name).or_default().corrupted += 1;
+            }
+            Outcome::Empty(name) => {
+                self.empty += 1;
+                self.by_organizer.entry(name).or_default().empty += 1;
+            }
+            Outcome::Unhandled => self.unhandled += 1,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "moved: {}, skipped: {}, errored: {}, corrupted: {}, empty: {}, unhandled: {}",
+            self.moved, self.skipped, self.errored, self.corrupted, self.empty, self.unhandled
+        )?;
+        for (name, counts) in &self.by_organizer {
+            write!(
+                f,
+                "\n  {}: moved: {}, skipped: {}, errored: {}, corrupted: {}, empty: {}",
+                name, counts.moved, counts.skipped, counts.errored, counts.corrupted, counts.empty
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Answers to the confirmation asked before each transfer, see
+/// [`Organizer::with_interactive`].
+struct Confirmation {
+    answers: Box<dyn BufRead + Send>,
+    yes_to_all: bool,
+}
+
+impl Confirmation {
+    /// Prints the question and reads the answer, `y` for yes, `a` for
+    /// yes to this and all the remaining files and anything else, or no
+    /// more answers, for no.
+    fn confirm(&mut self, question: &str) -> Result<bool> {
+        if self.yes_to_all {
+            return Ok(true);
+        }
+        print!("{} [y/N/a] ", question);
+        io::stdout()
+            .flush()
+            .wrap_err("failed to print confirmation")?;
+        let mut answer = String::new();
+        self.answers
+            .read_line(&mut answer)
+            .wrap_err("failed to read confirmation")?;
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => Ok(true),
+            "a" | "all" => {
+                self.yes_to_all = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Returns the available space in bytes of the filesystem of a dir.
+type FreeSpaceFn = dyn Fn(&Path) -> io::Result<u64> + Send + Sync;
+
+/// How often [`Organizer::watch`] checks whether it has to stop.
+const WATCH_STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Renames a file, replacing the destination if it exists.
+pub(crate) type RenameFn = dyn Fn(&Path, &Path) -> io::Result<()> + Send + Sync;
+
+/// Hashes the content of a file.
+type HashFn = dyn Fn(&Path) -> Result<Vec<u8>> + Send + Sync;
+
+/// Hash of a destination file compared for dedup, with the
+/// modification time and size it had when hashed.
+struct CachedHash {
+    modified: SystemTime,
+    len: u64,
+    hash: Vec<u8>,
+}
+
+/// Sends a file to the trash of the system.
+type TrashFn = dyn Fn(&Path) -> Result<(), trash::Error> + Send + Sync;
+
+/// Builds an [`Organizer`] adding its media type organizers one at a
+/// time, an alternative to [`Organizer::new`] and its `with_` methods.
+///
+/// ```
+/// use std::path::PathBuf;
+/// use the_media_organizer::{
+///     ConflictStrategy, OrganizerBuilder, PhotoOrganizer, TransferMode, VideoOrganizer,
+/// };
+///
+/// let organizer = OrganizerBuilder::new()
+///     .add_organizer(PhotoOrganizer::new(PathBuf::from("/my-photos")))
+///     .add_organizer(VideoOrganizer::new(PathBuf::from("/my-videos")))
+///     .transfer_mode(TransferMode::Copy)
+///     .conflict_strategy(ConflictStrategy::Rename)
+///     .dry_run(true)
+///     .build()?;
+/// # Ok::<(), color_eyre::Report>(())
+/// ```
+///
+/// The organizers are tried in the order they are added, building
+/// without any is an error:
+///
+/// ```
+/// use the_media_organizer::OrganizerBuilder;
+///
+/// assert!(OrganizerBuilder::new().build().is_err());
+/// ```
+pub struct OrganizerBuilder {
+    organizer: Organizer,
+}
+
+impl OrganizerBuilder {
+    pub fn new() -> OrganizerBuilder {
+        OrganizerBuilder {
+            organizer: Organizer::new(Vec::new()),
+        }
+    }
+
+    /// Adds a media type organizer, tried after the ones already added.
+    pub fn add_organizer(
+        mut self,
+        media_type_organizer: impl MediaTypeOrganizer + 'static,
+    ) -> OrganizerBuilder {
+        self.organizer
+            .media_type_organizers
+            .push(Box::new(media_type_organizer));
+        self
+    }
+
+    /// See [`Organizer::with_dry_run`].
+    pub fn dry_run(mut self, dry_run: bool) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_dry_run(dry_run);
+        self
+    }
+
+    /// See [`Organizer::with_transfer_mode`].
+    pub fn transfer_mode(mut self, transfer_mode: TransferMode) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_transfer_mode(transfer_mode);
+        self
+    }
+
+    /// See [`Organizer::with_conflict_strategy`].
+    pub fn conflict_strategy(mut self, conflict_strategy: ConflictStrategy) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_conflict_strategy(conflict_strategy);
+        self
+    }
+
+    /// See [`Organizer::with_dedup`].
+    pub fn dedup(mut self, dedup: bool) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_dedup(dedup);
+        self
+    }
+
+    /// See [`Organizer::with_verify`].
+    pub fn verify(mut self, verify: bool) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_verify(verify);
+        self
+    }
+
+    /// See [`Organizer::with_retries`].
+    pub fn retries(mut self, retries: u32, delay: Duration) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_retries(retries, delay);
+        self
+    }
+
+    /// See [`Organizer::with_journal`].
+    pub fn journal(mut self, journal: Journal) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_journal(journal);
+        self
+    }
+
+    /// See [`Organizer::with_jobs`].
+    pub fn jobs(mut self, jobs: usize) -> OrganizerBuilder {
+        self.organizer = self.organizer.with_jobs(jobs);
+        self
+    }
+
+    /// Returns the organizer, or an error when no media type organizer
+    /// was added.
+    pub fn build(self) -> Result<Organizer> {
+        if self.organizer.media_type_organizers.is_empty() {
+            bail!("at least one media type organizer is required");
+        }
+        Ok(self.organizer)
+    }
+}
+
+impl Default for OrganizerBuilder {
+    fn default() -> OrganizerBuilder {
+        OrganizerBuilder::new()
+    }
 }
 
-/// Organizes files by apply the contained [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
-pub struct Organizer {
-    media_type_organizers: Vec<Box<dyn MediaTypeOrganizer>>,
-}
+/// Organizes files by apply the contained [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
+pub struct Organizer {
+    media_type_organizers: Vec<Box<dyn MediaTypeOrganizer>>,
+    flat_dir: Option<PathBuf>,
+    dry_run: bool,
+    transfer_mode: TransferMode,
+    conflict_strategy: ConflictStrategy,
+    dedup: bool,
+    verify: bool,
+    retries: u32,
+    retry_delay: Duration,
+    journal: Option<Journal>,
+    unicode_normalization: Option<UnicodeNormalization>,
+    normalize_extension: bool,
+    sanitize_paths: bool,
+    keep_live_photos: bool,
+    live_photo_dirs: Mutex<HashMap<PathBuf, PathBuf>>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    exclude: Vec<Pattern>,
+    no_recurse: bool,
+    skip_empty: bool,
+    progress: bool,
+    /// Progress of the running [`Organizer::organize`].
+    running_progress: Mutex<Option<Progress>>,
+    newest_first: bool,
+    min_free_space: Option<u64>,
+    free_space: Box<FreeSpaceFn>,
+    rename: Box<RenameFn>,
+    trash_duplicates: bool,
+    trash: Box<TrashFn>,
+    hash: Box<HashFn>,
+    dst_hashes: Mutex<HashMap<PathBuf, CachedHash>>,
+    flatten: bool,
+    preserve_structure: bool,
+    claimed_paths: Mutex<HashSet<PathBuf>>,
+    jobs: usize,
+    prune_empty: bool,
+    file_reports: Option<Mutex<Vec<FileReport>>>,
+    confirmation: Option<Mutex<Confirmation>>,
+}
+
+impl Organizer {
+    /// Creates a new organizer with the given [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
+    pub fn new(media_type_organizers: Vec<Box<dyn MediaTypeOrganizer>>) -> Organizer {
+        Organizer {
+            media_type_organizers,
+            flat_dir: None,
+            dry_run: false,
+            transfer_mode: TransferMode::Move,
+            conflict_strategy: ConflictStrategy::Error,
+            dedup: false,
+            verify: false,
+            retries: 0,
+            retry_delay: Duration::from_millis(500),
+            journal: None,
+            unicode_normalization: None,
+            normalize_extension: false,
+            sanitize_paths: false,
+            keep_live_photos: false,
+            live_photo_dirs: Mutex::new(HashMap::new()),
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+            exclude: Vec::new(),
+            no_recurse: false,
+            skip_empty: false,
+            progress: false,
+            running_progress: Mutex::new(None),
+            newest_first: false,
+            min_free_space: None,
+            free_space: Box::new(|dir| fs2::available_space(dir)),
+            rename: Box::new(|from, to| fs::rename(from, to)),
+            trash_duplicates: false,
+            trash: Box::new(|file| trash::delete(file)),
+            hash: Box::new(file_hash),
+            dst_hashes: Mutex::new(HashMap::new()),
+            flatten: false,
+            preserve_structure: false,
+            claimed_paths: Mutex::new(HashSet::new()),
+            jobs: 1,
+            prune_empty: false,
+            file_reports: None,
+            confirmation: None,
+        }
+    }
+
+    /// Besides organizing the files into their destination, a link to
+    /// each organized file is created in the given flat directory. A
+    /// hardlink is tried first, falling back to a symlink. Names taken in
+    /// the flat directory are handled with the [`ConflictStrategy`].
+    pub fn with_flat_dir(mut self, flat_dir: PathBuf) -> Organizer {
+        self.flat_dir = Some(flat_dir);
+        self
+    }
+
+    /// When enabled the destination of each file is computed and
+    /// printed, but nothing is moved.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Organizer {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// How files are transferred to their destination, they are moved
+    /// by default.
+    pub fn with_transfer_mode(mut self, transfer_mode: TransferMode) -> Organizer {
+        self.transfer_mode = transfer_mode;
+        self
+    }
+
+    /// What to do when a file with the same name already exists in the
+    /// destination, by default it's an error.
+    pub fn with_conflict_strategy(mut self, conflict_strategy: ConflictStrategy) -> Organizer {
+        self.conflict_strategy = conflict_strategy;
+        self
+    }
+
+    /// When a file with the same name already exists in the destination
+    /// and both have the same content, the file is treated as a
+    /// duplicate instead of a conflict. When moving, the duplicate is
+    /// removed from the source. When copying or linking, it's skipped.
+    pub fn with_dedup(mut self, dedup: bool) -> Organizer {
+        self.dedup = dedup;
+        self
+    }
+
+    /// The source is hashed before transferring and the destination
+    /// after, files whose hashes differ are reported as corrupted.
+    pub fn with_verify(mut self, verify: bool) -> Organizer {
+        self.verify = verify;
+        self
+    }
+
+    /// Transfers failing with a transient IO error, interrupted, timed
+    /// out or would block, are retried up to `retries` times, waiting
+    /// `delay` before the first retry and doubling it for each of the
+    /// next ones. Other errors fail the file right away. Handy for
+    /// network shares.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Organizer {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Every moved file is recorded in the journal, so the run can be
+    /// undone with [`Journal::undo`]. Copied and hardlinked files are not
+    /// recorded as their source is left untouched.
+    pub fn with_journal(mut self, journal: Journal) -> Organizer {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Lowercases the extension of the destination file names, so
+    /// `IMG-20200407-WA0004.JPG` is organized as `IMG-20200407-WA0004.jpg`.
+    pub fn with_normalize_extension(mut self, normalize_extension: bool) -> Organizer {
+        self.normalize_extension = normalize_extension;
+        self
+    }
+
+    /// Replaces the characters FAT and exFAT don't allow, like `:` or
+    /// `?`, in the names of the destination dirs that don't exist yet and
+    /// of the files, and trims their trailing dots and spaces.
+    pub fn with_sanitize_paths(mut self, sanitize_paths: bool) -> Organizer {
+        self.sanitize_paths = sanitize_paths;
+        self
+    }
+
+    /// Keeps the halves of Live Photos together, the `.mov` video with
+    /// the same name as a `.heic` or `.jpg` photo is organized into the
+    /// destination dir of the photo.
+    pub fn with_keep_live_photos(mut self, keep_live_photos: bool) -> Organizer {
+        self.keep_live_photos = keep_live_photos;
+        self
+    }
+
+    /// Files whose name is taken in the destination by a different file
+    /// are sent to the trash of the system, instead of erroring or being
+    /// skipped as the [`ConflictStrategy`] says, so the media source
+    /// empties out. Identical files are left to [`Self::with_dedup`].
+    /// Copied and hardlinked files are only skipped.
+    pub fn with_trash_duplicates(mut self, trash_duplicates: bool) -> Organizer {
+        self.trash_duplicates = trash_duplicates;
+        self
+    }
+
+    /// Every file gets a name of its own in its destination dir, no
+    /// matter the source folder it comes from. Names taken in the
+    /// destination, or by another file of the run, get ` (1)`, ` (2)`,
+    /// etc. appended, whatever the [`ConflictStrategy`]. Files identical
+    /// to the existing one are still left to [`Self::with_dedup`].
+    pub fn with_flatten(mut self, flatten: bool) -> Organizer {
+        self.flatten = flatten;
+        self
+    }
+
+    /// The folders of the files inside their media source are recreated
+    /// inside their destination dir, `Trip/day1/camera.jpg` goes to
+    /// `2019/01 - January/Trip/day1/camera.jpg`.
+    pub fn with_preserve_structure(mut self, preserve_structure: bool) -> Organizer {
+        self.preserve_structure = preserve_structure;
+        self
+    }
+
+    /// Normalizes the destination file names to the given unicode form,
+    /// so names that only differ in their normalization end up the same.
+    pub fn with_unicode_normalization(mut self, form: UnicodeNormalization) -> Organizer {
+        self.unicode_normalization = Some(form);
+        self
+    }
+
+    /// Files and directories whose name, or path relative to the media
+    /// source, matches any of the glob patterns are not organized.
+    pub fn with_exclude(mut self, patterns: Vec<Pattern>) -> Organizer {
+        self.exclude = patterns;
+        self
+    }
+
+    /// Only the files directly in the media sources are organized, their
+    /// subdirectories are left untouched.
+    pub fn with_no_recurse(mut self, no_recurse: bool) -> Organizer {
+        self.no_recurse = no_recurse;
+        self
+    }
+
+    /// Empty files, placeholders or broken files that can't have a date,
+    /// are left in the source and counted as empty in the [`Report`].
+    pub fn with_skip_empty(mut self, skip_empty: bool) -> Organizer {
+        self.skip_empty = skip_empty;
+        self
+    }
+
+    /// The files of the media sources are counted first, and the number
+    /// organized out of them is shown in a progress bar, or logged now
+    /// and then when the output isn't a terminal.
+    pub fn with_progress(mut self, progress: bool) -> Organizer {
+        self.progress = progress;
+        self
+    }
+
+    /// Files smaller than `min_size` bytes are not organized.
+    pub fn with_min_size(mut self, min_size: u64) -> Organizer {
+        self.min_size = Some(min_size);
+        self
+    }
+
+    /// Files bigger than `max_size` bytes are not organized.
+    pub fn with_max_size(mut self, max_size: u64) -> Organizer {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Only files modified after `time` are organized.
+    pub fn with_modified_after(mut self, time: SystemTime) -> Organizer {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Only files modified before `time` are organized.
+    pub fn with_modified_before(mut self, time: SystemTime) -> Organizer {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Organizes the most recently modified files first, so if the run
+    /// is aborted, for example by [`Self::with_min_free_space`], the
+    /// newest files are the ones already organized. With
+    /// [`Self::with_jobs`] the order is only kept within each destination
+    /// dir.
+    pub fn with_newest_first(mut self, newest_first: bool) -> Organizer {
+        self.newest_first = newest_first;
+        self
+    }
+
+    /// Aborts the run when moving the next file would leave less than
+    /// `min_free_space` bytes available in its destination.
+    pub fn with_min_free_space(mut self, min_free_space: u64) -> Organizer {
+        self.min_free_space = Some(min_free_space);
+        self
+    }
+
+    /// Organizes the files using `jobs` threads. Files that go to the
+    /// same destination dir are organized by the same thread, in the
+    /// same order as with a single one, so the names given by
+    /// [`ConflictStrategy::Rename`] don't depend on the scheduling.
+    pub fn with_jobs(mut self, jobs: usize) -> Organizer {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Once all the files are organized, the directories of the media
+    /// source left empty are removed. The media source itself is kept.
+    /// Nothing is removed in dry run mode.
+    pub fn with_prune_empty(mut self, prune_empty: bool) -> Organizer {
+        self.prune_empty = prune_empty;
+        self
+    }
+
+    /// The [`Report`] of a run lists what happened to every file, with
+    /// the date it's organized by, which takes reading it once more.
+    pub fn with_file_reports(mut self, file_reports: bool) -> Organizer {
+        self.file_reports = file_reports.then(|| Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Asks before transferring each file, reading the answers from
+    /// `answers`, usually stdin. Declined files are skipped. Dry runs
+    /// don't ask.
+    pub fn with_interactive(mut self, answers: Box<dyn BufRead + Send>) -> Organizer {
+        self.confirmation = Some(Mutex::new(Confirmation {
+            answers,
+            yes_to_all: false,
+        }));
+        self
+    }
+
+    /// Takes the file reports recorded so far, sorted by path.
+    fn take_file_reports(&self) -> Vec<FileReport> {
+        let mut files = match &self.file_reports {
+            Some(file_reports) => {
+                std::mem::take(&mut *file_reports.lock().expect("file reports lock poisoned"))
+            }
+            None => return Vec::new(),
+        };
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    }
+
+    fn files(&self, media_src: impl Into<PathBuf>) -> FilesIter {
+        let mut files = FilesIter::new(media_src);
+        if let Some(min_size) = self.min_size {
+            files = files.with_min_size(min_size);
+        }
+        if let Some(max_size) = self.max_size {
+            files = files.with_max_size(max_size);
+        }
+        if let Some(time) = self.modified_after {
+            files = files.modified_after(time);
+        }
+        if let Some(time) = self.modified_before {
+            files = files.modified_before(time);
+        }
+        if !self.exclude.is_empty() {
+            files = files.with_exclude(self.exclude.clone());
+        }
+        if self.no_recurse {
+            files = files.with_max_depth(0);
+        }
+        files
+    }
+
+    /// Organize all the media files in the given media source
+    /// and its subdirectories according to the
+    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
+    ///
+    /// For each file it goes over all the registered
+    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers) to
+    /// determine if the file is supported and should be organize.
+    /// If the file should be organize it gets the new destination
+    /// path and moves the file to it.
+    ///
+    /// The files are handled by the first
+    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers)
+    /// that returns a new destination directory and for which the move
+    /// operation successfully executes.
+    ///
+    /// Once moved, the [`MediaTypeOrganizer::after_move`] hook is called
+    /// and, if a flat directory was set, the file is linked into it.
+    /// Failures in these steps don't undo the move.
+    ///
+    /// In dry run mode the destination paths, including the check for
+    /// files with the same name, are computed and printed, but the files
+    /// are not moved.
+    ///
+    /// The media sources are organized in turn, as a single run.
+    ///
+    /// Returns a [`Report`] counting what happened to the files. Every
+    /// file found in the media sources must have been accounted for,
+    /// otherwise an error with the missing files is returned.
+    ///
+    /// An error is also returned when the run is aborted because the
+    /// destination is running out of space.
+    pub fn organize(&self, media_srcs: &[PathBuf]) -> Result<Report> {
+        if self.progress {
+            let total: usize = media_srcs
+                .iter()
+                .map(|media_src| self.files(media_src).count())
+                .sum();
+            *self.lock_progress() = Some(Progress::new(total as u64));
+        }
+        let organized = self.organize_files(media_srcs);
+        if let Some(progress) = self.lock_progress().take() {
+            progress.finish();
+        }
+        let (files, outcomes) = organized?;
+        reconcile(&files, &outcomes)?;
+
+        if self.prune_empty && !self.dry_run {
+            for media_src in media_srcs {
+                prune_empty_dirs(media_src);
+            }
+        }
+
+        let mut report = Report::default();
+        for outcome in outcomes.into_values() {
+            report.add(outcome);
+        }
+        report.files = self.take_file_reports();
+        Ok(report)
+    }
+
+    fn lock_progress(&self) -> MutexGuard<'_, Option<Progress>> {
+        self.running_progress
+            .lock()
+            .expect("progress lock poisoned")
+    }
+
+    /// Organizes the files of the media sources, returning them in the
+    /// order they were found and what happened to each.
+    fn organize_files(
+        &self,
+        media_srcs: &[PathBuf],
+    ) -> Result<(Vec<PathBuf>, HashMap<PathBuf, Outcome>)> {
+        let mut files = Vec::new();
+        let mut outcomes = HashMap::new();
+        if self.jobs > 1 {
+            for media_src in media_srcs {
+                files.extend(self.files(media_src));
+            }
+            outcomes = self.organize_parallel(media_srcs, &files)?;
+        } else if self.newest_first {
+            for media_src in media_srcs {
+                files.extend(self.files(media_src));
+            }
+            sort_newest_first(&mut files);
+            for file in &files {
+                let media_src = media_src_of(media_srcs, file);
+                outcomes.insert(file.clone(), self.organize_file(file, media_src)?);
+            }
+        } else {
+            for media_src in media_srcs {
+                for file in self.files(media_src) {
+                    outcomes.insert(file.clone(), self.organize_file(&file, media_src)?);
+                    files.push(file);
+                }
+            }
+        }
+        Ok((files, outcomes))
+    }
+
+    /// Describes where [`organize`](Organizer::organize) would put each
+    /// file of the media sources, without touching them. The files are
+    /// listed under their destination dir, both sorted, followed by the
+    /// files no organizer handles. Name conflicts in the destination
+    /// aren't resolved.
+    pub fn preview(&self, media_srcs: &[PathBuf]) -> String {
+        let mut plan: BTreeMap<PathBuf, Vec<(PathBuf, PathBuf)>> = BTreeMap::new();
+        let mut unhandled = Vec::new();
+        for media_src in media_srcs {
+            for file in self.files(media_src) {
+                match self.first_destination(&file, media_src) {
+                    Some(dst_path) => {
+                        let dst_dir = dst_path.parent().map(Path::to_path_buf).unwrap_or_default();
+                        plan.entry(dst_dir).or_default().push((file, dst_path));
+                    }
+                    None => unhandled.push(file),
+                }
+            }
+        }
+
+        let mut preview = String::new();
+        for (dst_dir, mut files) in plan {
+            files.sort();
+            preview.push_str(&format!("{}\n", dst_dir.display()));
+            for (file, dst_path) in files {
+                let dst_name = dst_path.file_name().unwrap_or_default();
+                preview.push_str(&format!(
+                    "    {} -> {}\n",
+                    file.display(),
+                    Path::new(dst_name).display()
+                ));
+            }
+        }
+        if !unhandled.is_empty() {
+            unhandled.sort();
+            preview.push_str("Not organized\n");
+            for file in unhandled {
+                preview.push_str(&format!("    {}\n", file.display()));
+            }
+        }
+        preview
+    }
+
+    /// Decides where [`organize`](Organizer::organize) would transfer
+    /// each file of the media sources, without touching them, to be
+    /// carried out later with [`apply`](Organizer::apply). The files that
+    /// wouldn't be transferred, because they are already organized,
+    /// duplicates or their name is taken, are left out. When several
+    /// files go to the same path only the first is planned, unless name
+    /// conflicts are renamed.
+    pub fn plan(&self, media_srcs: &[PathBuf]) -> Plan {
+        let mut plan = Plan::default();
+        let mut planned = HashSet::new();
+        for media_src in media_srcs {
+            for file in self.files(media_src) {
+                let (organizer, dst_path) = match self.plan_file(&file, media_src) {
+                    Some(destination) => destination,
+                    None => continue,
+                };
+                let dst_path = if !planned.contains(&dst_path) {
+                    dst_path
+                } else if self.conflict_strategy == ConflictStrategy::Rename {
+                    Organizer::free_path(&dst_path, |path| {
+                        path.symlink_metadata().is_err() && !planned.contains(path)
+                    })
+                } else {
+                    warn!(
+                        "leaving {:?} out of the plan, {:?} is planned for another file",
+                        file, dst_path
+                    );
+                    continue;
+                };
+                planned.insert(dst_path.clone());
+                plan.moves.push(PlannedMove {
+                    source: file,
+                    destination: dst_path,
+                    organizer: organizer.to_owned(),
+                });
+            }
+        }
+        plan
+    }
+
+    /// The name of the organizer that would transfer the file and its
+    /// destination path, if it would be transferred.
+    fn plan_file(&self, file: &Path, media_src: &Path) -> Option<(&'static str, PathBuf)> {
+        for media_type_organizer in &self.media_type_organizers {
+            if !media_type_organizer.should_organize(file) {
+                continue;
+            }
+            if self.skip_empty && fs::metadata(file).is_ok_and(|metadata| metadata.len() == 0) {
+                return None;
+            }
+            if let Some(reason) = media_type_organizer.skip_reason(file) {
+                info!("skipping {:?}, {}", file, reason);
+                return None;
+            }
+            let dst_dir = match self.live_photo_dir(file) {
+                Some(dir) => Ok(dir),
+                None => media_type_organizer.destination_dir(file),
+            };
+            let dst_dir = match dst_dir {
+                Ok(dir) => dir,
+                Err(e) => {
+                    error!(
+                        "{:?}",
+                        e.wrap_err(format!("failed to get destination dir from {:?}", file))
+                    );
+                    continue;
+                }
+            };
+            self.record_live_photo_dir(file, &dst_dir);
+            let dst_dir = self.structured_dir(dst_dir, file, media_src);
+            let dst_name = match media_type_organizer.destination_name(file) {
+                Ok(name) => name,
+                Err(e) => {
+                    error!(
+                        "{:?}",
+                        e.wrap_err(format!("failed to get destination name from {:?}", file))
+                    );
+                    continue;
+                }
+            };
+            match self.destination_path(file, &dst_dir, dst_name) {
+                Ok(Destination::Path(dst_path)) => {
+                    return Some((media_type_organizer.name(), dst_path))
+                }
+                Ok(_) => return None,
+                Err(e) => error!("{:?}", e),
+            }
+        }
+        None
+    }
+
+    /// Transfers the files of a [`Plan`] to their planned destination,
+    /// the way [`organize`](Organizer::organize) would. Files no longer
+    /// in the source, or whose destination was taken since planning, are
+    /// left alone and counted as errored. Every organizer of the plan
+    /// should be one of this organizer.
+    pub fn apply(&self, plan: &Plan) -> Result<Report> {
+        let organizers = plan
+            .moves
+            .iter()
+            .map(|planned| {
+                self.media_type_organizers
+                    .iter()
+                    .find(|organizer| organizer.name() == planned.organizer)
+                    .ok_or_else(|| {
+                        eyre!(
+                            "the plan uses the {} organizer, which isn't enabled",
+                            planned.organizer
+                        )
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut report = Report::default();
+        for (planned, media_type_organizer) in plan.moves.iter().zip(organizers) {
+            report.add(self.apply_move(media_type_organizer.as_ref(), planned)?);
+        }
+        Ok(report)
+    }
+
+    fn apply_move(
+        &self,
+        media_type_organizer: &dyn MediaTypeOrganizer,
+        planned: &PlannedMove,
+    ) -> Result<Outcome> {
+        let (file, dst_path) = (&planned.source, &planned.destination);
+        let errored = Outcome::Errored(media_type_organizer.name());
+        if !file.is_file() {
+            error!(
+                "failed to apply the move of {:?}, it's no longer in the source",
+                file
+            );
+            return Ok(errored);
+        }
+        if self.conflict_strategy != ConflictStrategy::Overwrite
+            && dst_path.symlink_metadata().is_ok()
+        {
+            error!(
+                "failed to apply the move of {:?}, {:?} was taken after planning",
+                file, dst_path
+            );
+            return Ok(errored);
+        }
+        if self.dry_run {
+            println!(
+                "would {} {:?} → {:?}",
+                self.transfer_mode.verb(),
+                file,
+                dst_path
+            );
+            return Ok(Outcome::Moved(media_type_organizer.name()));
+        }
+        Ok(self
+            .transfer_to(media_type_organizer, file, dst_path)?
+            .unwrap_or(errored))
+    }
+
+    /// Groups of files of the media sources with the same content,
+    /// without touching them. Only the files with the same size as
+    /// another one are hashed, and empty files are left out. The files
+    /// of each group and the groups are sorted.
+    pub fn find_duplicates(&self, media_srcs: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+        let mut by_len: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+        for media_src in media_srcs {
+            for file in self.files(media_src) {
+                match fs::metadata(&file) {
+                    Ok(metadata) if metadata.len() == 0 => (),
+                    Ok(metadata) => by_len.entry(metadata.len()).or_default().push(file),
+                    Err(e) => warn!("failed to read the metadata of {:?}: {}", file, e),
+                }
+            }
+        }
+        let candidates: Vec<PathBuf> = by_len
+            .into_values()
+            .filter(|files| files.len() > 1)
+            .flatten()
+            .collect();
+
+        let mut by_hash: HashMap<Vec<u8>, Vec<PathBuf>> = HashMap::new();
+        thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(candidates.len().div_ceil(self.jobs).max(1))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .filter_map(|file| match (self.hash)(file) {
+                                Ok(hash) => Some((hash, file.clone())),
+                                Err(e) => {
+                                    error!("{:?}", e);
+                                    None
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (hash, file) in handle.join().expect("hashing thread panicked") {
+                    by_hash.entry(hash).or_default().push(file);
+                }
+            }
+        });
+
+        let mut groups: Vec<Vec<PathBuf>> = by_hash
+            .into_values()
+            .filter(|files| files.len() > 1)
+            .map(|mut files| {
+                files.sort();
+                files
+            })
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    /// Organizes the files created in, or moved into, the media sources
+    /// until `stop` is set, returning the report of what was organized.
+    /// The events are debounced, a file is organized once it hasn't
+    /// changed for `debounce`, so files being copied aren't organized
+    /// half written.
+    pub fn watch(
+        &self,
+        media_srcs: &[PathBuf],
+        debounce: Duration,
+        stop: &AtomicBool,
+    ) -> Result<Report> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::watcher(tx, debounce).wrap_err("failed to create watcher")?;
+        for media_src in media_srcs {
+            watcher
+                .watch(media_src, RecursiveMode::Recursive)
+                .wrap_err_with(|| format!("failed to watch {:?}", media_src))?;
+        }
+        let events = iter::from_fn(|| {
+            while !stop.load(Ordering::Relaxed) {
+                match rx.recv_timeout(WATCH_STOP_POLL) {
+                    Ok(event) => return Some(event),
+                    Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return None,
+                }
+            }
+            None
+        });
+        let paths = events.filter_map(|event| match event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Rename(_, path) => Some(path),
+            DebouncedEvent::Error(e, path) => {
+                warn!("failed to watch {:?}: {}", path, e);
+                None
+            }
+            _ => None,
+        });
+        self.organize_paths(media_srcs, paths)
+    }
+
+    /// Organizes each path as it comes, the files of directories
+    /// included. Paths that are gone, for example because they were
+    /// already organized, or that the filters reject are ignored.
+    fn organize_paths<I>(&self, media_srcs: &[PathBuf], paths: I) -> Result<Report>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        let mut report = Report::default();
+        for path in paths {
+            let media_src = match media_srcs.iter().find(|src| path.starts_with(src)) {
+                Some(media_src) => media_src,
+                None => continue,
+            };
+            let files: Vec<PathBuf> = if path.is_dir() {
+                if self.no_recurse && path != *media_src {
+                    continue;
+                }
+                self.files(path).collect()
+            } else if self.files(media_src).accepts(&path) {
+                vec![path]
+            } else {
+                continue;
+            };
+            for file in files {
+                report.add(self.organize_file(&file, media_src)?);
+            }
+        }
+        report.files = self.take_file_reports();
+        Ok(report)
+    }
+
+    /// Groups the files by destination dir and organizes each group in
+    /// a single thread, sorted by path, so conflicts inside the group
+    /// are always resolved in the same order.
+    fn organize_parallel(
+        &self,
+        media_srcs: &[PathBuf],
+        files: &[PathBuf],
+    ) -> Result<HashMap<PathBuf, Outcome>> {
+        if files.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // Getting the destination dirs reads the files, so it's done
+        // in parallel too.
+        let mut groups: BTreeMap<Option<PathBuf>, Vec<PathBuf>> = BTreeMap::new();
+        thread::scope(|scope| {
+            let handles: Vec<_> = files
+                .chunks(files.len().div_ceil(self.jobs))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|file| (self.first_destination_dir(file), file.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (dst_dir, file) in handle.join().expect("organizer thread panicked") {
+                    groups.entry(dst_dir).or_default().push(file);
+                }
+            }
+        });
+
+        let groups: Vec<Vec<PathBuf>> = groups
+            .into_values()
+            .map(|mut group| {
+                group.sort();
+                if self.newest_first {
+                    sort_newest_first(&mut group);
+                }
+                group
+            })
+            .collect();
+        let queue = Mutex::new(groups.into_iter());
+        let aborted = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.jobs)
+                .map(|_| {
+                    scope.spawn(|| -> Result<Vec<(PathBuf, Outcome)>> {
+                        let mut outcomes = Vec::new();
+                        loop {
+                            let group = match queue.lock().expect("queue lock poisoned").next() {
+                                Some(group) => group,
+                                None => return Ok(outcomes),
+                            };
+                            for file in group {
+                                if aborted.load(Ordering::Relaxed) {
+                                    return Ok(outcomes);
+                                }
+                                match self.organize_file(&file, media_src_of(media_srcs, &file)) {
+                                    Ok(outcome) => outcomes.push((file, outcome)),
+                                    Err(e) => {
+                                        aborted.store(true, Ordering::Relaxed);
+                                        return Err(e);
+                                    }
+                                }
+                            }
+                        }
+                    })
+                })
+                .collect();
+            let mut outcomes = HashMap::new();
+            for handle in handles {
+                outcomes.extend(handle.join().expect("organizer thread panicked")?);
+            }
+            Ok(outcomes)
+        })
+    }
+
+    /// Destination dir given by the first organizer that handles the
+    /// file, which is where it will most likely be organized into.
+    fn first_destination_dir(&self, file: &Path) -> Option<PathBuf> {
+        if let Some(dir) = self.live_photo_dir(file) {
+            return Some(dir);
+        }
+        self.media_type_organizers
+            .iter()
+            .filter(|organizer| organizer.should_organize(file))
+            .find_map(|organizer| organizer.destination_dir(file).ok())
+    }
+
+    /// The path the first organizer able to handle `file` would move
+    /// it to.
+    fn first_destination(&self, file: &Path, media_src: &Path) -> Option<PathBuf> {
+        self.media_type_organizers
+            .iter()
+            .filter(|organizer| organizer.should_organize(file))
+            .take_while(|organizer| organizer.skip_reason(file).is_none())
+            .find_map(|organizer| {
+                let dst_dir = match self.live_photo_dir(file) {
+                    Some(dir) => dir,
+                    None => organizer.destination_dir(file).ok()?,
+                };
+                let dst_name = organizer.destination_name(file).ok()?;
+                Some(
+                    self.destination_file(&self.structured_dir(dst_dir, file, media_src), dst_name),
+                )
+            })
+    }
+
+    /// The destination dir of the video of a Live Photo, which is the
+    /// one of its photo, whether the photo is still in the source or
+    /// was already organized.
+    fn live_photo_dir(&self, file: &Path) -> Option<PathBuf> {
+        if !self.keep_live_photos || !is_live_photo_video(file) {
+            return None;
+        }
+        let recorded = self
+            .live_photo_dirs
+            .lock()
+            .expect("live photo dirs lock poisoned")
+            .get(file)
+            .cloned();
+        if recorded.is_some() {
+            return recorded;
+        }
+        self.first_destination_dir(&live_photo_sibling(file)?)
+    }
+
+    /// Remembers where the photo of a Live Photo goes, for its video.
+    fn record_live_photo_dir(&self, file: &Path, dst_dir: &Path) {
+        if !self.keep_live_photos || is_live_photo_video(file) {
+            return;
+        }
+        if let Some(video) = live_photo_sibling(file) {
+            self.live_photo_dirs
+                .lock()
+                .expect("live photo dirs lock poisoned")
+                .insert(video, dst_dir.to_path_buf());
+        }
+    }
+
+    /// The dir of the file relative to its media source appended to
+    /// the destination dir, see [`Self::with_preserve_structure`].
+    fn structured_dir(&self, dst_dir: PathBuf, file: &Path, media_src: &Path) -> PathBuf {
+        if !self.preserve_structure {
+            return dst_dir;
+        }
+        match file
+            .parent()
+            .and_then(|dir| dir.strip_prefix(media_src).ok())
+        {
+            Some(relative_dir) => dst_dir.join(relative_dir),
+            None => dst_dir,
+        }
+    }
+
+    fn organize_file(&self, file: &Path, media_src: &Path) -> Result<Outcome> {
+        let mut file_report = FileReport {
+            path: file.to_path_buf(),
+            ..FileReport::default()
+        };
+        let outcome = self.organize_file_reporting(file, media_src, &mut file_report)?;
+        if let Some(progress) = &*self.lock_progress() {
+            progress.file_done(file_report.destination.as_deref());
+        }
+        if let Some(file_reports) = &self.file_reports {
+            file_report.organizer = outcome.organizer();
+            file_report.outcome = outcome.as_str();
+            file_reports
+                .lock()
+                .expect("file reports lock poisoned")
+                .push(file_report);
+        }
+        Ok(outcome)
+    }
+
+    /// Organizes the file filling in the date and destination of its
+    /// report.
+    fn organize_file_reporting(
+        &self,
+        file: &Path,
+        media_src: &Path,
+        file_report: &mut FileReport,
+    ) -> Result<Outcome> {
+        let mut outcome = Outcome::Unhandled;
+        for media_type_organizer in &self.media_type_organizers {
+            if !media_type_organizer.should_organize(file) {
+                continue;
+            }
+            if self.skip_empty && fs::metadata(file).is_ok_and(|metadata| metadata.len() == 0) {
+                warn!("skipping {:?}, it's empty", file);
+                return Ok(Outcome::Empty(media_type_organizer.name()));
+            }
+            if let Some(reason) = media_type_organizer.skip_reason(file) {
+                info!("skipping {:?}, {}", file, reason);
+                return Ok(Outcome::Skipped(media_type_organizer.name()));
+            }
+            outcome = Outcome::Errored(media_type_organizer.name());
+            let dst_dir = match self.live_photo_dir(file) {
+                Some(dir) => Ok(dir),
+                None => media_type_organizer.destination_dir(file),
+            };
+            let dst_dir = match dst_dir
+                .wrap_err_with(|| format!("failed to get destination dir from {:?}", file))
+            {
+                Ok(dir) => dir,
+                Err(e) => {
+                    error!("{:?}", e);
+                    continue;
+                }
+            };
+            self.record_live_photo_dir(file, &dst_dir);
+            let dst_dir = self.structured_dir(dst_dir, file, media_src);
+            if self.file_reports.is_some() {
+                file_report.date = media_type_organizer
+                    .date(file)
+                    .map(|date| date.format("%Y-%m", Locale::En));
+            }
+            let dst_name = match media_type_organizer
+                .destination_name(file)
+                .wrap_err_with(|| format!("failed to get destination name from {:?}", file))
+            {
+                Ok(name) => name,
+                Err(e) => {
+                    error!("{:?}", e);
+                    continue;
+                }
+            };
+
+            let dst_path = match self
+                .destination_path(file, &dst_dir, dst_name)
+                .wrap_err_with(|| {
+                    format!(
+                        "failed to move file {:?} to destination dir {:?}",
+                        file, dst_dir
+                    )
+                }) {
+                Ok(Destination::Path(dst_path)) => {
+                    file_report.destination = Some(dst_path.clone());
+                    dst_path
+                }
+                Ok(Destination::Organized) => {
+                    debug!("skipping {:?}, it's already organized", file);
+                    file_report.destination = Some(file.to_path_buf());
+                    return Ok(Outcome::Skipped(media_type_organizer.name()));
+                }
+                Ok(Destination::Skip) => {
+                    warn!(
+                        "skipping {:?}, a file with the same name already exists in {:?}",
+                        file, dst_dir
+                    );
+                    return Ok(Outcome::Skipped(media_type_organizer.name()));
+                }
+                Ok(Destination::Duplicate(existing)) => {
+                    file_report.destination = Some(existing.clone());
+                    return match self.remove_duplicate(file, &existing) {
+                        Ok(()) => Ok(Outcome::Skipped(media_type_organizer.name())),
+                        Err(e) => {
+                            error!("{:?}", e);
+                            Ok(Outcome::Errored(media_type_organizer.name()))
+                        }
+                    };
+                }
+                Ok(Destination::Trash(existing)) => {
+                    return match self.trash_duplicate(file, &existing) {
+                        Ok(()) => Ok(Outcome::Skipped(media_type_organizer.name())),
+                        Err(e) => {
+                            error!("{:?}", e);
+                            Ok(Outcome::Errored(media_type_organizer.name()))
+                        }
+                    };
+                }
+                Err(e) => {
+                    error!("{:?}", e);
+                    continue;
+                }
+            };
+
+            if self.dry_run {
+                println!(
+                    "would {} {:?} → {:?}",
+                    self.transfer_mode.verb(),
+                    file,
+                    dst_path
+                );
+                return Ok(Outcome::Moved(media_type_organizer.name()));
+            }
+
+            if let Some(confirmation) = &self.confirmation {
+                let verb = self.transfer_mode.verb();
+                let question = format!(
+                    "{}{} {:?} → {:?}?",
+                    verb[..1].to_uppercase(),
+                    &verb[1..],
+                    file,
+                    dst_path
+                );
+                if !confirmation
+                    .lock()
+                    .expect("confirmation lock poisoned")
+                    .confirm(&question)?
+                {
+                    debug!("skipping {:?}, not confirmed", file);
+                    file_report.destination = None;
+                    return Ok(Outcome::Skipped(media_type_organizer.name()));
+                }
+            }
+
+            if let Some(outcome) =
+                self.transfer_to(media_type_organizer.as_ref(), file, &dst_path)?
+            {
+                return Ok(outcome);
+            }
+        }
+        if outcome == Outcome::Unhandled {
+            debug!("no organizer handles {:?}", file);
+        }
+        Ok(outcome)
+    }
+
+    /// Transfers the file to its destination path, checking the free
+    /// space, journaling, verifying and post-processing it. Returns none,
+    /// after logging why, when it couldn't be transferred.
+    fn transfer_to(
+        &self,
+        media_type_organizer: &dyn MediaTypeOrganizer,
+        file: &Path,
+        dst_path: &Path,
+    ) -> Result<Option<Outcome>> {
+        let dst_dir = dst_path.parent().unwrap_or(Path::new(""));
+        self.check_free_space(file, dst_dir)?;
+
+        // Moving removes the source, so it's hashed beforehand.
+        let source_hash = if self.verify {
+            match file_hash(file) {
+                Ok(hash) => Some(hash),
+                Err(e) => {
+                    error!("{:?}", e.wrap_err("failed to verify file"));
+                    return Ok(None);
+                }
+            }
+        } else {
+            None
+        };
+
+        if let Err(e) = self
+            .transfer_file(file, dst_path, source_hash.as_deref())
+            .wrap_err_with(|| {
+                format!(
+                    "failed to {} file {:?} to destination dir {:?}",
+                    self.transfer_mode.verb(),
+                    file,
+                    dst_dir
+                )
+            })
+        {
+            error!("{:?}", e);
+            // A move across filesystems verifies the copy before removing
+            // the source, which is kept when it's corrupted.
+            if e.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<OrganizerError>(),
+                    Some(OrganizerError::VerificationFailed(_))
+                )
+            }) {
+                return Ok(Some(Outcome::Corrupted(media_type_organizer.name())));
+            }
+            return Ok(None);
+        }
+        if let (Some(journal), TransferMode::Move) = (&self.journal, self.transfer_mode) {
+            if let Err(e) = journal
+                .record(file, dst_path)
+                .wrap_err_with(|| format!("failed to journal move of {:?}", file))
+            {
+                error!("{:?}", e);
+            }
+        }
+        if let Some(source_hash) = source_hash {
+            if let Err(e) = Organizer::verify_file(&source_hash, dst_path) {
+                error!("{:?}", e);
+                return Ok(Some(Outcome::Corrupted(media_type_organizer.name())));
+            }
+        }
+        if let Err(e) = media_type_organizer
+            .after_move(dst_path)
+            .wrap_err_with(|| format!("failed to post-process {:?}", dst_path))
+        {
+            error!("{:?}", e);
+        }
+        if let Some(flat_dir) = &self.flat_dir {
+            if let Err(e) = self
+                .link_into_flat_dir(dst_path, flat_dir)
+                .wrap_err_with(|| {
+                    format!(
+                        "failed to link file {:?} into flat dir {:?}",
+                        dst_path, flat_dir
+                    )
+                })
+            {
+                error!("{:?}", e);
+            }
+        }
+        debug!("{} {:?} → {:?}", self.transfer_mode.verb(), file, dst_path);
+        Ok(Some(Outcome::Moved(media_type_organizer.name())))
+    }
+
+    /// Fails if moving the file would leave less than the minimum free
+    /// space in the destination. As the destination dir might not exist
+    /// yet, the space is checked in its closest existing ancestor.
+    fn check_free_space(&self, file: &Path, dst_dir: &Path) -> Result<()> {
+        let min_free_space = match self.min_free_space {
+            Some(min_free_space) => min_free_space,
+            None => return Ok(()),
+        };
+        let existing_dir = match dst_dir.ancestors().find(|dir| dir.is_dir()) {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        let available = (self.free_space)(existing_dir)
+            .wrap_err_with(|| format!("failed to get free space of {:?}", existing_dir))?;
+        let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+        if available < min_free_space.saturating_add(size) {
+            bail!(
+                "aborting, moving {:?} would leave less than {} bytes free in {:?}",
+                file,
+                min_free_space,
+                existing_dir
+            );
+        }
+        Ok(())
+    }
+
+    /// Path in `dst_dir` where the file will be moved to. When a file
+    /// with the same name already exists there, it's either a duplicate,
+    /// if dedup is enabled and both have the same content, or the
+    /// [`ConflictStrategy`] decides what to do.
+    fn destination_path(
+        &self,
+        file: &Path,
+        dst_dir: &Path,
+        dst_name: OsString,
+    ) -> Result<Destination> {
+        let dst_path = self.destination_file(dst_dir, dst_name);
+        if Organizer::is_same_file(file, &dst_path) {
+            return Ok(Destination::Organized);
+        }
+        if self.flatten {
+            return self.flat_destination_path(file, dst_path);
+        }
+        if !dst_path.is_file() {
+            return Ok(Destination::Path(dst_path));
+        }
+        if self.dedup && self.is_duplicate(file, &dst_path)? {
+            return Ok(Destination::Duplicate(dst_path));
+        }
+        match self.conflict_strategy {
+            ConflictStrategy::Skip | ConflictStrategy::Error if self.trash_duplicates => {
+                Ok(Destination::Trash(dst_path))
+            }
+            ConflictStrategy::Skip => Ok(Destination::Skip),
+            ConflictStrategy::Overwrite => Ok(Destination::Path(dst_path)),
+            ConflictStrategy::Rename => Ok(Destination::Path(Organizer::renamed_path(&dst_path))),
+            ConflictStrategy::Error => Err(OrganizerError::DestinationExists(dst_path).into()),
+        }
+    }
+
+    /// Destination path when flattening, renamed when the name is taken
+    /// in the destination or by another file of the run, which in dry
+    /// run mode isn't in the destination.
+    fn flat_destination_path(&self, file: &Path, dst_path: PathBuf) -> Result<Destination> {
+        if self.dedup && dst_path.is_file() && self.is_duplicate(file, &dst_path)? {
+            return Ok(Destination::Duplicate(dst_path));
+        }
+        let mut claimed = self
+            .claimed_paths
+            .lock()
+            .expect("claimed paths lock poisoned");
+        let is_free = |path: &Path| path.symlink_metadata().is_err() && !claimed.contains(path);
+        let dst_path = if is_free(&dst_path) {
+            dst_path
+        } else {
+            Organizer::free_path(&dst_path, is_free)
+        };
+        claimed.insert(dst_path.clone());
+        Ok(Destination::Path(dst_path))
+    }
+
+    /// If both paths are the same file, like when organizing a directory
+    /// that was already organized.
+    fn is_same_file(file: &Path, dst_path: &Path) -> bool {
+        if file == dst_path {
+            return true;
+        }
+        if !dst_path.is_file() {
+            return false;
+        }
+        match (fs::canonicalize(file), fs::canonicalize(dst_path)) {
+            (Ok(file), Ok(dst_path)) => file == dst_path,
+            _ => false,
+        }
+    }
+
+    fn verify_file(source_hash: &[u8], dst_path: &Path) -> Result<()> {
+        let dst_hash = file_hash(dst_path).wrap_err("failed to verify file")?;
+        if dst_hash != source_hash {
+            return Err(OrganizerError::VerificationFailed(dst_path.to_path_buf()).into());
+        }
+        Ok(())
+    }
+
+    /// If `file` and `existing` are different files with the same
+    /// content. Both are hashed at the same time.
+    fn is_duplicate(&self, file: &Path, existing: &Path) -> Result<bool> {
+        // Removing the source when it's the existing file would lose it.
+        if fs::canonicalize(file)? == fs::canonicalize(existing)? {
+            return Ok(false);
+        }
+        if fs::metadata(file)?.len() != fs::metadata(existing)?.len() {
+            return Ok(false);
+        }
+        let (file_hash, existing_hash) = thread::scope(|scope| {
+            let existing_hash = scope.spawn(|| self.destination_hash(existing));
+            let file_hash = (self.hash)(file);
+            (
+                file_hash,
+                existing_hash.join().expect("hashing thread panicked"),
+            )
+        });
+        Ok(file_hash? == existing_hash?)
+    }
+
+    /// Hash of a file in the destination, kept so files of the run
+    /// compared to it don't read it again while it's unchanged.
+    fn destination_hash(&self, path: &Path) -> Result<Vec<u8>> {
+        let metadata = fs::metadata(path)?;
+        let (modified, len) = (metadata.modified()?, metadata.len());
+        if let Some(cached) = self
+            .dst_hashes
+            .lock()
+            .expect("destination hashes lock poisoned")
+            .get(path)
+        {
+            if cached.modified == modified && cached.len == len {
+                return Ok(cached.hash.clone());
+            }
+        }
+        let hash = (self.hash)(path)?;
+        self.dst_hashes
+            .lock()
+            .expect("destination hashes lock poisoned")
+            .insert(
+                path.to_path_buf(),
+                CachedHash {
+                    modified,
+                    len,
+                    hash: hash.clone(),
+                },
+            );
+        Ok(hash)
+    }
+
+    fn remove_duplicate(&self, file: &Path, existing: &Path) -> Result<()> {
+        match (self.dry_run, self.transfer_mode) {
+            (true, _) => println!("would skip {:?}, identical to {:?}", file, existing),
+            (false, TransferMode::Move) => {
+                fs::remove_file(file)
+                    .wrap_err_with(|| format!("failed to remove duplicate {:?}", file))?;
+                info!("removed {:?}, identical to {:?}", file, existing);
+            }
+            (false, TransferMode::Copy | TransferMode::Hardlink) => {
+                info!("skipping {:?}, identical to {:?}", file, existing)
+            }
+        }
+        Ok(())
+    }
+
+    fn trash_duplicate(&self, file: &Path, existing: &Path) -> Result<()> {
+        match (self.dry_run, self.transfer_mode) {
+            (true, _) => println!("would trash {:?}, {:?} exists", file, existing),
+            (false, TransferMode::Move) => {
+                (self.trash)(file).wrap_err_with(|| format!("failed to trash {:?}", file))?;
+                info!("trashed {:?}, a different {:?} exists", file, existing);
+            }
+            (false, TransferMode::Copy | TransferMode::Hardlink) => {
+                warn!(
+                    "skipping {:?}, a file with the same name already exists in {:?}",
+                    file, existing
+                )
+            }
+        }
+        Ok(())
+    }
+
+    /// First free path made by appending ` (1)`, ` (2)`, etc. to the
+    /// file name, before the extension.
+    fn renamed_path(dst_path: &Path) -> PathBuf {
+        Organizer::free_path(dst_path, |path| path.symlink_metadata().is_err())
+    }
+
+    /// First path made by appending ` (1)`, ` (2)`, etc. to the file
+    /// name for which `is_free` is true.
+    fn free_path(dst_path: &Path, is_free: impl Fn(&Path) -> bool) -> PathBuf {
+        let stem = dst_path.file_stem().unwrap_or_default();
+        (1..)
+            .map(|n| {
+                let mut name = stem.to_owned();
+                name.push(format!(" ({})", n));
+                if let Some(extension) = dst_path.extension() {
+                    name.push(".");
+                    name.push(extension);
+                }
+                dst_path.with_file_name(name)
+            })
+            .find(|path| is_free(path))
+            .expect("ran out of names")
+    }
+
+    /// Path of the file named `dst_name` in `dst_dir`, normalized and
+    /// sanitized.
+    fn destination_file(&self, dst_dir: &Path, dst_name: OsString) -> PathBuf {
+        let dst_path = dst_dir.join(self.normalize(dst_name));
+        if !self.sanitize_paths {
+            return dst_path;
+        }
+        // The dirs that exist are kept, even when they wouldn't be valid
+        // names, as they are already in the filesystem.
+        let existing = dst_path
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.is_dir())
+            .unwrap_or(Path::new(""));
+        let new = dst_path.strip_prefix(existing).unwrap_or(&dst_path);
+        new.components()
+            .fold(existing.to_path_buf(), |path, component| match component {
+                Component::Normal(name) => path.join(sanitize_name(name)),
+                component => path.join(component),
+            })
+    }
+
+    /// Applies the unicode normalization to the file name, names that are
+    /// not valid unicode are left untouched, and lowercases its
+    /// extension if enabled.
+    fn normalize(&self, file_name: OsString) -> OsString {
+        let file_name = match (self.unicode_normalization, file_name.to_str()) {
+            (Some(UnicodeNormalization::Nfc), Some(name)) => {
+                OsString::from(name.nfc().collect::<String>())
+            }
+            (Some(UnicodeNormalization::Nfd), Some(name)) => {
+                OsString::from(name.nfd().collect::<String>())
+            }
+            _ => file_name,
+        };
+        if !self.normalize_extension {
+            return file_name;
+        }
+        let file_name = PathBuf::from(file_name);
+        match file_name.extension().and_then(OsStr::to_str) {
+            Some(extension) if extension != extension.to_lowercase() => file_name
+                .with_extension(extension.to_lowercase())
+                .into_os_string(),
+            _ => file_name.into_os_string(),
+        }
+    }
+
+    fn transfer_file(
+        &self,
+        file: &Path,
+        dst_path: &Path,
+        source_hash: Option<&[u8]>,
+    ) -> Result<()> {
+        if let Some(dst_dir) = dst_path.parent() {
+            if !dst_dir.is_dir() {
+                fs::create_dir_all(dst_dir).wrap_err("failed to create destination dir")?;
+            }
+        }
+        self.dst_hashes
+            .lock()
+            .expect("destination hashes lock poisoned")
+            .remove(dst_path);
+        let mut delay = self.retry_delay;
+        for retry in 1..=self.retries {
+            match self.transfer_file_once(file, dst_path, source_hash) {
+                Err(e) if is_transient(&e) => {
+                    warn!(
+                        "failed to transfer {:?}, retrying in {:?} ({}/{}): {}",
+                        file, delay, retry, self.retries, e
+                    );
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        self.transfer_file_once(file, dst_path, source_hash)
+    }
+
+    fn transfer_file_once(
+        &self,
+        file: &Path,
+        dst_path: &Path,
+        source_hash: Option<&[u8]>,
+    ) -> Result<()> {
+        match self.transfer_mode {
+            TransferMode::Move => self.move_file(file, dst_path, source_hash),
+            TransferMode::Copy => copy_file(file, dst_path),
+            TransferMode::Hardlink => {
+                // Unlike renaming and copying, linking doesn't replace an
+                // existing file.
+                if self.conflict_strategy == ConflictStrategy::Overwrite && dst_path.is_file() {
+                    fs::remove_file(dst_path).wrap_err("failed to remove existing file")?;
+                }
+                Organizer::hardlink_file(file, dst_path)
+            }
+        }
+    }
+
+    /// Renames the file into the destination. Renaming doesn't work
+    /// across filesystems, like from an SD card, in which case the file
+    /// is copied and then removed from the source. With `source_hash` the
+    /// copy is verified before removing the source, so a corrupted copy
+    /// doesn't lose the file.
+    fn move_file(&self, file: &Path, dst_path: &Path, source_hash: Option<&[u8]>) -> Result<()> {
+        match (self.rename)(file, dst_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                info!(
+                    "{:?} is in another filesystem than {:?}, copying and removing it instead",
+                    file, dst_path
+                );
+                copy_file(file, dst_path)?;
+                if let Some(source_hash) = source_hash {
+                    Organizer::verify_file(source_hash, dst_path)?;
+                }
+                fs::remove_file(file).wrap_err("failed to remove source file after copying it")
+            }
+            Err(source) => Err(OrganizerError::Io {
+                action: "move to the destination dir",
+                path: file.to_path_buf(),
+                source,
+            }
+            .into()),
+        }
+    }
+
+    fn hardlink_file(file: &Path, dst_path: &Path) -> Result<()> {
+        match fs::hard_link(file, dst_path) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                warn!(
+                    "failed to hardlink {:?} to {:?}, copying it instead: {}",
+                    file, dst_path, e
+                );
+                copy_file(file, dst_path)
+            }
+        }
+    }
+
+    fn link_into_flat_dir(&self, file: &Path, flat_dir: &Path) -> Result<()> {
+        if !flat_dir.is_dir() {
+            fs::create_dir_all(flat_dir).wrap_err("failed to create flat dir")?;
+        }
+
+        let file_name = match file.file_name() {
+            Some(name) => name,
+            None => return Err(eyre!("failed to get file name")),
+        };
+        // Files of other jobs may be linked with the same name meanwhile.
+        let _claimed = self
+            .claimed_paths
+            .lock()
+            .expect("claimed paths lock poisoned");
+        let mut link_path = flat_dir.join(file_name);
+        if link_path.symlink_metadata().is_ok() {
+            match self.conflict_strategy {
+                ConflictStrategy::Skip => return Ok(()),
+                ConflictStrategy::Overwrite => {
+                    fs::remove_file(&link_path).wrap_err("failed to remove existing link")?
+                }
+                ConflictStrategy::Rename => link_path = Organizer::renamed_path(&link_path),
+                ConflictStrategy::Error => {
+                    return Err(OrganizerError::DestinationExists(link_path).into())
+                }
+            }
+        }
+        if fs::hard_link(file, &link_path).is_ok() {
+            return Ok(());
+        }
+        symlink(file, &link_path).wrap_err("failed to create hardlink or symlink")
+    }
+}
+
+/// Removes the empty directories inside `dir`, the deepest first, so
+/// directories that only contain empty ones are removed too. `dir` is
+/// kept. Symlinks are not followed.
+fn prune_empty_dirs(dir: &Path) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("failed to read dir {:?} to prune it: {}", dir, e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let sub_dir = entry.path();
+        prune_empty_dirs(&sub_dir);
+        let is_empty = fs::read_dir(&sub_dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if is_empty {
+            if let Err(e) = fs::remove_dir(&sub_dir) {
+                error!("failed to remove empty dir {:?}: {}", sub_dir, e);
+            }
+        }
+    }
+}
+
+/// Checks that every file found in the media source has an outcome, and
+/// that no other file has one.
+fn reconcile(files: &[PathBuf], outcomes: &HashMap<PathBuf, Outcome>) -> Result<()> {
+    let missing: Vec<&PathBuf> = files
+        .iter()
+        .filter(|file| !outcomes.contains_key(*file))
+        .collect();
+    let found: HashSet<&PathBuf> = files.iter().collect();
+    let unexpected: Vec<&PathBuf> = outcomes
+        .keys()
+        .filter(|file| !found.contains(file))
+        .collect();
+    if !missing.is_empty() || !unexpected.is_empty() {
+        bail!(
+            "{} files were found but {} were accounted for, missing: {:?}, unexpected: {:?}",
+            files.len(),
+            outcomes.len(),
+            missing,
+            unexpected
+        );
+    }
+    Ok(())
+}
+
+/// The media source `file` was found in, the innermost when they are
+/// nested.
+fn media_src_of<'a>(media_srcs: &'a [PathBuf], file: &Path) -> &'a Path {
+    media_srcs
+        .iter()
+        .filter(|media_src| file.starts_with(media_src))
+        .max_by_key(|media_src| media_src.components().count())
+        .map(PathBuf::as_path)
+        .unwrap_or_else(|| Path::new(""))
+}
+
+/// Extensions of the photo of a Live Photo.
+const LIVE_PHOTO_EXTENSIONS: [&str; 3] = ["heic", "jpg", "jpeg"];
+
+fn is_live_photo_video(file: &Path) -> bool {
+    file.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mov"))
+}
+
+/// The other half of a Live Photo, a `.heic` or `.jpg` photo and a
+/// `.mov` video with the same name in the same dir, when `file` is one
+/// of them.
+fn live_photo_sibling(file: &Path) -> Option<PathBuf> {
+    let extension = file.extension()?;
+    let sibling_extensions: &[&str] = if is_live_photo_video(file) {
+        &LIVE_PHOTO_EXTENSIONS
+    } else if LIVE_PHOTO_EXTENSIONS
+        .iter()
+        .any(|photo_extension| extension.eq_ignore_ascii_case(photo_extension))
+    {
+        &["mov"]
+    } else {
+        return None;
+    };
+    sibling_extensions
+        .iter()
+        .flat_map(|extension| [extension.to_string(), extension.to_uppercase()])
+        .map(|extension| file.with_extension(extension))
+        .find(|sibling| sibling.is_file())
+}
+
+/// If the error was caused by an IO error that may not happen again.
+fn is_transient(error: &color_eyre::Report) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<io::Error>())
+        .any(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            )
+        })
+}
+
+/// Copies the file keeping its access and modification times, which
+/// renaming and hardlinking already keep.
+pub(crate) fn copy_file(file: &Path, dst_path: &Path) -> Result<()> {
+    fs::copy(file, dst_path).wrap_err("failed to copy file to destination dir")?;
+    let metadata = fs::metadata(file).wrap_err("failed to read source file metadata")?;
+    filetime::set_file_times(
+        dst_path,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+    .wrap_err("failed to keep the times of the copied file")
+}
+
+/// SHA-256 of the content of the file.
+pub(crate) fn file_hash(path: &Path) -> Result<Vec<u8>> {
+    let mut file =
+        fs::File::open(path).wrap_err_with(|| format!("failed to open {:?} to hash it", path))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).wrap_err_with(|| format!("failed to hash {:?}", path))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Sorts the files by modification time, the most recent first. Files
+/// without a modification time go last.
+fn sort_newest_first(files: &mut [PathBuf]) {
+    files.sort_by_cached_key(|file| Reverse(fs::metadata(file).and_then(|m| m.modified()).ok()));
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use photos::PhotoOrganizer;
+    use std::sync::atomic::{AtomicU64, AtomicUsize};
+    use std::sync::Arc;
+    use std::sync::Once;
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use videos::VideoOrganizer;
+
+    static LOGGED: Mutex<Vec<(log::Level, String)>> = Mutex::new(Vec::new());
+
+    /// Logger that keeps the records in [`LOGGED`], tests run in
+    /// parallel so they should only look for their own records.
+    struct CapturingLogger;
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOGGED
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn capture_logs() {
+        static LOGGER: CapturingLogger = CapturingLogger;
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    #[test]
+    fn organize_logs_errors() {
+        capture_logs();
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let broken = src.path().join("broken.jpg");
+        fs::write(&broken, "not a photo").unwrap();
+
+        Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let broken = format!("{:?}", broken);
+        assert!(LOGGED
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(level, message)| *level == log::Level::Error && message.contains(&broken)));
+    }
+
+    #[test]
+    fn organize() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let exif_photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("camera.jpg");
+        fs::copy(exif_photo, src.path().join("camera.jpg")).unwrap();
+
+        let wa_photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let sub_dir = src.path().join("sub_dir");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::copy(wa_photo, sub_dir.join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let video = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("20200829_205420.mp4");
+        let sub_sub_dir = sub_dir.join("sub_dir");
+        fs::create_dir(&sub_sub_dir).unwrap();
+        fs::copy(video, sub_sub_dir.join("20200829_205420.mp4")).unwrap();
+
+        fs::write(src.path().join("notes.txt"), "not media").unwrap();
+        fs::write(sub_dir.join("broken.jpg"), "not a photo").unwrap();
+
+        let report = Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
+            Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
+        ])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(3, report.moved);
+        assert_eq!(0, report.skipped);
+        assert_eq!(1, report.errored);
+        assert_eq!(1, report.unhandled);
+        assert_eq!(
+            Counts {
+                moved: 2,
+                skipped: 0,
+                errored: 1,
+                corrupted: 0,
+                empty: 0
+            },
+            report.by_organizer["photos"]
+        );
+        assert_eq!(
+            Counts {
+                moved: 1,
+                skipped: 0,
+                errored: 0,
+                corrupted: 0,
+                empty: 0
+            },
+            report.by_organizer["videos"]
+        );
+
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("01 - January")
+            .join("camera.jpg")
+            .is_file());
+
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("20200829_205420.mp4")
+            .is_file());
+    }
+
+    #[test]
+    fn organize_and_undo() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let journal_dir = TempDir::new().unwrap();
+        let journal_path = journal_dir.path().join("journal").join("journal.tsv");
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        let sub_dir = src.path().join("sub_dir");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            sub_dir.join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            sub_dir.join("20200829_205420.mp4"),
+        )
+        .unwrap();
+        let tree = |dir: &Path| {
+            let mut files: Vec<PathBuf> = FilesIter::new(dir)
+                .map(|file| file.strip_prefix(dir).unwrap().to_path_buf())
+                .collect();
+            files.sort();
+            files
+        };
+        let original = tree(src.path());
+
+        Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
+            Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
+        ])
+        .with_journal(Journal::new(journal_path.clone()))
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+        assert!(tree(src.path()).is_empty());
+        assert_eq!(
+            3,
+            fs::read_to_string(&journal_path).unwrap().lines().count()
+        );
+        fs::remove_dir(&sub_dir).unwrap();
+
+        Journal::new(journal_path.clone()).undo().unwrap();
+        assert_eq!(original, tree(src.path()));
+        assert!(tree(dst.path()).is_empty());
+        assert_eq!(
+            fs::read(fixtures.join("camera.jpg")).unwrap(),
+            fs::read(src.path().join("camera.jpg")).unwrap()
+        );
+        assert!(!journal_path.exists());
+    }
+
+    #[test]
+    fn reconcile_outcomes() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        fs::write(src.path().join("notes.txt"), "not media").unwrap();
+        fs::write(src.path().join("no-date.jpg"), "not a photo").unwrap();
+
+        let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))]);
+        let files: Vec<PathBuf> = organizer.files(src.path()).collect();
+        let outcomes: HashMap<PathBuf, Outcome> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.clone(),
+                    organizer.organize_file(file, src.path()).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            Outcome::Moved("photos"),
+            outcomes[&src.path().join("camera.jpg")]
+        );
+        assert_eq!(Outcome::Unhandled, outcomes[&src.path().join("notes.txt")]);
+        assert_eq!(
+            Outcome::Errored("photos"),
+            outcomes[&src.path().join("no-date.jpg")]
+        );
+        assert!(reconcile(&files, &outcomes).is_ok());
+
+        // A file that fell through without an outcome.
+        let mut dropped = outcomes.clone();
+        dropped.remove(&src.path().join("notes.txt"));
+        let err = reconcile(&files, &dropped).unwrap_err().to_string();
+        assert!(
+            err.contains("missing: [") && err.contains("notes.txt"),
+            "{}",
+            err
+        );
+
+        // A file found later that was never organized.
+        let mut unreported = files.clone();
+        unreported.push(src.path().join("late.jpg"));
+        let err = reconcile(&unreported, &outcomes).unwrap_err().to_string();
+        assert!(err.contains("late.jpg"), "{}", err);
+
+        // An outcome for a file that wasn't found, with the counts still
+        // matching.
+        let mut unknown = dropped;
+        unknown.insert(src.path().join("unknown.jpg"), Outcome::Unhandled);
+        assert_eq!(files.len(), unknown.len());
+        let err = reconcile(&files, &unknown).unwrap_err().to_string();
+        assert!(
+            err.contains("unexpected: [") && err.contains("unknown.jpg"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn organize_file_reports_json() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        fs::write(src.path().join("notes.txt"), "not media").unwrap();
+        fs::write(src.path().join("no-date.jpg"), "not a photo").unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_file_reports(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(1, json["moved"]);
+        let files = json["files"].as_array().unwrap();
+        assert_eq!(3, files.len());
+        let entry = |name: &str| {
+            files
+                .iter()
+                .find(|file| file["path"] == src.path().join(name).to_str().unwrap())
+                .unwrap()
+        };
+        let camera = entry("camera.jpg");
+        assert_eq!("moved", camera["outcome"]);
+        assert_eq!("photos", camera["organizer"]);
+        assert_eq!("2019-01", camera["date"]);
+        assert_eq!(
+            dst.path()
+                .join("2019")
+                .join("01 - January")
+                .join("camera.jpg")
+                .to_str()
+                .unwrap(),
+            camera["destination"]
+        );
+        let no_date = entry("no-date.jpg");
+        assert_eq!("errored", no_date["outcome"]);
+        assert!(no_date["destination"].is_null());
+        let notes = entry("notes.txt");
+        assert_eq!("unhandled", notes["outcome"]);
+        assert!(notes["organizer"].is_null());
+    }
+
+    #[test]
+    fn organize_prune_empty() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let sub_sub_dir = src.path().join("sub_dir").join("sub_dir");
+        fs::create_dir_all(&sub_sub_dir).unwrap();
+        fs::create_dir(src.path().join("sub_dir").join("empty")).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            src.path().join("sub_dir").join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            sub_sub_dir.join("20200829_205420.mp4"),
+        )
+        .unwrap();
+        let hidden_dir = src.path().join("hidden");
+        fs::create_dir(&hidden_dir).unwrap();
+        fs::write(hidden_dir.join(".nomedia"), "").unwrap();
+
+        Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
+            Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
+        ])
+        .with_prune_empty(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(src.path().is_dir());
+        assert!(!src.path().join("sub_dir").exists());
+        assert!(hidden_dir.join(".nomedia").is_file());
+        assert_eq!(1, fs::read_dir(src.path()).unwrap().count());
+    }
+
+    #[test]
+    fn organize_twice() {
+        let src = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            src.path().join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        // Organized into the source itself, so the second run finds the
+        // files already in their destination.
+        let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            src.path().to_path_buf(),
+        ))])
+        .with_conflict_strategy(ConflictStrategy::Rename)
+        .with_dedup(true);
+        let tree = || {
+            let mut files: Vec<PathBuf> = FilesIter::new(src.path()).collect();
+            files.sort();
+            files
+        };
+
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+        assert_eq!(2, report.moved);
+        let organized = tree();
+
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+        assert_eq!(0, report.moved);
+        assert_eq!(2, report.skipped);
+        assert_eq!(0, report.errored);
+        assert_eq!(organized, tree());
+        assert!(src
+            .path()
+            .join("2019")
+            .join("01 - January")
+            .join("camera.jpg")
+            .is_file());
+    }
+
+    #[test]
+    fn organize_skipping_empty_files() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        // Named with a date, it'd be organized if it wasn't empty.
+        fs::File::create(src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+        fs::File::create(src.path().join("notes.txt")).unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_skip_empty(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(1, report.moved);
+        assert_eq!(1, report.empty);
+        assert_eq!(0, report.skipped);
+        assert_eq!(0, report.errored);
+        assert_eq!(1, report.unhandled);
+        assert_eq!(1, report.by_organizer["photos"].empty);
+        assert!(src.path().join("IMG-20200407-WA0004.jpg").is_file());
+        assert!(!dst.path().join("2020").exists());
+        assert_eq!(
+            "moved: 1, skipped: 0, errored: 0, corrupted: 0, empty: 1, unhandled: 1\n  \
+             photos: moved: 1, skipped: 0, errored: 0, corrupted: 0, empty: 1",
+            report.to_string()
+        );
+    }
+
+    #[test]
+    fn organize_with_progress() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for jobs in [1, 2] {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            fs::create_dir(src.path().join("sub_dir")).unwrap();
+            fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+            fs::copy(
+                fixtures.join("IMG-20200407-WA0004.jpg"),
+                src.path().join("sub_dir").join("IMG-20200407-WA0004.jpg"),
+            )
+            .unwrap();
+            fs::write(src.path().join("notes.txt"), "notes").unwrap();
+
+            let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_jobs(jobs)
+            .with_progress(true);
+            let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+            assert_eq!(2, report.moved);
+            assert_eq!(1, report.unhandled);
+            assert!(dst
+                .path()
+                .join("2020")
+                .join("04 - April")
+                .join("IMG-20200407-WA0004.jpg")
+                .is_file());
+            assert!(organizer.lock_progress().is_none());
+        }
+    }
+
+    #[test]
+    fn organize_without_recursing() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let sub_dir = src.path().join("sub_dir");
+        fs::create_dir_all(sub_dir.join("sub_dir")).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            src.path().join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(fixtures.join("camera.jpg"), sub_dir.join("camera.jpg")).unwrap();
+        fs::copy(
+            fixtures.join("canon.jpg"),
+            sub_dir.join("sub_dir").join("canon.jpg"),
+        )
+        .unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_no_recurse(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+        assert!(sub_dir.join("camera.jpg").is_file());
+        assert!(sub_dir.join("sub_dir").join("canon.jpg").is_file());
+    }
+
+    #[test]
+    fn organize_multiple_sources() {
+        let sd_card = TempDir::new().unwrap();
+        let backup = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            sd_card.path().join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures.join("IMG_20200407_1.png"),
+            backup.path().join("IMG_20200407_1.png"),
+        )
+        .unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .organize(&[sd_card.path().to_path_buf(), backup.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+        assert!(dst
+            .path()
+            .join("2018")
+            .join("03 - March")
+            .join("IMG_20200407_1.png")
+            .is_file());
+    }
+
+    #[test]
+    fn preview() {
+        let src = TempDir::new().unwrap();
+        let dst = PathBuf::from("dst");
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for photo in ["IMG-20200407-WA0004.jpg", "IMG_20200407_1.png"] {
+            fs::copy(fixtures.join(photo), src.path().join(photo)).unwrap();
+        }
+        fs::write(src.path().join("notes.txt"), "notes").unwrap();
+
+        let preview = Organizer::new(vec![Box::new(PhotoOrganizer::new(dst.clone()))])
+            .preview(&[src.path().to_path_buf()]);
+
+        let expected = format!(
+            "{}\n    {} -> IMG_20200407_1.png\n{}\n    {} -> IMG-20200407-WA0004.jpg\nNot organized\n    {}\n",
+            dst.join("2018").join("03 - March").display(),
+            src.path().join("IMG_20200407_1.png").display(),
+            dst.join("2020").join("04 - April").display(),
+            src.path().join("IMG-20200407-WA0004.jpg").display(),
+            src.path().join("notes.txt").display(),
+        );
+        assert_eq!(expected, preview);
+        assert_eq!(3, fs::read_dir(src.path()).unwrap().count());
+    }
+
+    #[test]
+    fn plan_and_apply() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let media_src = || {
+            let src = TempDir::new().unwrap();
+            for dir in ["a", "b"] {
+                fs::create_dir(src.path().join(dir)).unwrap();
+                fs::copy(
+                    fixtures.join("IMG-20200407-WA0004.jpg"),
+                    src.path().join(dir).join("IMG-20200407-WA0004.jpg"),
+                )
+                .unwrap();
+            }
+            fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+            fs::copy(
+                fixtures.join("20200829_205420.mp4"),
+                src.path().join("20200829_205420.mp4"),
+            )
+            .unwrap();
+            fs::write(src.path().join("notes.txt"), "not media").unwrap();
+            src
+        };
+        let organizer = |dst: &Path| {
+            Organizer::new(vec![
+                Box::new(PhotoOrganizer::new(dst.to_path_buf())),
+                Box::new(VideoOrganizer::new(dst.to_path_buf())),
+            ])
+            .with_conflict_strategy(ConflictStrategy::Rename)
+        };
+        let organized = |dst: &Path| {
+            let mut files: Vec<PathBuf> = FilesIter::new(dst)
+                .map(|file| file.strip_prefix(dst).unwrap().to_path_buf())
+                .collect();
+            files.sort();
+            files
+        };
+
+        let direct_src = media_src();
+        let direct_dst = TempDir::new().unwrap();
+        let direct = organizer(direct_dst.path())
+            .organize(&[direct_src.path().to_path_buf()])
+            .unwrap();
+
+        let src = media_src();
+        let dst = TempDir::new().unwrap();
+        let plan_dir = TempDir::new().unwrap();
+        let plan_file = plan_dir.path().join("plan.json");
+        organizer(dst.path())
+            .plan(&[src.path().to_path_buf()])
+            .write(&plan_file)
+            .unwrap();
+        assert_eq!(0, fs::read_dir(dst.path()).unwrap().count());
+        let plan = Plan::read(&plan_file).unwrap();
+        assert_eq!(4, plan.moves.len());
+
+        let applied = organizer(dst.path()).apply(&plan).unwrap();
+        assert_eq!(direct.moved, applied.moved);
+        assert_eq!(direct.by_organizer, applied.by_organizer);
+        assert_eq!(organized(direct_dst.path()), organized(dst.path()));
+        assert!(organized(dst.path()).contains(
+            &PathBuf::from("2020")
+                .join("04 - April")
+                .join("IMG-20200407-WA0004 (1).jpg")
+        ));
+        assert_eq!(vec![PathBuf::from("notes.txt")], organized(src.path()));
+
+        // The sources are gone, so they can't be applied again.
+        let reapplied = organizer(dst.path()).apply(&plan).unwrap();
+        assert_eq!(4, reapplied.errored);
+        assert_eq!(0, reapplied.moved);
+    }
+
+    #[test]
+    fn organize_built_with_builder() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        let existing_dir = dst.path().join("2019").join("01 - January");
+        fs::create_dir_all(&existing_dir).unwrap();
+        fs::write(existing_dir.join("camera.jpg"), "another photo").unwrap();
+
+        let report = OrganizerBuilder::new()
+            .add_organizer(PhotoOrganizer::new(dst.path().to_path_buf()))
+            .transfer_mode(TransferMode::Copy)
+            .conflict_strategy(ConflictStrategy::Rename)
+            .build()
+            .unwrap()
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(src.path().join("camera.jpg").is_file());
+        assert!(existing_dir.join("camera (1).jpg").is_file());
+        assert_eq!(
+            "at least one media type organizer is required",
+            OrganizerBuilder::new().build().err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn organize_preserving_structure() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for jobs in [1, 2] {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            let day_dir = src.path().join("Trip").join("day1");
+            fs::create_dir_all(&day_dir).unwrap();
+            fs::copy(fixtures.join("camera.jpg"), day_dir.join("camera.jpg")).unwrap();
+            fs::copy(
+                fixtures.join("IMG-20200407-WA0004.jpg"),
+                src.path().join("IMG-20200407-WA0004.jpg"),
+            )
+            .unwrap();
+
+            Organizer::new(vec![Box::new(PhotoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_preserve_structure(true)
+            .with_jobs(jobs)
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+            assert!(dst
+                .path()
+                .join("2019")
+                .join("01 - January")
+                .join("Trip")
+                .join("day1")
+                .join("camera.jpg")
+                .is_file());
+            assert!(dst
+                .path()
+                .join("2020")
+                .join("04 - April")
+                .join("IMG-20200407-WA0004.jpg")
+                .is_file());
+        }
+    }
+
+    #[test]
+    fn organize_sanitizing_paths() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let trip_dir = src.path().join("2019-07 Paris: day 1?.");
+        fs::create_dir(&trip_dir).unwrap();
+        fs::copy(fixtures.join("camera.jpg"), trip_dir.join("camera*.jpg")).unwrap();
+
+        let report = Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_respect_date_folders(true),
+        )])
+        .with_sanitize_paths(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("07 - July")
+            .join("2019-07 Paris_ day 1_")
+            .join("camera_.jpg")
+            .is_file());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn organize_non_unicode_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let src_dir = TempDir::new().unwrap();
+        let src = src_dir.path().join(OsStr::from_bytes(b"caf\xe9"));
+        fs::create_dir(&src).unwrap();
+        let dst = TempDir::new().unwrap();
+        // The date of the first is in the exif and of the second in its name.
+        let exif_name = OsStr::from_bytes(b"caf\xe9.JPG");
+        let dated_name = OsStr::from_bytes(b"20200407_164809 caf\xe9.jpg");
+        fs::copy(fixtures.join("camera.jpg"), src.join(exif_name)).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            src.join(dated_name),
+        )
+        .unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .organize(&[src])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("01 - January")
+            .join(exif_name)
+            .is_file());
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join(dated_name)
+            .is_file());
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(
+            "Paris_ day 1_",
+            sanitize_name(OsStr::new("Paris: day 1?. "))
+        );
+        assert_eq!("a_b_c_d", sanitize_name(OsStr::new("a*b\"c<d")));
+        assert_eq!("_", sanitize_name(OsStr::new("...")));
+        assert_eq!(".hidden", sanitize_name(OsStr::new(".hidden")));
+    }
+
+    #[test]
+    fn organize_excluding() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::write(src.path().join("foo.tmp"), "temporary").unwrap();
+        let trash = src.path().join("Trash");
+        fs::create_dir(&trash).unwrap();
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, trash.join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_exclude(vec![
+            Pattern::new("*.tmp").unwrap(),
+            Pattern::new("Trash/*").unwrap(),
+        ])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(Report::default(), report);
+        assert!(src.path().join("foo.tmp").is_file());
+        assert!(trash.join("IMG-20200407-WA0004.jpg").is_file());
+    }
+
+    #[test]
+    fn organize_watched_paths() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))]);
+
+        let (tx, rx) = mpsc::channel();
+        let inbox = src.path().to_path_buf();
+        let dropper = thread::spawn(move || {
+            let photo = PathBuf::from(file!())
+                .parent()
+                .unwrap()
+                .join("fixtures")
+                .join("IMG-20200407-WA0004.jpg");
+            let new_file = inbox.join("IMG-20200407-WA0004.jpg");
+            fs::copy(photo, &new_file).unwrap();
+            tx.send(new_file.clone()).unwrap();
+            // Notified twice, the second time it's already organized.
+            tx.send(new_file).unwrap();
+            tx.send(PathBuf::from("/elsewhere/IMG-20200407-WA0004.jpg"))
+                .unwrap();
+        });
+        let report = organizer
+            .organize_paths(&[src.path().to_path_buf()], rx)
+            .unwrap();
+        dropper.join().unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+    }
+
+    #[test]
+    fn watch_until_stopped() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))]);
+        let organized = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+
+        let stop = AtomicBool::new(false);
+        let report = thread::scope(|scope| {
+            scope.spawn(|| {
+                // Give the watcher time to start.
+                thread::sleep(Duration::from_millis(500));
+                let photo = PathBuf::from(file!())
+                    .parent()
+                    .unwrap()
+                    .join("fixtures")
+                    .join("IMG-20200407-WA0004.jpg");
+                fs::copy(photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+                for _ in 0..100 {
+                    if organized.is_file() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+                stop.store(true, Ordering::Relaxed);
+            });
+            organizer.watch(
+                &[src.path().to_path_buf()],
+                Duration::from_millis(100),
+                &stop,
+            )
+        })
+        .unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(organized.is_file());
+    }
+
+    #[test]
+    fn organize_across_filesystems() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let mut organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))]);
+        organizer.rename = Box::new(|_, _| Err(io::ErrorKind::CrossesDevices.into()));
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+        assert_eq!(1, report.moved);
+        assert!(!src.path().join("IMG-20200407-WA0004.jpg").exists());
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+    }
+
+    #[test]
+    fn verify_across_filesystems() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let src_photo = src.path().join("IMG-20200407-WA0004.jpg");
+        fs::copy(&photo, &src_photo).unwrap();
+
+        let mut organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_verify(true);
+        // The source changes after it's hashed, so the copy doesn't match
+        // it, like when the copy is corrupted.
+        organizer.rename = Box::new(|file, _| {
+            fs::write(file, b"changed after hashing")?;
+            Err(io::ErrorKind::CrossesDevices.into())
+        });
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+        assert_eq!(0, report.moved);
+        assert_eq!(1, report.corrupted);
+        assert!(src_photo.is_file());
+
+        fs::copy(&photo, &src_photo).unwrap();
+        let dst_photo = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::remove_file(&dst_photo).unwrap();
+        organizer.rename = Box::new(|_, _| Err(io::ErrorKind::CrossesDevices.into()));
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+        assert_eq!(1, report.moved);
+        assert_eq!(0, report.corrupted);
+        assert!(!src_photo.exists());
+        assert!(dst_photo.is_file());
+    }
+
+    #[test]
+    fn organize_retrying_transient_errors() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        for (kind, moved) in [
+            (io::ErrorKind::Interrupted, 1),
+            (io::ErrorKind::TimedOut, 1),
+            (io::ErrorKind::NotFound, 0),
+        ] {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            fs::copy(&photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+
+            let attempts = Arc::new(AtomicUsize::new(0));
+            let mut organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_retries(3, Duration::from_millis(1));
+            let rename_attempts = Arc::clone(&attempts);
+            // Fails twice, then renames.
+            organizer.rename = Box::new(move |from, to| {
+                if rename_attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(kind.into())
+                } else {
+                    fs::rename(from, to)
+                }
+            });
+            let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+            assert_eq!(moved, report.moved);
+            if moved == 1 {
+                assert_eq!(3, attempts.load(Ordering::SeqCst));
+                assert!(dst
+                    .path()
+                    .join("2020")
+                    .join("04 - April")
+                    .join("IMG-20200407-WA0004.jpg")
+                    .is_file());
+            } else {
+                assert_eq!(1, attempts.load(Ordering::SeqCst));
+                assert!(src.path().join("IMG-20200407-WA0004.jpg").is_file());
+            }
+        }
+    }
+
+    #[test]
+    fn organize_copy_verify() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::copy(&photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_transfer_mode(TransferMode::Copy)
+        .with_verify(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+        assert_eq!(1, report.moved);
+        assert_eq!(0, report.corrupted);
+
+        let copy = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+        let source_hash = file_hash(&photo).unwrap();
+        Organizer::verify_file(&source_hash, &copy).unwrap();
+        fs::write(&copy, b"corrupted copy").unwrap();
+        assert!(Organizer::verify_file(&source_hash, &copy).is_err());
+    }
+
+    #[test]
+    fn organize_copy() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_transfer_mode(TransferMode::Copy);
+        organizer.organize(&[src.path().to_path_buf()]).unwrap();
+
+        assert!(src.path().join("IMG-20200407-WA0004.jpg").is_file());
+        let copy = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+        assert!(copy.is_file());
+
+        // The source is still there, but it collides with the copy.
+        fs::write(&copy, b"modified copy").unwrap();
+        organizer.organize(&[src.path().to_path_buf()]).unwrap();
+        assert_eq!(b"modified copy".to_vec(), fs::read(&copy).unwrap());
+    }
+
+    #[test]
+    fn organize_copy_keeps_times() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let src_photo = src.path().join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, &src_photo).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_586_268_000);
+        filetime::set_file_mtime(&src_photo, FileTime::from_system_time(modified)).unwrap();
+
+        Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_transfer_mode(TransferMode::Copy)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let copy = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+        let copy_modified = fs::metadata(copy).unwrap().modified().unwrap();
+        let difference = copy_modified
+            .duration_since(modified)
+            .unwrap_or_else(|e| e.duration());
+        assert!(difference < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn organize_hardlink() {
+        use std::os::unix::fs::MetadataExt;
+
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let src_photo = src.path().join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, &src_photo).unwrap();
+
+        Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_transfer_mode(TransferMode::Hardlink)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let dst_photo = dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg");
+        assert_eq!(
+            fs::metadata(&src_photo).unwrap().ino(),
+            fs::metadata(&dst_photo).unwrap().ino()
+        );
+    }
+
+    #[test]
+    fn organize_respecting_date_folders() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let trip = src.path().join("2019-07-trip");
+        fs::create_dir(&trip).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            trip.join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            trip.join("20200829_205420.mp4"),
+        )
+        .unwrap();
+        let not_a_date = src.path().join("2019-trip");
+        fs::create_dir(&not_a_date).unwrap();
+        fs::copy(
+            fixtures.join("IMG_20200407_164808037.jpg"),
+            not_a_date.join("IMG_20200407_164808037.jpg"),
+        )
+        .unwrap();
+
+        Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(dst.path().to_path_buf()).with_respect_date_folders(true)),
+            Box::new(VideoOrganizer::new(dst.path().to_path_buf()).with_respect_date_folders(true)),
+        ])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("07 - July")
+            .join("2019-07-trip")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("2019-07-trip")
+            .join("20200829_205420.mp4")
+            .is_file());
+        assert!(dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG_20200407_164808037.jpg")
+            .is_file());
+    }
+
+    /// Organizes a video into a destination where a file with the same
+    /// name already exists, returning the destination dir of the video.
+    fn organize_conflicting(conflict_strategy: ConflictStrategy) -> (TempDir, TempDir, PathBuf) {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::write(src.path().join("20200829_205420.mp4"), "new").unwrap();
+        let dst_dir = dst.path().join("2020");
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(dst_dir.join("20200829_205420.mp4"), "old").unwrap();
 
-impl Organizer {
-    /// Creates a new organizer with the given [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
-    pub fn new(media_type_organizers: Vec<Box<dyn MediaTypeOrganizer>>) -> Organizer {
-        Organizer {
-            media_type_organizers,
+        Organizer::new(vec![Box::new(VideoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_conflict_strategy(conflict_strategy)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+        (src, dst, dst_dir)
+    }
+
+    #[test]
+    fn organize_conflict_skip() {
+        let (src, _dst, dst_dir) = organize_conflicting(ConflictStrategy::Skip);
+        assert!(src.path().join("20200829_205420.mp4").is_file());
+        assert_eq!(
+            "old",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+        assert_eq!(1, fs::read_dir(&dst_dir).unwrap().count());
+    }
+
+    #[test]
+    fn organize_conflict_overwrite() {
+        let (src, _dst, dst_dir) = organize_conflicting(ConflictStrategy::Overwrite);
+        assert!(!src.path().join("20200829_205420.mp4").exists());
+        assert_eq!(
+            "new",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+        assert_eq!(1, fs::read_dir(&dst_dir).unwrap().count());
+    }
+
+    #[test]
+    fn organize_conflict_rename() {
+        let (src, _dst, dst_dir) = organize_conflicting(ConflictStrategy::Rename);
+        assert!(!src.path().join("20200829_205420.mp4").exists());
+        assert_eq!(
+            "old",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+        assert_eq!(
+            "new",
+            fs::read_to_string(dst_dir.join("20200829_205420 (1).mp4")).unwrap()
+        );
+
+        fs::write(dst_dir.join("20200829_205420 (2).mp4"), "").unwrap();
+        assert_eq!(
+            dst_dir.join("20200829_205420 (3).mp4"),
+            Organizer::renamed_path(&dst_dir.join("20200829_205420.mp4"))
+        );
+    }
+
+    #[test]
+    fn organize_flatten() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for dir in ["phone", "backup/phone"] {
+            fs::create_dir_all(src.path().join(dir)).unwrap();
+            fs::copy(
+                fixtures.join("IMG-20200407-WA0004.jpg"),
+                src.path().join(dir).join("IMG-20200407-WA0004.jpg"),
+            )
+            .unwrap();
+        }
+
+        let report = Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_rename_by_date(true),
+        )])
+        .with_flatten(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        let month_dir = dst.path().join("2020").join("04 - April");
+        assert!(month_dir.join("202004_IMG-20200407-WA0004.jpg").is_file());
+        assert!(month_dir
+            .join("202004_IMG-20200407-WA0004 (1).jpg")
+            .is_file());
+
+        // Nothing is in the destination in dry run mode.
+        let organizer = Organizer::new(vec![]).with_flatten(true).with_dry_run(true);
+        let dst_dir = dst.path().join("2021");
+        for expected in ["video.mp4", "video (1).mp4", "video (2).mp4"] {
+            assert_eq!(
+                Destination::Path(dst_dir.join(expected)),
+                organizer
+                    .destination_path(
+                        Path::new("video.mp4"),
+                        &dst_dir,
+                        OsString::from("video.mp4")
+                    )
+                    .unwrap()
+            );
         }
     }
 
-    /// Organize all the media files in the given media source
-    /// and its subdirectories according to the
-    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers).
-    ///
-    /// For each file it goes over all the registered
-    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers) to
-    /// determine if the file is supported and should be organize.
-    /// If the file should be organize it gets the new destination
-    /// path and moves the file to it.
-    ///
-    /// The files are handled by the first
-    /// [`MediaTypeOrganizers`](self::MediaTypeOrganizers)
-    /// that returns a new destination directory and for which the move
-    /// operation successfully executes.
-    pub fn organize(&self, media_src: PathBuf) -> Result<()> {
-        for file in FilesIter::new(media_src) {
-            for media_type_organizer in &self.media_type_organizers {
-                if !media_type_organizer.should_organize(&file) {
-                    continue;
+    #[test]
+    fn organize_conflict_error() {
+        let (src, _dst, dst_dir) = organize_conflicting(ConflictStrategy::Error);
+        assert!(src.path().join("20200829_205420.mp4").is_file());
+        assert_eq!(
+            "old",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+        assert_eq!(1, fs::read_dir(&dst_dir).unwrap().count());
+    }
+
+    #[test]
+    fn destination_exists_and_io_errors() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let file = src.path().join("20200829_205420.mp4");
+        fs::write(&file, "new").unwrap();
+        fs::write(dst.path().join("20200829_205420.mp4"), "old").unwrap();
+        let organizer = Organizer::new(Vec::new());
+
+        let err = organizer
+            .destination_path(&file, dst.path(), OsString::from("20200829_205420.mp4"))
+            .unwrap_err();
+        match err.downcast_ref::<OrganizerError>() {
+            Some(OrganizerError::DestinationExists(path)) => {
+                assert_eq!(&dst.path().join("20200829_205420.mp4"), path)
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+
+        let missing = src.path().join("missing.mp4");
+        let err = organizer
+            .move_file(&missing, &dst.path().join("missing.mp4"), None)
+            .unwrap_err();
+        match err.downcast_ref::<OrganizerError>() {
+            Some(OrganizerError::Io { path, source, .. }) => {
+                assert_eq!(&missing, path);
+                assert_eq!(io::ErrorKind::NotFound, source.kind());
+            }
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn organize_dedup() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::write(src.path().join("20200829_205420.mp4"), "video").unwrap();
+        let dst_dir = dst.path().join("2020");
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(dst_dir.join("20200829_205420.mp4"), "video").unwrap();
+
+        Organizer::new(vec![Box::new(VideoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_dedup(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(!src.path().join("20200829_205420.mp4").exists());
+        assert_eq!(
+            "video",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+        assert_eq!(1, fs::read_dir(&dst_dir).unwrap().count());
+    }
+
+    #[test]
+    fn find_duplicates() {
+        let src = TempDir::new().unwrap();
+        let other_src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::create_dir(src.path().join("sub_dir")).unwrap();
+        fs::write(src.path().join("20200829_205420.mp4"), "video").unwrap();
+        fs::write(src.path().join("sub_dir").join("copy.mp4"), "video").unwrap();
+        fs::write(other_src.path().join("unique.mp4"), "other").unwrap();
+        fs::File::create(src.path().join("empty.mp4")).unwrap();
+        fs::File::create(other_src.path().join("empty.mp4")).unwrap();
+
+        for jobs in [1, 2] {
+            let groups = Organizer::new(vec![Box::new(VideoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_jobs(jobs)
+            .find_duplicates(&[src.path().to_path_buf(), other_src.path().to_path_buf()]);
+
+            assert_eq!(
+                vec![vec![
+                    src.path().join("20200829_205420.mp4"),
+                    src.path().join("sub_dir").join("copy.mp4"),
+                ]],
+                groups
+            );
+        }
+        assert_eq!(3, fs::read_dir(src.path()).unwrap().count());
+        assert_eq!(0, fs::read_dir(dst.path()).unwrap().count());
+    }
+
+    #[test]
+    fn organize_dedup_hashing_destination_once() {
+        let src = TempDir::new().unwrap();
+        let other_src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        fs::write(src.path().join("20200829_205420.mp4"), "video").unwrap();
+        fs::write(other_src.path().join("20200829_205420.mp4"), "video").unwrap();
+        let dst_dir = dst.path().join("2020");
+        fs::create_dir(&dst_dir).unwrap();
+        let existing = dst_dir.join("20200829_205420.mp4");
+        fs::write(&existing, "video").unwrap();
+
+        let mut organizer = Organizer::new(vec![Box::new(VideoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_dedup(true);
+        let reads = Arc::new(Mutex::new(Vec::new()));
+        let hashed = reads.clone();
+        organizer.hash = Box::new(move |path| {
+            hashed.lock().unwrap().push(path.to_path_buf());
+            file_hash(path)
+        });
+        let report = organizer
+            .organize(&[src.path().to_path_buf(), other_src.path().to_path_buf()])
+            .unwrap();
+
+        assert_eq!(2, report.skipped);
+        assert!(!src.path().join("20200829_205420.mp4").exists());
+        assert!(!other_src.path().join("20200829_205420.mp4").exists());
+        let reads = reads.lock().unwrap();
+        assert_eq!(3, reads.len());
+        assert_eq!(1, reads.iter().filter(|path| **path == existing).count());
+    }
+
+    #[test]
+    fn organize_trash_duplicates() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let trash = TempDir::new().unwrap();
+        fs::write(src.path().join("20200829_205420.mp4"), "new").unwrap();
+        fs::write(src.path().join("20200829_205421.mp4"), "video").unwrap();
+        let dst_dir = dst.path().join("2020");
+        fs::create_dir(&dst_dir).unwrap();
+        fs::write(dst_dir.join("20200829_205420.mp4"), "old").unwrap();
+        fs::write(dst_dir.join("20200829_205421.mp4"), "video").unwrap();
+
+        let mut organizer = Organizer::new(vec![Box::new(VideoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_dedup(true)
+        .with_trash_duplicates(true);
+        let trash_dir = trash.path().to_path_buf();
+        organizer.trash = Box::new(move |file| {
+            fs::rename(file, trash_dir.join(file.file_name().unwrap())).map_err(|e| {
+                trash::Error::Unknown {
+                    description: e.to_string(),
                 }
-                let dst_dir = match media_type_organizer
-                    .destination_dir(&file)
-                    .wrap_err_with(|| format!("failed to get destination dir from {:?}", file))
-                {
-                    Ok(dir) => dir,
-                    Err(e) => {
-                        eprintln!("{:?}", e);
-                        continue;
-                    }
-                };
+            })
+        });
+        let report = organizer.organize(&[src.path().to_path_buf()]).unwrap();
 
-                match Organizer::move_file(&file, &dst_dir).wrap_err_with(|| {
-                    format!(
-                        "failed to move file {:?} to destination dir {:?}",
-                        file, dst_dir
-                    )
-                }) {
-                    Ok(()) => break,
-                    Err(e) => eprintln!("{:?}", e),
+        assert_eq!(2, report.skipped);
+        assert_eq!(0, fs::read_dir(src.path()).unwrap().count());
+        // The different file is trashed and the identical one removed.
+        assert_eq!(
+            "new",
+            fs::read_to_string(trash.path().join("20200829_205420.mp4")).unwrap()
+        );
+        assert!(!trash.path().join("20200829_205421.mp4").exists());
+        assert_eq!(
+            "old",
+            fs::read_to_string(dst_dir.join("20200829_205420.mp4")).unwrap()
+        );
+    }
+
+    #[test]
+    fn organize_parallel_conflicts_deterministically() {
+        let names = [
+            "20190829_205420.mp4",
+            "20200829_205420.mp4",
+            "20210829_205420.mp4",
+        ];
+        let sources = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        for _ in 0..5 {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            for source in sources {
+                let dir = src.path().join(source);
+                fs::create_dir(&dir).unwrap();
+                for name in names {
+                    fs::write(dir.join(name), source).unwrap();
+                }
+            }
+
+            Organizer::new(vec![Box::new(VideoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_conflict_strategy(ConflictStrategy::Rename)
+            .with_jobs(4)
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+            for name in names {
+                let dir = dst.path().join(&name[..4]);
+                assert_eq!("a", fs::read_to_string(dir.join(name)).unwrap());
+                for (n, source) in sources.iter().enumerate().skip(1) {
+                    let renamed = format!("{} ({}).mp4", &name[..15], n);
+                    assert_eq!(*source, fs::read_to_string(dir.join(renamed)).unwrap());
                 }
             }
         }
-        Ok(())
     }
 
-    fn move_file(file: &Path, dst_dir: &Path) -> Result<()> {
-        if !dst_dir.is_dir() {
-            fs::create_dir_all(dst_dir).wrap_err("failed to create destination dir")?;
+    #[test]
+    fn organize_with_jobs() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        let names = [
+            "camera.jpg",
+            "canon.jpg",
+            "IMG-20200407-WA0004.jpg",
+            "20200829_205420.mp4",
+            "VID_20200829_120000.mov",
+        ];
+        for jobs in [1, 4] {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            let journal = TempDir::new().unwrap();
+            for name in names {
+                fs::copy(fixtures.join(name), src.path().join(name)).unwrap();
+            }
+            let walk: Vec<PathBuf> = FilesIter::new(src.path()).collect();
+
+            let report = Organizer::new(vec![
+                Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
+                Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
+            ])
+            .with_journal(Journal::new(journal.path().join("journal")))
+            .with_jobs(jobs)
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+            assert_eq!(names.len(), report.moved);
+            assert_eq!(0, fs::read_dir(src.path()).unwrap().count());
+            // A single job organizes the files one by one, in the order
+            // they are walked.
+            if jobs == 1 {
+                let moved: Vec<PathBuf> = fs::read_to_string(journal.path().join("journal"))
+                    .unwrap()
+                    .lines()
+                    .map(|line| PathBuf::from(line.split('\t').next().unwrap()))
+                    .collect();
+                let walk: Vec<PathBuf> = walk
+                    .iter()
+                    .map(|file| std::path::absolute(file).unwrap())
+                    .collect();
+                assert_eq!(walk, moved);
+            }
         }
+    }
 
-        let file_name = match file.file_name() {
-            Some(name) => name,
-            None => return Err(eyre!("failed to get file name")),
-        };
-        let dst_path = &dst_dir.join(file_name);
-        if dst_path.is_file() {
-            return Err(eyre!(
-                "a file with the same name already exists in the destination path"
-            ));
+    #[test]
+    fn organize_dry_run() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            src.path().join("20200829_205420.mp4"),
+        )
+        .unwrap();
+
+        Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
+            Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
+        ])
+        .with_dry_run(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(src.path().join("camera.jpg").is_file());
+        assert!(src.path().join("20200829_205420.mp4").is_file());
+        assert_eq!(0, fs::read_dir(dst.path()).unwrap().count());
+    }
+
+    #[test]
+    fn organize_interactive() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for photo in [
+            "camera.jpg",
+            "IMG-20200407-WA0004.jpg",
+            "IMG_20200407_1.png",
+            "IMG_20200407_2.png",
+        ] {
+            fs::copy(fixtures.join(photo), src.path().join(photo)).unwrap();
         }
-        fs::rename(file, dst_path).wrap_err("failed to move file to destination dir")
+
+        // No to the first, yes to the second and all the remaining.
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_interactive(Box::new(io::Cursor::new("n\ny\na\n")))
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(3, report.moved);
+        assert_eq!(1, report.skipped);
+        assert_eq!(1, fs::read_dir(src.path()).unwrap().count());
     }
-}
 
-#[cfg(test)]
-mod tests {
+    #[test]
+    fn organize_interactive_without_answers() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
 
-    use super::*;
-    use photos::PhotoOrganizer;
-    use tempfile::TempDir;
-    use videos::VideoOrganizer;
+        let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_interactive(Box::new(io::empty()))
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(1, report.skipped);
+        assert!(src.path().join("camera.jpg").is_file());
+    }
 
     #[test]
-    fn organize() {
+    fn organize_normalizing_extension() {
         let src = TempDir::new().unwrap();
         let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            src.path().join("IMG-20200407-WA0004.JPG"),
+        )
+        .unwrap();
 
-        let exif_photo = PathBuf::from(file!())
-            .parent()
+        Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_normalize_extension(true)
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let month_dir = dst.path().join("2020").join("04 - April");
+        let names: Vec<OsString> = fs::read_dir(&month_dir)
             .unwrap()
-            .join("fixtures")
-            .join("camera.jpg");
-        fs::copy(exif_photo, src.path().join("camera.jpg")).unwrap();
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(vec![OsString::from("IMG-20200407-WA0004.jpg")], names);
 
-        let wa_photo = PathBuf::from(file!())
+        let organizer = Organizer::new(vec![]).with_normalize_extension(true);
+        assert_eq!(
+            OsString::from("clip.tar.gz"),
+            organizer.normalize(OsString::from("clip.tar.GZ"))
+        );
+        assert_eq!(
+            OsString::from("README"),
+            organizer.normalize(OsString::from("README"))
+        );
+    }
+
+    #[test]
+    fn organize_keeping_live_photos() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for jobs in [1, 2] {
+            let src = TempDir::new().unwrap();
+            let photos_dst = TempDir::new().unwrap();
+            let videos_dst = TempDir::new().unwrap();
+            for file in ["IMG_0001.JPG", "IMG_0001.MOV", "holidays.mp4"] {
+                fs::copy(fixtures.join(file), src.path().join(file)).unwrap();
+            }
+
+            let report = Organizer::new(vec![
+                Box::new(PhotoOrganizer::new(photos_dst.path().to_path_buf())),
+                Box::new(VideoOrganizer::new(videos_dst.path().to_path_buf())),
+            ])
+            .with_keep_live_photos(true)
+            .with_jobs(jobs)
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+            assert_eq!(3, report.moved);
+            let live_photo_dir = photos_dst.path().join("2019").join("06 - June");
+            assert!(live_photo_dir.join("IMG_0001.JPG").is_file());
+            assert!(live_photo_dir.join("IMG_0001.MOV").is_file());
+            // Videos without a photo are organized as usual.
+            assert!(videos_dst
+                .path()
+                .join("2016")
+                .join("holidays.mp4")
+                .is_file());
+        }
+    }
+
+    #[test]
+    fn normalize_unicode_destination_name() {
+        let dst = TempDir::new().unwrap();
+        let composed = PathBuf::from("caf\u{e9}.jpg");
+        let decomposed = PathBuf::from("cafe\u{301}.jpg");
+        assert_ne!(composed, decomposed);
+
+        for (form, expected) in [
+            (UnicodeNormalization::Nfc, "caf\u{e9}.jpg"),
+            (UnicodeNormalization::Nfd, "cafe\u{301}.jpg"),
+        ] {
+            let organizer = Organizer::new(vec![]).with_unicode_normalization(form);
+            let composed_dst = organizer
+                .destination_path(&composed, dst.path(), composed.clone().into_os_string())
+                .unwrap();
+            let decomposed_dst = organizer
+                .destination_path(&decomposed, dst.path(), decomposed.clone().into_os_string())
+                .unwrap();
+            assert_eq!(composed_dst, decomposed_dst);
+            assert_eq!(Destination::Path(dst.path().join(expected)), composed_dst);
+        }
+    }
+
+    #[test]
+    fn organize_newest_first_until_out_of_space() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let photo = PathBuf::from(file!())
             .parent()
             .unwrap()
             .join("fixtures")
             .join("IMG-20200407-WA0004.jpg");
-        let sub_dir = src.path().join("sub_dir");
-        fs::create_dir(&sub_dir).unwrap();
-        fs::copy(wa_photo, sub_dir.join("IMG-20200407-WA0004.jpg")).unwrap();
+        let now = SystemTime::now();
+        let names = [
+            "IMG-20200407-WA0001.jpg",
+            "IMG-20200407-WA0002.jpg",
+            "IMG-20200407-WA0003.jpg",
+        ];
+        for (age, name) in names.iter().enumerate() {
+            let path = src.path().join(name);
+            fs::copy(&photo, &path).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(3600 * age as u64))
+                .unwrap();
+        }
+
+        // There is space for two files.
+        let size = fs::metadata(&photo).unwrap().len();
+        let available = AtomicU64::new(2 * size);
+        let mut organizer = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .with_newest_first(true)
+        .with_min_free_space(0);
+        organizer.free_space = Box::new(move |_| {
+            let space = available.load(Ordering::SeqCst);
+            available.store(space.saturating_sub(size), Ordering::SeqCst);
+            Ok(space)
+        });
+
+        let err = organizer
+            .organize(&[src.path().to_path_buf()])
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("aborting"), "{}", err);
+
+        let month_dir = dst.path().join("2020").join("04 - April");
+        assert!(month_dir.join(names[0]).is_file());
+        assert!(month_dir.join(names[1]).is_file());
+        assert!(!month_dir.join(names[2]).exists());
+        assert!(src.path().join(names[2]).is_file());
+    }
+
+    /// Organizes `YYYYMMDD_*.mp4` videos into a single dir, naming them
+    /// after their year and month.
+    struct RenamingOrganizer {
+        dst_dir: PathBuf,
+    }
+
+    impl MediaTypeOrganizer for RenamingOrganizer {
+        fn should_organize(&self, item: &Path) -> bool {
+            item.extension() == Some(OsStr::new("mp4"))
+        }
+
+        fn name(&self) -> &'static str {
+            "renaming"
+        }
+
+        fn destination_dir(&self, _item: &Path) -> Result<PathBuf> {
+            Ok(self.dst_dir.clone())
+        }
+
+        fn destination_name(&self, item: &Path) -> Result<OsString> {
+            let name = item
+                .file_name()
+                .and_then(OsStr::to_str)
+                .ok_or_else(|| eyre!("invalid file name"))?;
+            let date = Date::new(name[..4].parse()?, name[4..6].parse()?)?;
+            Ok(format!("{}.mp4", date.format("%Y-%m", crate::date::Locale::En)).into())
+        }
+    }
+
+    #[test]
+    fn organize_with_destination_name() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
 
         let video = PathBuf::from(file!())
             .parent()
             .unwrap()
             .join("fixtures")
             .join("20200829_205420.mp4");
-        let sub_sub_dir = sub_dir.join("sub_dir");
-        fs::create_dir(&sub_sub_dir).unwrap();
-        fs::copy(video, sub_sub_dir.join("20200829_205420.mp4")).unwrap();
+        fs::copy(video, src.path().join("20200829_205420.mp4")).unwrap();
+
+        Organizer::new(vec![Box::new(RenamingOrganizer {
+            dst_dir: dst.path().to_path_buf(),
+        })])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(!src.path().join("20200829_205420.mp4").exists());
+        assert!(dst.path().join("2020-08.mp4").is_file());
+    }
+
+    #[test]
+    fn organize_also_flat() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let flat = TempDir::new().unwrap();
+
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+        let sub_dir = src.path().join("sub_dir");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::copy(
+            fixtures.join("IMG-20200407-WA0004.jpg"),
+            sub_dir.join("IMG-20200407-WA0004.jpg"),
+        )
+        .unwrap();
+        fs::copy(
+            fixtures.join("20200829_205420.mp4"),
+            sub_dir.join("20200829_205420.mp4"),
+        )
+        .unwrap();
 
         Organizer::new(vec![
             Box::new(PhotoOrganizer::new(dst.path().to_path_buf())),
             Box::new(VideoOrganizer::new(dst.path().to_path_buf())),
         ])
-        .organize(src.path().to_path_buf())
+        .with_flat_dir(flat.path().to_path_buf())
+        .organize(&[src.path().to_path_buf()])
         .unwrap();
 
         assert!(dst
@@ -142,17 +4087,100 @@ mod tests {
             .join("camera.jpg")
             .is_file());
 
-        assert!(dst
+        for name in [
+            "camera.jpg",
+            "IMG-20200407-WA0004.jpg",
+            "20200829_205420.mp4",
+        ] {
+            assert!(flat.path().join(name).is_file());
+        }
+    }
+
+    #[test]
+    fn organize_also_flat_name_clash() {
+        let fixtures = PathBuf::from(file!()).parent().unwrap().join("fixtures");
+        for (strategy, links) in [
+            (ConflictStrategy::Error, vec!["photo.jpg"]),
+            (ConflictStrategy::Skip, vec!["photo.jpg"]),
+            (ConflictStrategy::Overwrite, vec!["photo.jpg"]),
+            (ConflictStrategy::Rename, vec!["photo (1).jpg", "photo.jpg"]),
+        ] {
+            // Same name, taken on different dates.
+            let first_src = TempDir::new().unwrap();
+            let second_src = TempDir::new().unwrap();
+            fs::copy(
+                fixtures.join("camera.jpg"),
+                first_src.path().join("photo.jpg"),
+            )
+            .unwrap();
+            fs::copy(
+                fixtures.join("canon.jpg"),
+                second_src.path().join("photo.jpg"),
+            )
+            .unwrap();
+            let dst = TempDir::new().unwrap();
+            let flat = TempDir::new().unwrap();
+
+            let report = Organizer::new(vec![Box::new(PhotoOrganizer::new(
+                dst.path().to_path_buf(),
+            ))])
+            .with_flat_dir(flat.path().to_path_buf())
+            .with_conflict_strategy(strategy)
+            .organize(&[
+                first_src.path().to_path_buf(),
+                second_src.path().to_path_buf(),
+            ])
+            .unwrap();
+
+            assert_eq!(2, report.moved, "{:?}", strategy);
+            let mut names: Vec<_> = fs::read_dir(flat.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            assert_eq!(links, names, "{:?}", strategy);
+
+            let first = fs::read(fixtures.join("camera.jpg")).unwrap();
+            let second = fs::read(fixtures.join("canon.jpg")).unwrap();
+            let linked = fs::read(flat.path().join("photo.jpg")).unwrap();
+            match strategy {
+                ConflictStrategy::Overwrite => assert_eq!(second, linked),
+                _ => assert_eq!(first, linked),
+            }
+        }
+    }
+
+    #[test]
+    fn organize_extract_motion_video() {
+        let src = TempDir::new().unwrap();
+        let photos_dst = TempDir::new().unwrap();
+        let videos_dst = TempDir::new().unwrap();
+
+        let motion_photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("20200407_164809.jpg");
+        fs::copy(motion_photo, src.path().join("20200407_164809.jpg")).unwrap();
+
+        Organizer::new(vec![
+            Box::new(
+                PhotoOrganizer::new(photos_dst.path().to_path_buf())
+                    .with_motion_video_dst(videos_dst.path().to_path_buf()),
+            ),
+            Box::new(VideoOrganizer::new(videos_dst.path().to_path_buf())),
+        ])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(photos_dst
             .path()
             .join("2020")
             .join("04 - April")
-            .join("IMG-20200407-WA0004.jpg")
+            .join("20200407_164809.jpg")
             .is_file());
 
-        assert!(dst
-            .path()
-            .join("2020")
-            .join("20200829_205420.mp4")
-            .is_file());
+        let video = fs::read(videos_dst.path().join("2020").join("20200407_164809.mp4")).unwrap();
+        assert_eq!(b"ftyp", &video[4..8]);
     }
 }
@@ -0,0 +1,236 @@
+use super::videos::VideoOrganizer;
+use super::{destination_override, MediaTypeOrganizer};
+use crate::date::{Date, Locale};
+use crate::error::OrganizerError;
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use id3::TagLike;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// It organizes audio files, like voice memos and music, in directories
+/// by year and month, `2020/04 - April/`. The date is taken from the
+/// recording date of the ID3 tags of `mp3` and `wav` files and from the
+/// creation time of `m4a` files. When they don't have one, or for
+/// `flac` files, it's taken from the file name using the regex
+/// `^(?:AUD|REC|Recording)?[-_ ]?(?P<year>\d{4})(?P<month>\d{2})\d{2}[-_ ].+\.\w+$`,
+/// which matches the `AUD-YYYYMMDD-WAXXXX.m4a` WhatsApp voice notes and the
+/// `REC_YYYYMMDD_HHMMSS.mp3` names of voice recorders.
+pub struct AudioOrganizer {
+    dst_dir: PathBuf,
+    date_from_filename_regex: Regex,
+    locale: Locale,
+    mtime_fallback: bool,
+    unknown_date_dir: Option<PathBuf>,
+    destination_overrides: HashMap<String, PathBuf>,
+}
+
+impl AudioOrganizer {
+    const SUPPORTED: [&'static str; 4] = ["mp3", "m4a", "flac", "wav"];
+
+    pub fn new(dst_dir: PathBuf) -> AudioOrganizer {
+        AudioOrganizer {
+            dst_dir,
+            date_from_filename_regex: Regex::new(
+                r"^(?:AUD|REC|Recording)?[-_ ]?(?P<year>\d{4})(?P<month>\d{2})\d{2}[-_ ].+\.\w+$",
+            )
+            .unwrap(),
+            locale: Locale::En,
+            mtime_fallback: false,
+            unknown_date_dir: None,
+            destination_overrides: HashMap::new(),
+        }
+    }
+
+    /// Language of the month names. Defaults to English.
+    pub fn with_locale(mut self, locale: Locale) -> AudioOrganizer {
+        self.locale = locale;
+        self
+    }
+
+    /// Audio files whose date can't be taken from the tags nor the name
+    /// are organized by their modification time.
+    pub fn with_mtime_fallback(mut self, mtime_fallback: bool) -> AudioOrganizer {
+        self.mtime_fallback = mtime_fallback;
+        self
+    }
+
+    /// Audio files without a date are moved to this directory of the
+    /// destination instead of being left in the source.
+    pub fn with_unknown_date_dir(mut self, unknown_date_dir: PathBuf) -> AudioOrganizer {
+        self.unknown_date_dir = Some(unknown_date_dir);
+        self
+    }
+
+    /// Audio files with one of the extensions, lowercase and without the
+    /// dot, are organized in its directory instead of the destination dir.
+    pub fn with_destination_overrides(
+        mut self,
+        destination_overrides: HashMap<String, PathBuf>,
+    ) -> AudioOrganizer {
+        self.destination_overrides = destination_overrides;
+        self
+    }
+
+    fn dst_dir(&self, audio: &Path) -> &Path {
+        destination_override(&self.destination_overrides, audio).unwrap_or(&self.dst_dir)
+    }
+
+    fn get_date(&self, audio: &Path) -> Result<Date> {
+        let tags_date =
+            AudioOrganizer::date_from_tags(audio).wrap_err("failed to get date from tags");
+        if tags_date.is_ok() {
+            return tags_date;
+        }
+
+        let filename_date = self
+            .date_from_filename(audio)
+            .wrap_err("failed to get date from filename")
+            .wrap_err(tags_date.unwrap_err());
+        if filename_date.is_ok() || !self.mtime_fallback {
+            return filename_date;
+        }
+
+        AudioOrganizer::date_from_mtime(audio)
+            .wrap_err("failed to get date from modification time")
+            .wrap_err(filename_date.unwrap_err())
+    }
+
+    fn date_from_tags(audio: &Path) -> Result<Date> {
+        let extension = audio
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase());
+        let tag = match extension.as_deref() {
+            Some("m4a") => return VideoOrganizer::date_from_metadata(audio),
+            // The tags of WAV files are in a chunk of the RIFF container,
+            // which is detected by its header.
+            Some("mp3") | Some("wav") => {
+                id3::Tag::read_from_path(audio).wrap_err("failed to read id3 tags")?
+            }
+            _ => return Err(eyre!("format without supported tags")),
+        };
+        let timestamp = tag
+            .date_recorded()
+            .or_else(|| tag.date_released())
+            .ok_or_else(|| eyre!("id3 tags have no date"))?;
+        let month = timestamp
+            .month
+            .ok_or_else(|| eyre!("id3 date has no month"))?;
+        let year = u16::try_from(timestamp.year).wrap_err("invalid year in id3 date")?;
+        Ok(Date::new(year, month)?)
+    }
+
+    fn date_from_mtime(audio: &Path) -> Result<Date> {
+        let modified = fs::metadata(audio)
+            .and_then(|metadata| metadata.modified())
+            .wrap_err("failed to read modification time")?;
+        Date::from_timestamp(modified)
+    }
+
+    fn date_from_filename(&self, audio: &Path) -> Result<Date> {
+        let file_name = audio
+            .file_name()
+            .ok_or_else(|| eyre!("failed to read file name"))?
+            .to_string_lossy();
+
+        let captures = self
+            .date_from_filename_regex
+            .captures(&file_name)
+            .ok_or(OrganizerError::FilenameNoDate)?;
+        let year: u16 = match captures.name("year") {
+            Some(y) => y.as_str().parse().wrap_err("invalid year in filename")?,
+            None => return Err(eyre!("failed to retrieve year from filename")),
+        };
+        let month: u8 = match captures.name("month") {
+            Some(m) => m.as_str().parse().wrap_err("invalid month in filename")?,
+            None => return Err(eyre!("failed retrieve month from filename")),
+        };
+        Ok(Date::new(year, month)?)
+    }
+
+    fn is_supported(extension: &OsStr) -> bool {
+        AudioOrganizer::SUPPORTED
+            .iter()
+            .any(|supported| extension.eq_ignore_ascii_case(supported))
+    }
+}
+
+impl MediaTypeOrganizer for AudioOrganizer {
+    fn name(&self) -> &'static str {
+        "audio"
+    }
+
+    fn should_organize(&self, item: &Path) -> bool {
+        item.extension().is_some_and(AudioOrganizer::is_supported)
+    }
+
+    fn date(&self, item: &Path) -> Option<Date> {
+        self.get_date(item).ok()
+    }
+
+    fn destination_dir(&self, item: &Path) -> Result<PathBuf> {
+        let date = match (self.get_date(item), &self.unknown_date_dir) {
+            (Ok(date), _) => date,
+            (Err(_), Some(unknown_date_dir)) => {
+                return Ok(self.dst_dir(item).join(unknown_date_dir))
+            }
+            (Err(e), None) => return Err(e.wrap_err("failed to generate destination dir")),
+        };
+        Ok(self
+            .dst_dir(item)
+            .join(date.get_year())
+            .join(date.format("%m - %B", self.locale)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn should_organize() {
+        let audio_organizer = AudioOrganizer::new(PathBuf::from("dst"));
+
+        for audio in ["song.mp3", "memo.M4A", "album.flac", "note.wav"] {
+            assert!(audio_organizer.should_organize(&PathBuf::from(audio)));
+        }
+        assert!(!audio_organizer.should_organize(&PathBuf::from("video.mp4")));
+        assert!(!audio_organizer.should_organize(&PathBuf::from("mp3")));
+    }
+
+    #[test]
+    fn destination_dir() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let audio_organizer = AudioOrganizer::new(PathBuf::from("dst"));
+
+        assert_eq!(
+            PathBuf::from("dst").join("2015").join("09 - September"),
+            audio_organizer
+                .destination_dir(&fixtures.join("voice memo.mp3"))
+                .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("dst").join("2016").join("03 - March"),
+            audio_organizer
+                .destination_dir(&fixtures.join("voice memo.wav"))
+                .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("04 - April"),
+            audio_organizer
+                .destination_dir(&PathBuf::from("AUD-20200407-WA0003.m4a"))
+                .unwrap()
+        );
+        assert!(audio_organizer
+            .destination_dir(&PathBuf::from("song.flac"))
+            .is_err());
+    }
+}
@@ -0,0 +1,91 @@
+use super::{destination_override, MediaTypeOrganizer};
+use color_eyre::eyre::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// It moves any file to a single directory. Registered after the other
+/// organizers it sweeps up what they don't claim, like documents or
+/// archives, and the media files they fail to organize.
+pub struct FallbackOrganizer {
+    dst_dir: PathBuf,
+    destination_overrides: HashMap<String, PathBuf>,
+}
+
+impl FallbackOrganizer {
+    pub fn new(dst_dir: PathBuf) -> FallbackOrganizer {
+        FallbackOrganizer {
+            dst_dir,
+            destination_overrides: HashMap::new(),
+        }
+    }
+
+    /// Files with one of the extensions, lowercase and without the dot,
+    /// are moved to its directory instead of the destination dir.
+    pub fn with_destination_overrides(
+        mut self,
+        destination_overrides: HashMap<String, PathBuf>,
+    ) -> FallbackOrganizer {
+        self.destination_overrides = destination_overrides;
+        self
+    }
+}
+
+impl MediaTypeOrganizer for FallbackOrganizer {
+    fn name(&self) -> &'static str {
+        "misc"
+    }
+
+    fn should_organize(&self, _item: &Path) -> bool {
+        true
+    }
+
+    fn destination_dir(&self, item: &Path) -> Result<PathBuf> {
+        Ok(destination_override(&self.destination_overrides, item)
+            .unwrap_or(&self.dst_dir)
+            .to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::organizer::Organizer;
+    use crate::PhotoOrganizer;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn organize_unhandled_files() {
+        let src = TempDir::new().unwrap();
+        let photos_dst = TempDir::new().unwrap();
+        let misc_dst = TempDir::new().unwrap();
+        fs::write(src.path().join("notes.txt"), "notes").unwrap();
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        fs::copy(photo, src.path().join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let report = Organizer::new(vec![
+            Box::new(PhotoOrganizer::new(photos_dst.path().to_path_buf())),
+            Box::new(FallbackOrganizer::new(misc_dst.path().to_path_buf())),
+        ])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        assert_eq!(0, report.unhandled);
+        assert_eq!(1, report.by_organizer["misc"].moved);
+        assert!(misc_dst.path().join("notes.txt").is_file());
+        assert!(photos_dst
+            .path()
+            .join("2020")
+            .join("04 - April")
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+    }
+}
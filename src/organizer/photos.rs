@@ -1,10 +1,23 @@
-use super::MediaTypeOrganizer;
-use crate::date::Date;
+use super::{
+    date_folder, date_prefixed_name, destination_override, is_invalid_in_name, MediaTypeOrganizer,
+};
+use crate::date::{Date, Locale};
+use crate::dhash;
+use crate::directory::FilesIter;
+use crate::error::OrganizerError;
+use crate::geo;
+use crate::motion;
+use crate::orientation;
+use chrono::{Datelike, NaiveDate, TimeDelta};
 use color_eyre::eyre::{eyre, Result, WrapErr};
 use regex::Regex;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::fs;
-use std::io;
+use std::io::{self, BufRead, Read, Seek};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, UNIX_EPOCH};
 
 /// For supported photos, it generates the destination path usinga 2
 /// level directory structure where the first level is the year and
@@ -18,41 +31,638 @@ use std::path::{Path, PathBuf};
 /// │      └── 04 - April
 /// │         └── IMG-20200407-WA0004.jpg
 ///
-/// The date is taken from the exif of the
-/// photo, if this fails or the image doesn't have exif, it tries to
-/// get the date from the name. Taking the date from the name is just a
-/// regex over the format that WhatsApp and cameras use, which is
-/// `IMG-YYYYMMDD-WAXXXX.jpg` or `IMG_YYYYMMDD_XXXXX.jpg`.
+/// The date is taken from the exif of the photo, trying the tags in
+/// [`Self::with_exif_date_tags`], if this fails or the image doesn't have
+/// exif, it tries to get the date from the name. Taking the date from the
+/// name is just a few regexes, tried in order, over the formats that
+/// WhatsApp, cameras and screenshots use, which are
+/// `IMG-YYYYMMDD-WAXXXX.jpg`, `IMG_YYYYMMDD_XXXXX.jpg`, Samsung's
+/// `YYYYMMDD_HHMMSS.jpg`, also with a suffix like `YYYYMMDD_HHMMSS(1).jpg`,
+/// `Screenshot_YYYYMMDD-HHMMSS.png` and
+/// `Screenshot YYYY-MM-DD at HH.MM.SS.png`.
 ///
-/// Only the following formats are organized `jpeg`, `jpg` and `JPG`.
+/// By default, only the following formats are organized `jpeg`, `jpg`,
+/// `png` and the camera RAW `cr2`, `nef`, `arw` and `dng`, ignoring
+/// case, see [`Self::with_extensions`]. The exif
+/// of PNG files is read from their `eXIf` chunk.
+///
+/// The directory structure can be changed with a strftime-like
+/// template, see [`Self::with_folder_format`], and the month names can be
+/// localized with [`Self::with_locale`].
+///
+/// Photos inside a source folder already named like a date, for
+/// example `2019-07-trip`, can be kept together, see
+/// [`Self::with_respect_date_folders`].
+///
+/// Photos with GPS exif tags can be grouped by the country they were
+/// taken in, see [`Self::with_group_by_location`], and the nearest city,
+/// see [`Self::with_group_by_city`], and by the camera
+/// that took them, see [`Self::with_group_by_camera`]. The shots of a
+/// burst can be kept together, see [`Self::with_group_bursts`]. The
+/// iPhone edited photos, like `IMG_E1234.JPG`, go with their original,
+/// `IMG_1234.JPG`, when it's next to them.
+///
+/// Near-duplicates of photos already in the destination, like
+/// re-compressed or resized copies, can be set aside for review, see
+/// [`Self::with_near_duplicates`].
+///
+/// Photos can be renamed with their date as prefix, see
+/// [`Self::with_rename_by_date`], and JPEGs rotated to the orientation
+/// they are displayed in, see [`Self::with_fix_orientation`].
+///
+/// Optionally, the video embedded in motion photos (Samsung/Google) can
+/// be extracted into a videos destination. See
+/// [`Self::with_motion_video_dst`].
 pub struct PhotoOrganizer {
     dst_dir: PathBuf,
-    date_from_filename_regex: Regex,
+    extensions: Vec<String>,
+    date_from_filename_regexes: Vec<Regex>,
+    exif_date_tags: Vec<ExifDateTag>,
+    folder_format: String,
+    locale: Locale,
+    respect_date_folders: bool,
+    group_by_location: bool,
+    group_by_city: bool,
+    places: Mutex<HashMap<(i32, i32), Place>>,
+    iphone_originals: Mutex<HashMap<(PathBuf, String), PathBuf>>,
+    group_by_camera: bool,
+    group_bursts: bool,
+    unknown_placeholder: Option<String>,
+    mtime_fallback: bool,
+    near_duplicates: Option<NearDuplicates>,
+    motion_video_dst: Option<PathBuf>,
+    rename_by_date: bool,
+    fix_orientation: bool,
+    min_resolution: Option<(u32, u32)>,
+    unknown_date_dir: Option<PathBuf>,
+    destination_overrides: HashMap<String, PathBuf>,
+    takeout_sidecar: TakeoutSidecar,
+    exif_timezone: ExifTimezone,
+    date_sources: Vec<DateSource>,
+}
+
+/// Exif tags a photo date can be taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExifDateTag {
+    /// When the photo was taken.
+    DateTimeOriginal,
+    /// When the photo was digitized, also known as `CreateDate`.
+    DateTimeDigitized,
+    /// When the file was last changed.
+    DateTime,
+}
+
+impl ExifDateTag {
+    /// The tags in the order they are tried by default.
+    pub fn defaults() -> Vec<ExifDateTag> {
+        vec![
+            ExifDateTag::DateTimeOriginal,
+            ExifDateTag::DateTimeDigitized,
+            ExifDateTag::DateTime,
+        ]
+    }
+
+    fn tag(self) -> exif::Tag {
+        match self {
+            ExifDateTag::DateTimeOriginal => exif::Tag::DateTimeOriginal,
+            ExifDateTag::DateTimeDigitized => exif::Tag::DateTimeDigitized,
+            ExifDateTag::DateTime => exif::Tag::DateTime,
+        }
+    }
+
+    /// Tag with the offset from UTC of the date.
+    fn offset_tag(self) -> exif::Tag {
+        match self {
+            ExifDateTag::DateTimeOriginal => exif::Tag::OffsetTimeOriginal,
+            ExifDateTag::DateTimeDigitized => exif::Tag::OffsetTimeDigitized,
+            ExifDateTag::DateTime => exif::Tag::OffsetTime,
+        }
+    }
+}
+
+/// Sources a photo date can be taken from, see
+/// [`PhotoOrganizer::with_date_sources`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateSource {
+    /// The exif date tags.
+    Exif,
+    /// The name of the photo, matched with the filename regexes.
+    Filename,
+    /// The XMP sidecar, `photo.xmp` or `photo.jpg.xmp`.
+    Sidecar,
+    /// The modification time of the file.
+    Mtime,
+    /// The creation time of the file, skipped on the platforms and
+    /// filesystems that don't track it.
+    Birthtime,
+}
+
+impl DateSource {
+    /// The sources in the order they are tried by default.
+    pub fn defaults() -> Vec<DateSource> {
+        vec![DateSource::Exif, DateSource::Filename, DateSource::Sidecar]
+    }
+}
+
+/// How the offset from UTC of the exif dates, see
+/// [`PhotoOrganizer::with_exif_timezone`], is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExifTimezone {
+    /// The dates are converted to UTC.
+    Normalize,
+    /// The dates are taken as the local time of the camera, the default.
+    Ignore,
+}
+
+/// When the date of the Google Takeout sidecar of a photo, see
+/// [`PhotoOrganizer::with_takeout_sidecar`], is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TakeoutSidecar {
+    /// Before the exif, the default, as Google Photos strips it from
+    /// many exported photos.
+    First,
+    /// After the exif, the file name and the XMP sidecar.
+    Last,
+    /// Never.
+    Ignore,
+}
+
+/// Perceptual hashes of the photos in the destination, built on first
+/// use.
+struct NearDuplicates {
+    max_distance: u32,
+    index: Mutex<Option<Vec<u64>>>,
+}
+
+/// Country and nearest city of a location, either unknown when it's
+/// outside the bundled ones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Place {
+    country: Option<&'static str>,
+    city: Option<&'static str>,
 }
 
 impl PhotoOrganizer {
-    const SUPPORTED: [&'static str; 3] = ["jpeg", "jpg", "JPG"];
+    const DEFAULT_EXTENSIONS: [&'static str; 7] =
+        ["jpeg", "jpg", "png", "cr2", "nef", "arw", "dng"];
+    const DEFAULT_FOLDER_FORMAT: &'static str = "%Y/%m - %B";
+    const NEAR_DUPLICATES_DIR: &'static str = "duplicates-review";
+    pub(crate) const BURSTS_DIR: &'static str = "Bursts";
+    /// Bytes of a TIFF based photo, like most RAW formats, read to find
+    /// its exif before falling back to reading the whole file.
+    const TIFF_EXIF_READ_LIMIT: u64 = 1024 * 1024;
 
     pub fn new(dst_dir: PathBuf) -> PhotoOrganizer {
         PhotoOrganizer {
             dst_dir,
-            date_from_filename_regex: Regex::new(
-                r"^(?:IMG[-_])?(\d{4})(\d{2})\d{2}[-_](?:WA)?\d+\.(jpeg|jpg|JPG)$",
-            )
-            .unwrap(),
+            extensions: PhotoOrganizer::DEFAULT_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            date_from_filename_regexes: [
+                r"^(?:IMG[-_])?(?P<year>\d{4})(?P<month>\d{2})\d{2}[-_](?:WA)?\d+\.\w+$",
+                r"^(?P<year>\d{4})(?P<month>\d{2})\d{2}_\d{6}.*\.\w+$",
+                r"^Screenshot_(?P<year>\d{4})(?P<month>\d{2})\d{2}[-_]\d{6}.*\.\w+$",
+                r"^Screenshot (?P<year>\d{4})-(?P<month>\d{2})-\d{2} at .+\.\w+$",
+            ]
+            .iter()
+            .map(|regex| Regex::new(regex).unwrap())
+            .collect(),
+            exif_date_tags: ExifDateTag::defaults(),
+            folder_format: PhotoOrganizer::DEFAULT_FOLDER_FORMAT.to_owned(),
+            locale: Locale::En,
+            respect_date_folders: false,
+            group_by_location: false,
+            group_by_city: false,
+            places: Mutex::new(HashMap::new()),
+            iphone_originals: Mutex::new(HashMap::new()),
+            group_by_camera: false,
+            group_bursts: false,
+            unknown_placeholder: None,
+            mtime_fallback: false,
+            near_duplicates: None,
+            motion_video_dst: None,
+            rename_by_date: false,
+            fix_orientation: false,
+            min_resolution: None,
+            unknown_date_dir: None,
+            destination_overrides: HashMap::new(),
+            takeout_sidecar: TakeoutSidecar::First,
+            exif_timezone: ExifTimezone::Ignore,
+            date_sources: DateSource::defaults(),
+        }
+    }
+
+    /// Photos whose perceptual hash differs in at most `max_distance`
+    /// bits, out of 64, from the one of a photo already in the
+    /// destination go to `duplicates-review` instead of their date
+    /// directory. The destination is indexed the first time a photo is
+    /// checked.
+    pub fn with_near_duplicates(mut self, max_distance: u32) -> PhotoOrganizer {
+        self.near_duplicates = Some(NearDuplicates {
+            max_distance,
+            index: Mutex::new(None),
+        });
+        self
+    }
+
+    fn is_near_duplicate(&self, photo: &Path, near_duplicates: &NearDuplicates) -> bool {
+        let hash = match dhash::dhash(photo) {
+            Ok(hash) => hash,
+            Err(_) => return false,
+        };
+        let mut index = near_duplicates.index.lock().expect("index lock poisoned");
+        index
+            .get_or_insert_with(|| self.index_destination())
+            .iter()
+            .any(|indexed| dhash::distance(hash, *indexed) <= near_duplicates.max_distance)
+    }
+
+    fn index_destination(&self) -> Vec<u64> {
+        FilesIter::new(&self.dst_dir)
+            .filter(|photo| self.should_organize(photo))
+            .filter_map(|photo| dhash::dhash(&photo).ok())
+            .collect()
+    }
+
+    /// Photos in a folder whose name starts with a date, like
+    /// `2019-07-trip`, are organized by the date of the folder instead of
+    /// their own, and the folder is kept. For example
+    /// `2019-07-trip/camera.jpg` goes to `2019/07 - July/2019-07-trip/`.
+    /// Only the folder containing the photo is taken into account.
+    pub fn with_respect_date_folders(mut self, respect_date_folders: bool) -> PhotoOrganizer {
+        self.respect_date_folders = respect_date_folders;
+        self
+    }
+
+    /// Photos with GPS exif tags are grouped inside their date directory
+    /// by the country they were taken in, for example
+    /// `2020/04 - April/Spain/`. The country is looked up offline and
+    /// approximately, photos without GPS tags or taken outside the known
+    /// countries stay in the date directory, or go to
+    /// [`Self::with_unknown_placeholder`].
+    pub fn with_group_by_location(mut self, group_by_location: bool) -> PhotoOrganizer {
+        self.group_by_location = group_by_location;
+        self
+    }
+
+    /// Photos with GPS exif tags are grouped inside their date directory,
+    /// and country when grouping by location, by the nearest city, for
+    /// example `2020/04 - April/Madrid/`. The city is looked up offline
+    /// among the bundled ones, photos more than 100 km away from all of
+    /// them stay in the date directory, or go to
+    /// [`Self::with_unknown_placeholder`].
+    pub fn with_group_by_city(mut self, group_by_city: bool) -> PhotoOrganizer {
+        self.group_by_city = group_by_city;
+        self
+    }
+
+    /// Replaces the extensions of the files organized as photos, which
+    /// are matched ignoring case and default to `jpeg`, `jpg`, `png`,
+    /// `cr2`, `nef`, `arw` and `dng`.
+    pub fn with_extensions(mut self, extensions: Vec<String>) -> PhotoOrganizer {
+        self.extensions = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Replaces the default regexes used to take the date from the file
+    /// name. The year and month are taken from the `year` and `month`
+    /// named groups, for example `^DSC(?P<year>\d{4})(?P<month>\d{2})`.
+    pub fn with_filename_regex(mut self, regex: Regex) -> PhotoOrganizer {
+        self.date_from_filename_regexes = vec![regex];
+        self
+    }
+
+    /// When the `photoTakenTime` of the JSON sidecars of Google Takeout
+    /// exports, `photo.jpg.json`, is used as the date of the photo.
+    /// Defaults to [`TakeoutSidecar::First`].
+    pub fn with_takeout_sidecar(mut self, takeout_sidecar: TakeoutSidecar) -> PhotoOrganizer {
+        self.takeout_sidecar = takeout_sidecar;
+        self
+    }
+
+    /// If the exif dates are converted to UTC using their offset tag,
+    /// `OffsetTimeOriginal` for `DateTimeOriginal`, when the photo has
+    /// it. A photo taken just after midnight of the first of the month
+    /// in a timezone ahead of UTC goes to the previous month. Defaults
+    /// to [`ExifTimezone::Ignore`].
+    pub fn with_exif_timezone(mut self, exif_timezone: ExifTimezone) -> PhotoOrganizer {
+        self.exif_timezone = exif_timezone;
+        self
+    }
+
+    /// Exif tags the date is taken from, the first that is present and
+    /// valid is used. Defaults to [`ExifDateTag::defaults`].
+    pub fn with_exif_date_tags(mut self, tags: Vec<ExifDateTag>) -> PhotoOrganizer {
+        self.exif_date_tags = tags;
+        self
+    }
+
+    /// Sources the date is taken from, in the order they are tried. For
+    /// example the name first for WhatsApp photos, whose exif date is
+    /// when they were copied. Defaults to [`DateSource::defaults`]. The
+    /// Google Takeout sidecar is tried as set with
+    /// [`Self::with_takeout_sidecar`], and the modification time last
+    /// with [`Self::with_mtime_fallback`] when it's not a source.
+    pub fn with_date_sources(mut self, date_sources: Vec<DateSource>) -> PhotoOrganizer {
+        self.date_sources = date_sources;
+        self
+    }
+
+    /// Photos are grouped inside their date directory, and location if
+    /// grouped by it, by the camera model of their exif, prefixed by the
+    /// make when the model doesn't include it. For example
+    /// `2020/04 - April/Canon EOS R/`. Photos without a model stay in
+    /// the date directory, or go to [`Self::with_unknown_placeholder`].
+    pub fn with_group_by_camera(mut self, group_by_camera: bool) -> PhotoOrganizer {
+        self.group_by_camera = group_by_camera;
+        self
+    }
+
+    /// Folder the photos grouped by location, city or camera that don't
+    /// have it go to, instead of staying in the one above. A photo
+    /// without GPS tags only gets one, even when grouped by both
+    /// location and city.
+    pub fn with_unknown_placeholder(mut self, unknown_placeholder: String) -> PhotoOrganizer {
+        self.unknown_placeholder = Some(unknown_placeholder);
+        self
+    }
+
+    /// The shots of a burst, named like `IMG_0001_BURST001.jpg` or, on
+    /// Pixel phones, `00001IMG_00001_BURST20200407164808.jpg`, are
+    /// grouped inside their date directory, after any other grouping, in
+    /// `Bursts/<id>/`, `Bursts/IMG_0001/` and `Bursts/20200407164808/` for
+    /// those. Other photos are unaffected.
+    pub fn with_group_bursts(mut self, group_bursts: bool) -> PhotoOrganizer {
+        self.group_bursts = group_bursts;
+        self
+    }
+
+    /// Photos whose date can't be taken from the exif nor the name are
+    /// organized by their modification time.
+    pub fn with_mtime_fallback(mut self, mtime_fallback: bool) -> PhotoOrganizer {
+        self.mtime_fallback = mtime_fallback;
+        self
+    }
+
+    /// Photos without a date are moved to this directory of the
+    /// destination, for example `unknown-date`, instead of being left in
+    /// the source.
+    pub fn with_unknown_date_dir(mut self, unknown_date_dir: PathBuf) -> PhotoOrganizer {
+        self.unknown_date_dir = Some(unknown_date_dir);
+        self
+    }
+
+    /// Photos with one of the extensions, lowercase and without the dot,
+    /// are organized in its directory instead of the destination dir.
+    pub fn with_destination_overrides(
+        mut self,
+        destination_overrides: HashMap<String, PathBuf>,
+    ) -> PhotoOrganizer {
+        self.destination_overrides = destination_overrides;
+        self
+    }
+
+    fn dst_dir(&self, photo: &Path) -> &Path {
+        destination_override(&self.destination_overrides, photo).unwrap_or(&self.dst_dir)
+    }
+
+    fn date_dir(&self, photo: &Path, date: &Date) -> PathBuf {
+        date.format(&self.folder_format, self.locale)
+            .split('/')
+            .filter(|dir| !dir.is_empty())
+            .fold(self.dst_dir(photo).to_path_buf(), |path, dir| {
+                path.join(dir)
+            })
+    }
+
+    /// Template for the destination directory of each photo, relative
+    /// to the destination dir. `/` separates directory levels, see
+    /// [`Date::format`] for the supported tokens. Defaults to
+    /// `%Y/%m - %B`.
+    pub fn with_folder_format(mut self, folder_format: String) -> PhotoOrganizer {
+        self.folder_format = folder_format;
+        self
+    }
+
+    /// Language of the month names in the folder format. Defaults to
+    /// English.
+    pub fn with_locale(mut self, locale: Locale) -> PhotoOrganizer {
+        self.locale = locale;
+        self
+    }
+
+    /// Extracts the video embedded in motion photos into the given
+    /// directory, in a folder for the year the photo was taken. The
+    /// photo itself is organized as usual.
+    pub fn with_motion_video_dst(mut self, motion_video_dst: PathBuf) -> PhotoOrganizer {
+        self.motion_video_dst = Some(motion_video_dst);
+        self
+    }
+
+    /// Prefixes the name of the photos with their `YYYYMM` date, like
+    /// `201901_camera.jpg`, so they sort chronologically.
+    pub fn with_rename_by_date(mut self, rename_by_date: bool) -> PhotoOrganizer {
+        self.rename_by_date = rename_by_date;
+        self
+    }
+
+    /// Once moved, the pixels of JPEGs with an exif orientation other
+    /// than `1` are rotated to how they are displayed and the tag reset,
+    /// for viewers that ignore it.
+    pub fn with_fix_orientation(mut self, fix_orientation: bool) -> PhotoOrganizer {
+        self.fix_orientation = fix_orientation;
+        self
+    }
+
+    /// Photos smaller than `width` by `height`, like thumbnails or web
+    /// images, are left in the source and counted as skipped. Portrait
+    /// photos are compared with the threshold turned, and photos whose
+    /// dimensions can't be read are organized.
+    pub fn with_min_resolution(mut self, width: u32, height: u32) -> PhotoOrganizer {
+        self.min_resolution = Some((width, height));
+        self
+    }
+
+    /// Width and height of the photo, from the exif when it has them,
+    /// which avoids decoding it, or else from the image header.
+    fn dimensions(photo: &Path) -> Option<(u32, u32)> {
+        if let Ok(exif) = PhotoOrganizer::read_exif(photo) {
+            let dimension = |tag| {
+                exif.get_field(tag, exif::In::PRIMARY)
+                    .and_then(|field| field.value.get_uint(0))
+            };
+            if let (Some(width), Some(height)) = (
+                dimension(exif::Tag::PixelXDimension),
+                dimension(exif::Tag::PixelYDimension),
+            ) {
+                return Some((width, height));
+            }
+        }
+        image::image_dimensions(photo).ok()
+    }
+
+    fn is_jpeg(photo: &Path) -> bool {
+        photo
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+    }
+
+    fn extract_motion_video(&self, photo: &Path, videos_dst: &Path) -> Result<()> {
+        let data = fs::read(photo).wrap_err("failed to read photo")?;
+        let video = match motion::find_motion_video(&data) {
+            Some(video) => video,
+            None => return Ok(()),
+        };
+
+        let photo_date = self.get_date(photo)?;
+        let dst_dir = videos_dst.join(photo_date.get_year());
+        if !dst_dir.is_dir() {
+            fs::create_dir_all(&dst_dir).wrap_err("failed to create motion video dir")?;
+        }
+        let file_stem = photo
+            .file_stem()
+            .ok_or_else(|| eyre!("failed to retrieve photo filename"))?;
+        let dst_path = dst_dir.join(file_stem).with_extension("mp4");
+        if dst_path.is_file() {
+            return Err(eyre!(
+                "a file with the same name already exists in the motion video destination path"
+            ));
         }
+        fs::write(dst_path, video).wrap_err("failed to write motion video")
     }
 
     fn get_date(&self, photo: &Path) -> Result<Date> {
-        let exif_date =
-            PhotoOrganizer::date_from_exif(photo).wrap_err("failed to get date from exif");
-        if exif_date.is_ok() {
-            return exif_date;
+        // The error of each source wraps the ones of the sources before.
+        let mut error: Option<color_eyre::Report> = None;
+        let mut date = |date: Result<Date>| match date {
+            Ok(date) => Some(date),
+            Err(e) => {
+                error = Some(match error.take() {
+                    Some(previous) => e.wrap_err(previous),
+                    None => e,
+                });
+                None
+            }
+        };
+        let takeout_date = || {
+            PhotoOrganizer::date_from_takeout(photo)
+                .wrap_err("failed to get date from takeout sidecar")
+        };
+
+        if self.takeout_sidecar == TakeoutSidecar::First {
+            if let Some(date) = date(takeout_date()) {
+                return Ok(date);
+            }
+        }
+        for source in &self.date_sources {
+            if let Some(date) = date(self.date_from_source(photo, *source)) {
+                return Ok(date);
+            }
         }
+        if self.takeout_sidecar == TakeoutSidecar::Last {
+            if let Some(date) = date(takeout_date()) {
+                return Ok(date);
+            }
+        }
+        if self.mtime_fallback && !self.date_sources.contains(&DateSource::Mtime) {
+            if let Some(date) = date(self.date_from_source(photo, DateSource::Mtime)) {
+                return Ok(date);
+            }
+        }
+        Err(error.unwrap_or_else(|| eyre!("no date sources to try")))
+    }
+
+    fn date_from_source(&self, photo: &Path, source: DateSource) -> Result<Date> {
+        match source {
+            DateSource::Exif => self
+                .date_from_exif(photo)
+                .wrap_err("failed to get date from exif"),
+            DateSource::Filename => self
+                .date_from_filename(photo)
+                .wrap_err("failed to get date from filename"),
+            DateSource::Sidecar => PhotoOrganizer::date_from_sidecar(photo)
+                .wrap_err("failed to get date from xmp sidecar"),
+            DateSource::Mtime => PhotoOrganizer::date_from_mtime(photo)
+                .wrap_err("failed to get date from modification time"),
+            DateSource::Birthtime => PhotoOrganizer::date_from_birthtime(photo)
+                .wrap_err("failed to get date from creation time"),
+        }
+    }
 
-        self.date_from_filename(photo)
-            .wrap_err("failed to get date from filename")
-            .wrap_err(exif_date.unwrap_err())
+    /// Reads the date from the `photoshop:DateCreated` or
+    /// `exif:DateTimeOriginal` field of the XMP sidecar of the photo,
+    /// `photo.xmp` or `photo.jpg.xmp`.
+    fn date_from_sidecar(photo: &Path) -> Result<Date> {
+        static XMP_DATE: OnceLock<Regex> = OnceLock::new();
+        let xmp_date = XMP_DATE.get_or_init(|| {
+            Regex::new(
+                r#"(?:photoshop:DateCreated|exif:DateTimeOriginal)\s*(?:=\s*["']|>)\s*(\d{4})-(\d{2})"#,
+            )
+            .unwrap()
+        });
+
+        let mut with_xmp_extension = photo.as_os_str().to_owned();
+        with_xmp_extension.push(".xmp");
+        let sidecar = [
+            photo.with_extension("xmp"),
+            PathBuf::from(with_xmp_extension),
+        ]
+        .into_iter()
+        .find(|sidecar| sidecar.is_file())
+        .ok_or_else(|| eyre!("photo has no xmp sidecar"))?;
+        let xmp = fs::read_to_string(&sidecar)
+            .wrap_err_with(|| format!("failed to read sidecar {:?}", sidecar))?;
+        let captures = xmp_date
+            .captures(&xmp)
+            .ok_or_else(|| eyre!("sidecar {:?} has no date", sidecar))?;
+        Ok(Date::new(captures[1].parse()?, captures[2].parse()?)?)
+    }
+
+    /// Reads the date from the `photoTakenTime.timestamp` of the Google
+    /// Takeout sidecar of the photo, `photo.jpg.json`,
+    /// `photo.jpg.supplemental-metadata.json` or `photo.json`.
+    fn date_from_takeout(photo: &Path) -> Result<Date> {
+        let with_extension = |extension: &str| {
+            let mut sidecar = photo.as_os_str().to_owned();
+            sidecar.push(extension);
+            PathBuf::from(sidecar)
+        };
+        let sidecar = [
+            with_extension(".json"),
+            with_extension(".supplemental-metadata.json"),
+            photo.with_extension("json"),
+        ]
+        .into_iter()
+        .find(|sidecar| sidecar.is_file())
+        .ok_or_else(|| eyre!("photo has no takeout sidecar"))?;
+        let content = fs::read_to_string(&sidecar)
+            .wrap_err_with(|| format!("failed to read sidecar {:?}", sidecar))?;
+        let metadata: serde_json::Value = serde_json::from_str(&content)
+            .wrap_err_with(|| format!("sidecar {:?} is not valid json", sidecar))?;
+        let timestamp: u64 = metadata["photoTakenTime"]["timestamp"]
+            .as_str()
+            .ok_or_else(|| eyre!("sidecar {:?} has no photoTakenTime", sidecar))?
+            .parse()
+            .wrap_err_with(|| format!("sidecar {:?} has an invalid timestamp", sidecar))?;
+        Date::from_timestamp(UNIX_EPOCH + Duration::from_secs(timestamp))
+    }
+
+    fn date_from_mtime(photo: &Path) -> Result<Date> {
+        let modified = fs::metadata(photo)
+            .and_then(|metadata| metadata.modified())
+            .wrap_err("failed to read modification time")?;
+        Date::from_timestamp(modified)
+    }
+
+    fn date_from_birthtime(photo: &Path) -> Result<Date> {
+        let created = fs::metadata(photo)
+            .and_then(|metadata| metadata.created())
+            .wrap_err("failed to read creation time")?;
+        Date::from_timestamp(created)
     }
 
     fn date_from_filename(&self, photo: &Path) -> Result<Date> {
@@ -60,85 +670,447 @@ impl PhotoOrganizer {
             .file_name()
             .ok_or_else(|| eyre!("failed to retrieve photo filename"))?;
 
+        // Names that aren't valid unicode are matched lossily, the date
+        // is usually in the part that is.
+        let file_name = file_name.to_string_lossy();
         let captures = self
-            .date_from_filename_regex
-            .captures(
-                file_name
-                    .to_str()
-                    .ok_or_else(|| eyre!("failed to get file name as string"))?,
-            )
-            .ok_or_else(|| eyre!("file name doesn't have date format"))?;
-        let year: u16 = match captures.get(1) {
-            Some(y) => y.as_str().parse().unwrap(),
+            .date_from_filename_regexes
+            .iter()
+            .find_map(|regex| regex.captures(&file_name))
+            .ok_or(OrganizerError::FilenameNoDate)?;
+        let year: u16 = match captures.name("year") {
+            Some(y) => y.as_str().parse().wrap_err("invalid year in filename")?,
             None => return Err(eyre!("failed to retrieve year from filename")),
         };
-        let month: u8 = match captures.get(2) {
-            Some(m) => m.as_str().parse().unwrap(),
+        let month: u8 = match captures.name("month") {
+            Some(m) => m.as_str().parse().wrap_err("invalid month in filename")?,
             None => return Err(eyre!("failed retrieve month from filename")),
         };
-        Date::new(year, month)
+        Ok(Date::new(year, month)?)
+    }
+
+    fn read_exif(photo: &Path) -> Result<exif::Exif> {
+        PhotoOrganizer::read_exif_limited(photo, PhotoOrganizer::TIFF_EXIF_READ_LIMIT)
     }
 
-    fn date_from_exif(photo: &Path) -> Result<Date> {
+    /// The exif reader reads TIFF files whole, but their IFDs are usually
+    /// at the start, so only the first `limit` bytes are tried first. A
+    /// prefix that cuts the exif fails to parse, and then the whole file
+    /// is read, so the result is the same.
+    fn read_exif_limited(photo: &Path, limit: u64) -> Result<exif::Exif> {
         let file = fs::File::open(photo).wrap_err("failed to open file")?;
         let mut bufreader = io::BufReader::new(&file);
         let exifreader = exif::Reader::new();
-        let exif = exifreader
-            .read_from_container(&mut bufreader)
-            .wrap_err("failed to read the file")?;
+        let header = bufreader.fill_buf().wrap_err("failed to read the file")?;
+        if header.starts_with(b"II*\0") || header.starts_with(b"MM\0*") {
+            let mut prefix = Vec::new();
+            bufreader
+                .by_ref()
+                .take(limit)
+                .read_to_end(&mut prefix)
+                .wrap_err("failed to read the file")?;
+            if let Ok(exif) = exifreader.read_raw(prefix) {
+                return Ok(exif);
+            }
+            bufreader.rewind().wrap_err("failed to read the file")?;
+        }
+        match exifreader.read_from_container(&mut bufreader) {
+            Ok(exif) => Ok(exif),
+            Err(exif::Error::NotFound(_)) => Err(OrganizerError::ExifMissing.into()),
+            Err(e) => Err(e).wrap_err("failed to read the file"),
+        }
+    }
+
+    fn date_from_exif(&self, photo: &Path) -> Result<Date> {
+        let exif = PhotoOrganizer::read_exif(photo)?;
+        let mut last_error = eyre!("no exif date tags to try");
+        for tag in &self.exif_date_tags {
+            match self.exif_date(&exif, *tag) {
+                Ok(date) => return Ok(date),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn exif_date(&self, exif: &exif::Exif, tag: ExifDateTag) -> Result<Date> {
         let datetime_tag = exif
-            .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
-            .ok_or_else(|| eyre!("exif DateTimeOriginal tag is missing"))?;
-        let exif_datetime = match datetime_tag.value {
+            .get_field(tag.tag(), exif::In::PRIMARY)
+            .ok_or(OrganizerError::ExifTagMissing(tag))?;
+        let mut exif_datetime = match datetime_tag.value {
             exif::Value::Ascii(ref vec) if !vec.is_empty() => {
                 exif::DateTime::from_ascii(&vec[0]).wrap_err("exif date value is broken")?
             }
             _ => return Err(eyre!("exif date value is broken")),
         };
-        Date::new(exif_datetime.year, exif_datetime.month)
+        if self.exif_timezone == ExifTimezone::Normalize {
+            if let Some(exif::Value::Ascii(offset)) = exif
+                .get_field(tag.offset_tag(), exif::In::PRIMARY)
+                .map(|field| &field.value)
+            {
+                // A broken offset is ignored, the date is still usable.
+                if let Some(offset) = offset.first() {
+                    let _ = exif_datetime.parse_offset(offset);
+                }
+            }
+            if let Some(offset) = exif_datetime.offset {
+                let utc = NaiveDate::from_ymd_opt(
+                    exif_datetime.year.into(),
+                    exif_datetime.month.into(),
+                    exif_datetime.day.into(),
+                )
+                .and_then(|date| {
+                    date.and_hms_opt(
+                        exif_datetime.hour.into(),
+                        exif_datetime.minute.into(),
+                        exif_datetime.second.into(),
+                    )
+                })
+                .ok_or_else(|| eyre!("exif date value is broken"))?
+                    - TimeDelta::minutes(offset.into());
+                return Ok(Date::new(utc.year() as u16, utc.month() as u8)?);
+            }
+        }
+        Ok(Date::new(exif_datetime.year, exif_datetime.month)?)
+    }
+
+    /// Place of the GPS exif tags of the photo.
+    fn place_from_exif(&self, photo: &Path) -> Option<Place> {
+        let (latitude, longitude) = PhotoOrganizer::gps_from_exif(photo)?;
+        Some(self.place(latitude, longitude))
+    }
+
+    /// Country and nearest city of a location. Lookups are cached for as
+    /// long as the organizer lives, a run of the command line, by the
+    /// location rounded to thousandths of a degree, about a hundred
+    /// meters, as a batch usually has many photos from the same places.
+    fn place(&self, latitude: f64, longitude: f64) -> Place {
+        let key = (
+            (latitude * 1000.0).round() as i32,
+            (longitude * 1000.0).round() as i32,
+        );
+        let mut places = self.places.lock().expect("places lock poisoned");
+        *places.entry(key).or_insert_with(|| Place {
+            country: geo::country(latitude, longitude),
+            city: geo::nearest_city(latitude, longitude),
+        })
+    }
+
+    /// Latitude and longitude, in decimal degrees, of the GPS exif tags of
+    /// the photo.
+    fn gps_from_exif(photo: &Path) -> Option<(f64, f64)> {
+        let exif = PhotoOrganizer::read_exif(photo).ok()?;
+        let latitude = PhotoOrganizer::gps_coordinate(
+            &exif,
+            exif::Tag::GPSLatitude,
+            exif::Tag::GPSLatitudeRef,
+            b'S',
+        )?;
+        let longitude = PhotoOrganizer::gps_coordinate(
+            &exif,
+            exif::Tag::GPSLongitude,
+            exif::Tag::GPSLongitudeRef,
+            b'W',
+        )?;
+        Some((latitude, longitude))
+    }
+
+    /// The parent directory and number of an iPhone photo, `IMG_1234.JPG`,
+    /// or of its edited version, `IMG_E1234.JPG`, when `edited`.
+    fn iphone_key(photo: &Path, edited: bool) -> Option<(PathBuf, String)> {
+        static IPHONE_NAMES: OnceLock<[Regex; 2]> = OnceLock::new();
+        let iphone_names = IPHONE_NAMES.get_or_init(|| {
+            [
+                Regex::new(r"(?i)^IMG_(?P<number>\d+)\.\w+$").unwrap(),
+                Regex::new(r"(?i)^IMG_E(?P<number>\d+)\.\w+$").unwrap(),
+            ]
+        });
+        let name = photo.file_name()?.to_str()?;
+        let captures = iphone_names[usize::from(edited)].captures(name)?;
+        let parent = photo.parent().unwrap_or(Path::new("")).to_path_buf();
+        Some((parent, captures["number"].to_owned()))
+    }
+
+    /// Destination directory of the original of an iPhone edited photo,
+    /// so both are kept together. The original is looked up next to the
+    /// edited one, or among the already organized originals when it was
+    /// moved before.
+    fn iphone_original_dir(&self, photo: &Path) -> Result<Option<PathBuf>> {
+        let key = match PhotoOrganizer::iphone_key(photo, true) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        if let Some(dir) = self
+            .iphone_originals
+            .lock()
+            .expect("iphone originals lock poisoned")
+            .get(&key)
+        {
+            return Ok(Some(dir.clone()));
+        }
+        let entries = match fs::read_dir(&key.0) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(None),
+        };
+        let original = entries.filter_map(|entry| entry.ok()).find(|entry| {
+            let path = entry.path();
+            self.should_organize(&path)
+                && PhotoOrganizer::iphone_key(&path, false).as_ref() == Some(&key)
+        });
+        match original {
+            Some(original) => self.destination_dir(&original.path()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Id shared by the shots of the burst the photo is part of, taken
+    /// from its name.
+    fn burst_id(photo: &Path) -> Option<String> {
+        static BURST_NAMES: OnceLock<[Regex; 2]> = OnceLock::new();
+        let burst_names = BURST_NAMES.get_or_init(|| {
+            [
+                Regex::new(r"(?i)^\d+IMG_\d+_BURST(?P<id>\d{14})(?:_COVER)?\.\w+$").unwrap(),
+                Regex::new(r"(?i)^(?P<id>.+)_BURST\d+(?:_COVER)?\.\w+$").unwrap(),
+            ]
+        });
+        let name = photo.file_name()?.to_str()?;
+        burst_names
+            .iter()
+            .find_map(|regex| regex.captures(name))
+            .map(|captures| captures["id"].to_owned())
+    }
+
+    /// Make and model of the camera in the exif of the photo, usable as
+    /// a folder name.
+    fn camera_from_exif(photo: &Path) -> Option<String> {
+        let exif = PhotoOrganizer::read_exif(photo).ok()?;
+        let model = PhotoOrganizer::exif_text(&exif, exif::Tag::Model)?;
+        let camera = match PhotoOrganizer::exif_text(&exif, exif::Tag::Make) {
+            Some(make) if !model.to_lowercase().starts_with(&make.to_lowercase()) => {
+                format!("{} {}", make, model)
+            }
+            _ => model,
+        };
+        let camera = sanitize_folder_name(&camera);
+        if camera.is_empty() {
+            return None;
+        }
+        Some(camera)
+    }
+
+    fn exif_text(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+        let text = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(values) => String::from_utf8_lossy(values.first()?).into_owned(),
+            _ => return None,
+        };
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if text.is_empty() {
+            return None;
+        }
+        Some(text.to_owned())
     }
 
-    fn is_supported(extension: &str) -> bool {
-        for i in PhotoOrganizer::SUPPORTED.iter() {
-            if extension.eq(*i) {
-                return true;
+    /// Reads a coordinate stored as degrees, minutes and seconds, in
+    /// decimal degrees. The reference, N/S or E/W, is negative when it's
+    /// `negative_ref`.
+    fn gps_coordinate(
+        exif: &exif::Exif,
+        tag: exif::Tag,
+        ref_tag: exif::Tag,
+        negative_ref: u8,
+    ) -> Option<f64> {
+        let degrees = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Rational(dms) if dms.len() == 3 => {
+                dms[0].to_f64() + dms[1].to_f64() / 60.0 + dms[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        match &exif.get_field(ref_tag, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(refs) if refs.first()?.first() == Some(&negative_ref) => {
+                Some(-degrees)
             }
+            exif::Value::Ascii(_) => Some(degrees),
+            _ => None,
         }
-        false
+    }
+
+    fn is_supported(&self, extension: &OsStr) -> bool {
+        self.extensions
+            .iter()
+            .any(|supported| extension.eq_ignore_ascii_case(supported))
     }
 }
 
 impl MediaTypeOrganizer for PhotoOrganizer {
+    fn name(&self) -> &'static str {
+        "photos"
+    }
+
     fn should_organize(&self, item: &Path) -> bool {
-        let extension = item.extension().and_then(|e| e.to_str());
-        match extension {
-            Some(e) => PhotoOrganizer::is_supported(e),
-            None => false,
+        item.extension().is_some_and(|e| self.is_supported(e))
+    }
+
+    fn skip_reason(&self, item: &Path) -> Option<String> {
+        let (min_width, min_height) = self.min_resolution?;
+        let (width, height) = PhotoOrganizer::dimensions(item)?;
+        let fits = |(w, h): (u32, u32)| w >= min_width && h >= min_height;
+        if fits((width, height)) || fits((height, width)) {
+            return None;
+        }
+        Some(format!(
+            "its resolution {}x{} is below {}x{}",
+            width, height, min_width, min_height
+        ))
+    }
+
+    fn date(&self, item: &Path) -> Option<Date> {
+        if self.respect_date_folders {
+            if let Some((folder_date, _)) = date_folder(item) {
+                return Some(folder_date);
+            }
+        }
+        self.get_date(item).ok()
+    }
+
+    fn destination_name(&self, item: &Path) -> Result<OsString> {
+        if self.rename_by_date {
+            let date = self.date(item);
+            if date.is_some() || self.unknown_date_dir.is_none() {
+                return date_prefixed_name(item, date);
+            }
         }
+        item.file_name()
+            .map(|name| name.to_os_string())
+            .ok_or_else(|| eyre!("failed to get file name"))
     }
 
     fn destination_dir(&self, item: &Path) -> Result<PathBuf> {
-        let photo_date = self.get_date(item)?;
-        Ok(self
-            .dst_dir
-            .join(photo_date.get_year())
-            .join(photo_date.get_month()))
+        if let Some(dir) = self.iphone_original_dir(item)? {
+            return Ok(dir);
+        }
+        if let Some(near_duplicates) = &self.near_duplicates {
+            if self.is_near_duplicate(item, near_duplicates) {
+                return Ok(self.dst_dir(item).join(PhotoOrganizer::NEAR_DUPLICATES_DIR));
+            }
+        }
+        if self.respect_date_folders {
+            if let Some((folder_date, folder)) = date_folder(item) {
+                return Ok(self.date_dir(item, &folder_date).join(folder));
+            }
+        }
+        let photo_date = match (self.get_date(item), &self.unknown_date_dir) {
+            (Ok(date), _) => date,
+            (Err(_), Some(unknown_date_dir)) => {
+                return Ok(self.dst_dir(item).join(unknown_date_dir))
+            }
+            (Err(e), None) => return Err(e),
+        };
+        let mut dir = self.date_dir(item, &photo_date);
+        if self.group_by_location || self.group_by_city {
+            if let Some(place) = self.place_from_exif(item) {
+                let unknown = self.unknown_placeholder.as_deref();
+                if let Some(country) = place.country.or(unknown).filter(|_| self.group_by_location)
+                {
+                    dir.push(country);
+                }
+                if let Some(city) = place.city.or(unknown).filter(|_| self.group_by_city) {
+                    dir.push(city);
+                }
+            } else if let Some(unknown) = &self.unknown_placeholder {
+                dir.push(unknown);
+            }
+        }
+        if self.group_by_camera {
+            if let Some(camera) = PhotoOrganizer::camera_from_exif(item) {
+                dir.push(camera);
+            } else if let Some(unknown) = &self.unknown_placeholder {
+                dir.push(unknown);
+            }
+        }
+        if self.group_bursts {
+            if let Some(burst_id) = PhotoOrganizer::burst_id(item) {
+                dir.push(PhotoOrganizer::BURSTS_DIR);
+                dir.push(burst_id);
+            }
+        }
+        if let Some(key) = PhotoOrganizer::iphone_key(item, false) {
+            self.iphone_originals
+                .lock()
+                .expect("iphone originals lock poisoned")
+                .insert(key, dir.clone());
+        }
+        Ok(dir)
+    }
+
+    fn after_move(&self, item: &Path) -> Result<()> {
+        // The motion video goes before rotating, which re-encodes the
+        // photo without it.
+        let motion_video = match &self.motion_video_dst {
+            Some(videos_dst) => self
+                .extract_motion_video(item, videos_dst)
+                .wrap_err("failed to extract motion video"),
+            None => Ok(()),
+        };
+        let orientation = if self.fix_orientation && PhotoOrganizer::is_jpeg(item) {
+            orientation::fix_orientation(item)
+                .map(|_| ())
+                .wrap_err("failed to fix orientation")
+        } else {
+            Ok(())
+        };
+        if let Some(near_duplicates) = &self.near_duplicates {
+            if let Some(index) = near_duplicates
+                .index
+                .lock()
+                .expect("index lock poisoned")
+                .as_mut()
+            {
+                if let Ok(hash) = dhash::dhash(item) {
+                    index.push(hash);
+                }
+            }
+        }
+        motion_video.and(orientation)
     }
 }
 
+/// Replaces the characters that aren't valid in folder names on some
+/// systems and trims the surrounding spaces and dots, which Windows
+/// doesn't allow at the end.
+fn sanitize_folder_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if is_invalid_in_name(c) { '_' } else { c })
+        .collect::<String>()
+        .trim_matches(|c| c == ' ' || c == '.')
+        .to_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, SystemTime};
     use tempfile::TempDir;
 
     #[test]
     fn should_organize() {
         let organizer = PhotoOrganizer::new(PathBuf::new());
-        for extension in PhotoOrganizer::SUPPORTED.iter() {
+        for extension in PhotoOrganizer::DEFAULT_EXTENSIONS.iter() {
+            assert!(organizer.should_organize(&PathBuf::from(format!("file.{}", extension))));
+        }
+        for extension in ["JpG", "JPEG", "Png", "NEF"] {
             assert!(organizer.should_organize(&PathBuf::from(format!("file.{}", extension))));
         }
     }
 
+    #[test]
+    fn should_organize_custom_extensions() {
+        let organizer = PhotoOrganizer::new(PathBuf::new())
+            .with_extensions(vec!["HEIC".to_owned(), ".webp".to_owned()]);
+        assert!(organizer.should_organize(&PathBuf::from("file.heic")));
+        assert!(organizer.should_organize(&PathBuf::from("file.WebP")));
+        assert!(!organizer.should_organize(&PathBuf::from("file.jpg")));
+    }
+
     #[test]
     fn should_not_organize() {
         let organizer = PhotoOrganizer::new(PathBuf::new());
@@ -177,30 +1149,950 @@ mod tests {
     }
 
     #[test]
-    fn destination_dir_from_filename() {
-        let src = TempDir::new().unwrap();
-        let photo_dst = TempDir::new().unwrap().into_path();
-        let dst = photo_dst.clone();
+    fn destination_dir_from_png_exif() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG_20200407_1.png");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
 
+        // The name has a date too, but the one in the eXIf chunk is used.
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("03 - March"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_samsung_name() {
         let photo = PathBuf::from(file!())
             .parent()
             .unwrap()
             .parent()
             .unwrap()
             .join("fixtures")
-            .join("IMG-20200407-WA0004.jpg");
-        let sub_dir = src.path().join("sub_dir");
-        fs::create_dir(&sub_dir).unwrap();
-        fs::copy(photo.clone(), sub_dir.join("camera.jpg")).unwrap();
-        let photo_organizer = PhotoOrganizer::new(photo_dst);
+            .join("20190815_101530.jpg");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
 
         assert_eq!(
-            dst.join("2020").join("04 - April").to_str().unwrap(),
+            PathBuf::from("dst").join("2019").join("08 - August"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+        for name in ["20190815_101530(1).jpg", "20190815_101530_001.jpg"] {
+            assert_eq!(
+                PathBuf::from("dst").join("2019").join("08 - August"),
+                photo_organizer
+                    .destination_dir(&PathBuf::from(name))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn destination_dir_from_png_without_exif() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG_20200407_2.png");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+
+        assert!(matches!(
             photo_organizer
-                .destination_dir(&photo)
-                .unwrap()
-                .to_str()
-                .unwrap()
+                .date_from_exif(&photo)
+                .unwrap_err()
+                .downcast_ref::<OrganizerError>(),
+            Some(OrganizerError::ExifMissing)
+        ));
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("04 - April"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_screenshot_name() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+
+        for screenshot in [
+            "Screenshot_20200407-143022.png",
+            "Screenshot 2020-04-07 at 14.30.22.png",
+        ] {
+            assert_eq!(
+                PathBuf::from("dst").join("2020").join("04 - April"),
+                photo_organizer
+                    .destination_dir(&fixtures.join(screenshot))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn organize_with_destination_overrides() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let screenshots = TempDir::new().unwrap();
+        for photo in ["Screenshot_20200407-143022.png", "IMG-20200407-WA0004.jpg"] {
+            fs::copy(fixtures.join(photo), src.path().join(photo)).unwrap();
+        }
+
+        let report = crate::organizer::Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_destination_overrides(
+                HashMap::from([("png".to_owned(), screenshots.path().to_path_buf())]),
+            ),
+        )])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        let april = PathBuf::from("2020").join("04 - April");
+        assert!(screenshots
+            .path()
+            .join(&april)
+            .join("Screenshot_20200407-143022.png")
+            .is_file());
+        assert!(dst
+            .path()
+            .join(&april)
+            .join("IMG-20200407-WA0004.jpg")
+            .is_file());
+        assert!(!dst
+            .path()
+            .join(&april)
+            .join("Screenshot_20200407-143022.png")
+            .exists());
+    }
+
+    #[test]
+    fn destination_dir_by_location() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer =
+            PhotoOrganizer::new(PathBuf::from("dst")).with_group_by_location(true);
+
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2019")
+                .join("06 - June")
+                .join("Spain"),
+            photo_organizer
+                .destination_dir(&fixtures.join("gps.jpg"))
+                .unwrap()
+        );
+        // Without GPS tags it's the same as without grouping.
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("03 - March"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+
+        let photo_organizer = photo_organizer.with_unknown_placeholder("Sin GPS".to_owned());
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2018")
+                .join("03 - March")
+                .join("Sin GPS"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_by_city() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"))
+            .with_group_by_location(true)
+            .with_group_by_city(true);
+
+        for _ in 0..2 {
+            assert_eq!(
+                PathBuf::from("dst")
+                    .join("2019")
+                    .join("06 - June")
+                    .join("Spain")
+                    .join("Madrid"),
+                photo_organizer
+                    .destination_dir(&fixtures.join("gps.jpg"))
+                    .unwrap()
+            );
+        }
+        assert_eq!(1, photo_organizer.places.lock().unwrap().len());
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("03 - March"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+
+        // Without GPS tags there's neither a country nor a city.
+        let photo_organizer = photo_organizer.with_unknown_placeholder("unknown".to_owned());
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2018")
+                .join("03 - March")
+                .join("unknown"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn nearby_places_cached() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"))
+            .with_group_by_location(true)
+            .with_group_by_city(true);
+
+        // A tenth of a second of latitude apart.
+        let place = photo_organizer.place_from_exif(&fixtures.join("gps.jpg"));
+        assert_eq!(
+            place,
+            photo_organizer.place_from_exif(&fixtures.join("gps_nearby.jpg"))
+        );
+        assert_eq!(
+            Some(Place {
+                country: Some("Spain"),
+                city: Some("Madrid"),
+            }),
+            place
+        );
+        assert_eq!(1, photo_organizer.places.lock().unwrap().len());
+
+        photo_organizer.place(48.8566, 2.3522);
+        assert_eq!(2, photo_organizer.places.lock().unwrap().len());
+    }
+
+    #[test]
+    fn organize_renamed_by_date() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("camera.jpg");
+        fs::copy(photo, src.path().join("camera.jpg")).unwrap();
+
+        crate::organizer::Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_rename_by_date(true),
+        )])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert!(!src.path().join("camera.jpg").exists());
+        assert!(dst
+            .path()
+            .join("2019")
+            .join("01 - January")
+            .join("201901_camera.jpg")
+            .is_file());
+    }
+
+    #[test]
+    fn organize_fixing_orientation() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        fs::copy(fixtures.join("rotated.jpg"), src.path().join("rotated.jpg")).unwrap();
+        // Same content, but not a JPEG.
+        fs::copy(fixtures.join("rotated.jpg"), src.path().join("rotated.dng")).unwrap();
+
+        crate::organizer::Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_fix_orientation(true),
+        )])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        let month_dir = dst.path().join("2021").join("05 - May");
+        assert_eq!(
+            (8, 16),
+            image::image_dimensions(month_dir.join("rotated.jpg")).unwrap()
+        );
+        assert_eq!(
+            fs::read(fixtures.join("rotated.jpg")).unwrap(),
+            fs::read(month_dir.join("rotated.dng")).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_mtime() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let src = TempDir::new().unwrap();
+        let dateless = src.path().join("holidays.jpg");
+        fs::copy(photo, &dateless).unwrap();
+        // 2018-05-15T12:00:00Z
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_526_385_600);
+        filetime::set_file_mtime(&dateless, filetime::FileTime::from_system_time(modified))
+            .unwrap();
+
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+        assert!(photo_organizer.destination_dir(&dateless).is_err());
+
+        let photo_organizer = photo_organizer.with_mtime_fallback(true);
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("05 - May"),
+            photo_organizer.destination_dir(&dateless).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_os = "macos", target_os = "linux", windows))]
+    fn destination_dir_from_birthtime() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let src = TempDir::new().unwrap();
+        let dateless = src.path().join("holidays.jpg");
+        // The creation time can't be set, it's when the file is copied.
+        let before = chrono::Local::now();
+        fs::copy(photo, &dateless).unwrap();
+        let after = chrono::Local::now();
+        // Some filesystems, like overlayfs, or older kernels don't have it.
+        if fs::metadata(&dateless).and_then(|m| m.created()).is_err() {
+            return;
+        }
+        // 2018-05-15T12:00:00Z
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_526_385_600);
+        filetime::set_file_mtime(&dateless, filetime::FileTime::from_system_time(modified))
+            .unwrap();
+        let copied = [before, after]
+            .map(|time| PathBuf::from("dst").join(time.format("%Y/%m - %B").to_string()));
+
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"))
+            .with_date_sources(vec![DateSource::Birthtime, DateSource::Mtime]);
+        let dst_dir = photo_organizer.destination_dir(&dateless).unwrap();
+        assert!(copied.contains(&dst_dir), "{:?}", dst_dir);
+
+        let photo_organizer =
+            photo_organizer.with_date_sources(vec![DateSource::Mtime, DateSource::Birthtime]);
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("05 - May"),
+            photo_organizer.destination_dir(&dateless).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_by_camera() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst")).with_group_by_camera(true);
+
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2019")
+                .join("06 - June")
+                .join("Canon EOS R"),
+            photo_organizer
+                .destination_dir(&fixtures.join("canon.jpg"))
+                .unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2019")
+                .join("01 - January")
+                .join("samsung SM-G955F"),
+            photo_organizer
+                .destination_dir(&fixtures.join("camera.jpg"))
+                .unwrap()
+        );
+        // Without a model it's the same as without grouping.
+        assert_eq!(
+            PathBuf::from("dst").join("2018").join("03 - March"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+        let photo_organizer = photo_organizer.with_unknown_placeholder("Other cameras".to_owned());
+        assert_eq!(
+            PathBuf::from("dst")
+                .join("2018")
+                .join("03 - March")
+                .join("Other cameras"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_1.png"))
+                .unwrap()
+        );
+        assert_eq!("Foo_Bar", sanitize_folder_name(" Foo/Bar. "));
+    }
+
+    #[test]
+    fn organize_grouping_bursts() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        for photo in [
+            "IMG_0002_BURST001.jpg",
+            "IMG_0002_BURST002.jpg",
+            "camera.jpg",
+        ] {
+            fs::copy(fixtures.join(photo), src.path().join(photo)).unwrap();
+        }
+
+        let report = crate::organizer::Organizer::new(vec![Box::new(
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_group_bursts(true),
+        )])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(3, report.moved);
+        let date_dir = dst.path().join("2019").join("01 - January");
+        let burst_dir = date_dir.join("Bursts").join("IMG_0002");
+        assert!(burst_dir.join("IMG_0002_BURST001.jpg").is_file());
+        assert!(burst_dir.join("IMG_0002_BURST002.jpg").is_file());
+        assert!(date_dir.join("camera.jpg").is_file());
+    }
+
+    #[test]
+    fn organize_iphone_edits_with_originals() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+        // The edited photo has no date of its own.
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("01 - January"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_E1234.JPG"))
+                .unwrap()
+        );
+        fs::copy(
+            fixtures.join("IMG_E1234.JPG"),
+            src.path().join("IMG_E1234.JPG"),
+        )
+        .unwrap();
+        assert!(photo_organizer
+            .destination_dir(&src.path().join("IMG_E1234.JPG"))
+            .is_err());
+
+        fs::copy(
+            fixtures.join("IMG_1234.JPG"),
+            src.path().join("IMG_1234.JPG"),
+        )
+        .unwrap();
+        let report = crate::organizer::Organizer::new(vec![Box::new(PhotoOrganizer::new(
+            dst.path().to_path_buf(),
+        ))])
+        .organize(&[src.path().to_path_buf()])
+        .unwrap();
+
+        assert_eq!(2, report.moved);
+        let date_dir = dst.path().join("2019").join("01 - January");
+        assert!(date_dir.join("IMG_1234.JPG").is_file());
+        assert!(date_dir.join("IMG_E1234.JPG").is_file());
+    }
+
+    #[test]
+    fn organize_with_min_resolution() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        // 4032x1960 in the exif, 319x490 and 160x120 in the header.
+        for name in [
+            "camera.jpg",
+            "IMG-20200407-WA0004.jpg",
+            "IMG-20200407-WA0005.jpg",
+        ] {
+            fs::copy(fixtures.join(name), src.path().join(name)).unwrap();
+        }
+        let photo_organizer =
+            PhotoOrganizer::new(dst.path().to_path_buf()).with_min_resolution(400, 300);
+        assert_eq!(
+            Some((4032, 1960)),
+            PhotoOrganizer::dimensions(&fixtures.join("camera.jpg"))
+        );
+        assert_eq!(
+            Some("its resolution 160x120 is below 400x300".to_owned()),
+            photo_organizer.skip_reason(&fixtures.join("IMG-20200407-WA0005.jpg"))
+        );
+
+        let report = crate::organizer::Organizer::new(vec![Box::new(photo_organizer)])
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+        assert_eq!(2, report.moved);
+        assert_eq!(1, report.skipped);
+        assert_eq!(1, report.by_organizer["photos"].skipped);
+        assert!(src.path().join("IMG-20200407-WA0005.jpg").is_file());
+        let date_dir = dst.path().join("2020").join("04 - April");
+        assert!(date_dir.join("IMG-20200407-WA0004.jpg").is_file());
+        assert!(!date_dir.join("IMG-20200407-WA0005.jpg").exists());
+    }
+
+    #[test]
+    fn organize_unknown_date() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        for unknown_date_dir in ["unknown-date", "_needs_review"] {
+            let src = TempDir::new().unwrap();
+            let dst = TempDir::new().unwrap();
+            fs::copy(fixtures.join("camera.jpg"), src.path().join("camera.jpg")).unwrap();
+            // Without the date of the WhatsApp name nor exif.
+            fs::copy(
+                fixtures.join("IMG-20200407-WA0004.jpg"),
+                src.path().join("holiday.jpg"),
+            )
+            .unwrap();
+
+            let report = crate::organizer::Organizer::new(vec![Box::new(
+                PhotoOrganizer::new(dst.path().to_path_buf())
+                    .with_unknown_date_dir(PathBuf::from(unknown_date_dir)),
+            )])
+            .organize(&[src.path().to_path_buf()])
+            .unwrap();
+
+            assert_eq!(2, report.moved);
+            assert_eq!(0, report.errored);
+            assert!(dst
+                .path()
+                .join(unknown_date_dir)
+                .join("holiday.jpg")
+                .is_file());
+            assert!(dst
+                .path()
+                .join("2019")
+                .join("01 - January")
+                .join("camera.jpg")
+                .is_file());
+            assert_eq!(2, fs::read_dir(dst.path()).unwrap().count());
+        }
+    }
+
+    #[test]
+    fn burst_id_from_name() {
+        assert_eq!(
+            Some("IMG_0001".to_owned()),
+            PhotoOrganizer::burst_id(Path::new("IMG_0001_BURST001.jpg"))
+        );
+        assert_eq!(
+            Some("20200407164808".to_owned()),
+            PhotoOrganizer::burst_id(Path::new("00000IMG_00000_BURST20200407164808_COVER.jpg"))
+        );
+        assert_eq!(
+            Some("20200407164808".to_owned()),
+            PhotoOrganizer::burst_id(Path::new("00001IMG_00001_BURST20200407164808.jpg"))
+        );
+        assert_eq!(None, PhotoOrganizer::burst_id(Path::new("IMG_0001.jpg")));
+    }
+
+    #[test]
+    fn destination_dir_from_custom_filename_regex() {
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst")).with_filename_regex(
+            Regex::new(r"^trip-(?P<month>\d{2})-(?P<year>\d{4})-\d+\.jpg$").unwrap(),
+        );
+
+        assert_eq!(
+            PathBuf::from("dst").join("2017").join("08 - August"),
+            photo_organizer
+                .destination_dir(&PathBuf::from("trip-08-2017-001.jpg"))
+                .unwrap()
+        );
+        // The default regexes are replaced.
+        assert!(photo_organizer
+            .destination_dir(&PathBuf::from("IMG-20200407-WA0004.jpg"))
+            .is_err());
+    }
+
+    #[test]
+    fn destination_dir_from_exif_date_digitized() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("digitized.jpg");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+
+        assert_eq!(
+            PathBuf::from("dst").join("2017").join("11 - November"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+
+        let photo_organizer = photo_organizer.with_exif_date_tags(vec![ExifDateTag::DateTime]);
+        assert!(matches!(
+            photo_organizer
+                .date_from_exif(&photo)
+                .unwrap_err()
+                .downcast_ref::<OrganizerError>(),
+            Some(OrganizerError::ExifTagMissing(ExifDateTag::DateTime))
+        ));
+        assert!(matches!(
+            photo_organizer
+                .date_from_filename(&photo)
+                .unwrap_err()
+                .downcast_ref::<OrganizerError>(),
+            Some(OrganizerError::FilenameNoDate)
+        ));
+    }
+
+    #[test]
+    fn destination_dir_with_date_sources() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let src = TempDir::new().unwrap();
+        // Taken in January 2019 according to the exif.
+        let photo = src.path().join("IMG-20200407-WA0001.jpg");
+        fs::copy(fixtures.join("camera.jpg"), &photo).unwrap();
+
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("01 - January"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+
+        let photo_organizer =
+            photo_organizer.with_date_sources(vec![DateSource::Filename, DateSource::Exif]);
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("04 - April"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+        // The next source is tried when there's no date in the name.
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("01 - January"),
+            photo_organizer
+                .destination_dir(&fixtures.join("camera.jpg"))
+                .unwrap()
+        );
+
+        let photo_organizer = photo_organizer.with_date_sources(vec![DateSource::Sidecar]);
+        assert!(photo_organizer.destination_dir(&photo).is_err());
+    }
+
+    #[test]
+    fn destination_dir_with_exif_timezone() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("offset.jpg");
+        // Taken 2020-05-01 00:30:00 at +02:00.
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("05 - May"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+
+        let photo_organizer = photo_organizer.with_exif_timezone(ExifTimezone::Normalize);
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("04 - April"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+        // Without an offset tag the date is kept.
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("01 - January"),
+            photo_organizer
+                .destination_dir(&photo.with_file_name("camera.jpg"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_takeout_sidecar() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo = fixtures.join("takeout.jpg");
+
+        // The sidecar says July, the exif June.
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("07 - July"),
+            PhotoOrganizer::new(PathBuf::from("dst"))
+                .destination_dir(&photo)
+                .unwrap()
+        );
+        for takeout_sidecar in [TakeoutSidecar::Last, TakeoutSidecar::Ignore] {
+            assert_eq!(
+                PathBuf::from("dst").join("2019").join("06 - June"),
+                PhotoOrganizer::new(PathBuf::from("dst"))
+                    .with_takeout_sidecar(takeout_sidecar)
+                    .destination_dir(&photo)
+                    .unwrap()
+            );
+        }
+
+        // Without exif, after it is still used.
+        let src = TempDir::new().unwrap();
+        let photo = src.path().join("no-exif.png");
+        fs::copy(fixtures.join("Screenshot_20200407-143022.png"), &photo).unwrap();
+        fs::copy(
+            fixtures.join("takeout.jpg.json"),
+            src.path().join("no-exif.png.json"),
+        )
+        .unwrap();
+        let photo_organizer =
+            PhotoOrganizer::new(PathBuf::from("dst")).with_takeout_sidecar(TakeoutSidecar::Last);
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("07 - July"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+        let photo_organizer = photo_organizer.with_takeout_sidecar(TakeoutSidecar::Ignore);
+        assert!(photo_organizer.destination_dir(&photo).is_err());
+    }
+
+    #[test]
+    fn destination_dir_from_xmp_sidecar() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+
+        assert_eq!(
+            PathBuf::from("dst").join("2016").join("02 - February"),
+            photo_organizer
+                .destination_dir(&fixtures.join("DSC_0002.NEF"))
+                .unwrap()
+        );
+
+        // Sidecars named after the whole file name, with an element.
+        let src = TempDir::new().unwrap();
+        let photo = src.path().join("DSC_0003.NEF");
+        fs::copy(fixtures.join("DSC_0002.NEF"), &photo).unwrap();
+        fs::write(
+            src.path().join("DSC_0003.NEF.xmp"),
+            "<exif:DateTimeOriginal>2015-09-01T08:00:00</exif:DateTimeOriginal>",
+        )
+        .unwrap();
+        assert_eq!(
+            PathBuf::from("dst").join("2015").join("09 - September"),
+            photo_organizer.destination_dir(&photo).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_raw() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"));
+
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("06 - June"),
+            photo_organizer
+                .destination_dir(&fixtures.join("DSC_0001.NEF"))
+                .unwrap()
+        );
+        // A RAW without DateTimeOriginal falls back to its name.
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("04 - April"),
+            photo_organizer
+                .destination_dir(&fixtures.join("IMG_20200407_3.dng"))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn read_exif_of_large_raw() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        // A RAW whose image data makes it much bigger than the read limit.
+        let dir = TempDir::new().unwrap();
+        let raw = dir.path().join("large.NEF");
+        let mut data = fs::read(fixtures.join("DSC_0001.NEF")).unwrap();
+        data.resize(8 * PhotoOrganizer::TIFF_EXIF_READ_LIMIT as usize, 0);
+        fs::write(&raw, data).unwrap();
+
+        let fields = |exif: exif::Exif| {
+            exif.fields()
+                .map(|f| (f.tag, f.ifd_num, f.display_value().to_string()))
+                .collect::<Vec<_>>()
+        };
+        let whole = exif::Reader::new()
+            .read_from_container(&mut io::BufReader::new(fs::File::open(&raw).unwrap()))
+            .unwrap();
+        assert_eq!(
+            fields(whole),
+            fields(PhotoOrganizer::read_exif(&raw).unwrap())
+        );
+        // A limit cutting the exif falls back to reading the whole file.
+        assert_eq!(
+            fields(PhotoOrganizer::read_exif(&raw).unwrap()),
+            fields(PhotoOrganizer::read_exif_limited(&raw, 16).unwrap())
+        );
+        assert_eq!(
+            PathBuf::from("dst").join("2019").join("06 - June"),
+            PhotoOrganizer::new(PathBuf::from("dst"))
+                .destination_dir(&raw)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_near_duplicate() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let dst = TempDir::new().unwrap();
+        let month_dir = dst.path().join("2020").join("04 - April");
+        fs::create_dir_all(&month_dir).unwrap();
+        fs::copy(&photo, month_dir.join("IMG-20200407-WA0004.jpg")).unwrap();
+
+        let src = TempDir::new().unwrap();
+        let resized = src.path().join("IMG-20200407-WA0005.jpg");
+        image::open(&photo)
+            .unwrap()
+            .resize(160, 160, image::imageops::FilterType::Triangle)
+            .save(&resized)
+            .unwrap();
+        let different = src.path().join("IMG-20200407-WA0006.jpg");
+        image::RgbImage::from_fn(160, 160, |x, y| {
+            let shade = if (x / 20 + y / 20) % 2 == 0 { 255 } else { 0 };
+            image::Rgb([shade, shade, shade])
+        })
+        .save(&different)
+        .unwrap();
+
+        let photo_organizer = PhotoOrganizer::new(dst.path().to_path_buf()).with_near_duplicates(8);
+        assert_eq!(
+            dst.path().join("duplicates-review"),
+            photo_organizer.destination_dir(&resized).unwrap()
+        );
+        assert_eq!(
+            month_dir,
+            photo_organizer.destination_dir(&different).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_filename() {
+        let src = TempDir::new().unwrap();
+        let photo_dst = TempDir::new().unwrap().into_path();
+        let dst = photo_dst.clone();
+
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let sub_dir = src.path().join("sub_dir");
+        fs::create_dir(&sub_dir).unwrap();
+        fs::copy(photo.clone(), sub_dir.join("camera.jpg")).unwrap();
+        let photo_organizer = PhotoOrganizer::new(photo_dst);
+
+        assert_eq!(
+            dst.join("2020").join("04 - April").to_str().unwrap(),
+            photo_organizer
+                .destination_dir(&photo)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_with_folder_format_and_locale() {
+        let photo = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg");
+        let photo_organizer = PhotoOrganizer::new(PathBuf::from("dst"))
+            .with_folder_format("%Y/%b/%B".to_owned())
+            .with_locale(Locale::Es);
+
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("Abr").join("Abril"),
+            photo_organizer.destination_dir(&photo).unwrap()
         );
     }
 }
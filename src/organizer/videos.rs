@@ -1,77 +1,340 @@
-use super::MediaTypeOrganizer;
-use crate::date::Date;
-use color_eyre::eyre::{eyre, Result, WrapErr};
+use super::{date_folder, date_prefixed_name, destination_override, MediaTypeOrganizer};
+use crate::date::{Date, Locale};
+use crate::error::OrganizerError;
+use color_eyre::eyre::{bail, eyre, Result, WrapErr};
 use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Resolution class of the videos whose shorter side has at least
+/// `min_lines` pixels, for example `HD` from 720.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionTier {
+    pub name: String,
+    pub min_lines: u16,
+}
+
+impl ResolutionTier {
+    pub fn new(name: &str, min_lines: u16) -> ResolutionTier {
+        ResolutionTier {
+            name: name.to_owned(),
+            min_lines,
+        }
+    }
+
+    /// `4K` from 2160 lines, `HD` from 720 and `SD` for the rest.
+    pub fn defaults() -> Vec<ResolutionTier> {
+        vec![
+            ResolutionTier::new("4K", 2160),
+            ResolutionTier::new("HD", 720),
+            ResolutionTier::new("SD", 0),
+        ]
+    }
+}
 
 /// It organizes videos in directories by year. The year is taken from
-/// the file name using the regex `^(?:VID[-_])?(\d{4})(\d{2})\d{2}[_-].+\.mp4$`,
-/// which basically translate to `VID-YYYYMMDD-whatever.mp4` where
-/// `VID-` is optional and `-` can be changed to `_`.
+/// the creation time of MP4 and MOV videos and, when they don't have
+/// one, from the file name using the regex
+/// `^(?:VID[-_]|PXL[-_])?(?P<year>\d{4})(?P<month>\d{2})\d{2}[_-].+\.\w+$`, which basically
+/// translate to `VID-YYYYMMDD-whatever.mp4` where `VID-` is optional and
+/// `-` can be changed to `_`. Only `mp4`, `mov`, `avi`, `mkv`, `m4v` and
+/// `3gp` videos are organized.
+///
+/// Videos inside a source folder already named like a date, for
+/// example `2019-07-trip`, can be kept together, see
+/// [`Self::with_respect_date_folders`].
+///
+/// Videos can be organized by month too, like photos, see
+/// [`Self::with_group_by_month`]. Inside the year, or month, videos can be
+/// grouped by resolution, see [`Self::with_resolution_tiers`].
+///
+/// Videos can be renamed with their date as prefix, see
+/// [`Self::with_rename_by_date`].
 pub struct VideoOrganizer {
     dst_dir: PathBuf,
     date_from_filename_regex: Regex,
+    respect_date_folders: bool,
+    group_by_month: bool,
+    locale: Locale,
+    mtime_fallback: bool,
+    resolution_tiers: Option<Vec<ResolutionTier>>,
+    unknown_placeholder: String,
+    rename_by_date: bool,
+    unknown_date_dir: Option<PathBuf>,
+    destination_overrides: HashMap<String, PathBuf>,
 }
 
 impl VideoOrganizer {
-    const SUPPORTED: [&'static str; 2] = ["mp4", "avi"];
+    const SUPPORTED: [&'static str; 6] = ["mp4", "mov", "avi", "mkv", "m4v", "3gp"];
+    const DEFAULT_UNKNOWN_PLACEHOLDER: &'static str = "unknown";
 
     pub fn new(dst_dir: PathBuf) -> VideoOrganizer {
         VideoOrganizer {
             dst_dir,
             date_from_filename_regex: Regex::new(
-                r"^(?:VID[-_]|PXL[-_])?(\d{4})(\d{2})\d{2}[_-].+\.mp4$",
+                r"^(?:VID[-_]|PXL[-_])?(?P<year>\d{4})(?P<month>\d{2})\d{2}[_-].+\.\w+$",
             )
             .unwrap(),
+            respect_date_folders: false,
+            group_by_month: false,
+            locale: Locale::En,
+            mtime_fallback: false,
+            resolution_tiers: None,
+            unknown_placeholder: VideoOrganizer::DEFAULT_UNKNOWN_PLACEHOLDER.to_owned(),
+            rename_by_date: false,
+            unknown_date_dir: None,
+            destination_overrides: HashMap::new(),
         }
     }
 
+    /// Organizes the videos in a directory for the month inside the
+    /// year, like the photos, for example `2020/08 - August/`.
+    pub fn with_group_by_month(mut self, group_by_month: bool) -> VideoOrganizer {
+        self.group_by_month = group_by_month;
+        self
+    }
+
+    /// Language of the month names when grouping by month. Defaults to
+    /// English.
+    pub fn with_locale(mut self, locale: Locale) -> VideoOrganizer {
+        self.locale = locale;
+        self
+    }
+
+    /// Replaces the default regex used to take the date from the file
+    /// name. The year and month are taken from the `year` and `month`
+    /// named groups, for example `^CAM(?P<year>\d{4})(?P<month>\d{2})`.
+    pub fn with_filename_regex(mut self, regex: Regex) -> VideoOrganizer {
+        self.date_from_filename_regex = regex;
+        self
+    }
+
+    /// Videos whose date can't be taken from the metadata nor the name
+    /// are organized by their modification time.
+    pub fn with_mtime_fallback(mut self, mtime_fallback: bool) -> VideoOrganizer {
+        self.mtime_fallback = mtime_fallback;
+        self
+    }
+
+    /// Videos without a date are moved to this directory of the
+    /// destination instead of being left in the source.
+    pub fn with_unknown_date_dir(mut self, unknown_date_dir: PathBuf) -> VideoOrganizer {
+        self.unknown_date_dir = Some(unknown_date_dir);
+        self
+    }
+
+    /// Videos with one of the extensions, lowercase and without the dot,
+    /// are organized in its directory instead of the destination dir.
+    pub fn with_destination_overrides(
+        mut self,
+        destination_overrides: HashMap<String, PathBuf>,
+    ) -> VideoOrganizer {
+        self.destination_overrides = destination_overrides;
+        self
+    }
+
+    fn dst_dir(&self, video: &Path) -> &Path {
+        destination_override(&self.destination_overrides, video).unwrap_or(&self.dst_dir)
+    }
+
+    /// Groups the videos inside the year by the first tier their
+    /// resolution reaches, trying the tiers with more lines first. For
+    /// example `2020/4K/`. Videos whose resolution can't be read, or that
+    /// don't reach any tier, go to `unknown`, see
+    /// [`Self::with_unknown_placeholder`].
+    pub fn with_resolution_tiers(mut self, mut tiers: Vec<ResolutionTier>) -> VideoOrganizer {
+        tiers.sort_by_key(|tier| Reverse(tier.min_lines));
+        self.resolution_tiers = Some(tiers);
+        self
+    }
+
+    /// Folder the videos go to when grouped by resolution and it can't be
+    /// read or doesn't reach any tier, defaults to `unknown`.
+    pub fn with_unknown_placeholder(mut self, unknown_placeholder: String) -> VideoOrganizer {
+        self.unknown_placeholder = unknown_placeholder;
+        self
+    }
+
+    /// Prefixes the name of the videos with their `YYYYMM` date, like
+    /// `202008_holidays.mp4`, so they sort chronologically.
+    pub fn with_rename_by_date(mut self, rename_by_date: bool) -> VideoOrganizer {
+        self.rename_by_date = rename_by_date;
+        self
+    }
+
+    fn resolution_dir<'a>(&'a self, tiers: &'a [ResolutionTier], video: &Path) -> &'a str {
+        let lines = match VideoOrganizer::resolution(video) {
+            Ok((width, height)) => width.min(height),
+            Err(_) => return &self.unknown_placeholder,
+        };
+        tiers
+            .iter()
+            .find(|tier| lines >= tier.min_lines)
+            .map(|tier| tier.name.as_str())
+            .unwrap_or(&self.unknown_placeholder)
+    }
+
+    /// Width and height of the first video track.
+    fn resolution(video: &Path) -> Result<(u16, u16)> {
+        VideoOrganizer::read_header(video)?
+            .tracks()
+            .values()
+            .find(|track| matches!(track.track_type(), Ok(mp4::TrackType::Video)))
+            .map(|track| (track.width(), track.height()))
+            .ok_or_else(|| eyre!("video track is missing"))
+    }
+
+    fn read_header(video: &Path) -> Result<mp4::Mp4Reader<io::BufReader<fs::File>>> {
+        let file = fs::File::open(video).wrap_err("failed to open file")?;
+        let size = file.metadata().wrap_err("failed to read file size")?.len();
+        mp4::Mp4Reader::read_header(io::BufReader::new(file), size)
+            .wrap_err("failed to read mp4 header")
+    }
+
+    /// Videos in a folder whose name starts with a date, like
+    /// `2019-07-trip`, are organized by the date of the folder instead of
+    /// their own, and the folder is kept. For example
+    /// `2019-07-trip/video.mp4` goes to `2019/2019-07-trip/`. Only the
+    /// folder containing the video is taken into account.
+    pub fn with_respect_date_folders(mut self, respect_date_folders: bool) -> VideoOrganizer {
+        self.respect_date_folders = respect_date_folders;
+        self
+    }
+
     fn get_date(&self, video: &Path) -> Result<Date> {
+        let metadata_date =
+            VideoOrganizer::date_from_metadata(video).wrap_err("failed to get date from metadata");
+        if metadata_date.is_ok() {
+            return metadata_date;
+        }
+
+        let filename_date = self
+            .date_from_filename(video)
+            .wrap_err("failed to get date from filename")
+            .wrap_err(metadata_date.unwrap_err());
+        if filename_date.is_ok() || !self.mtime_fallback {
+            return filename_date;
+        }
+
+        VideoOrganizer::date_from_mtime(video)
+            .wrap_err("failed to get date from modification time")
+            .wrap_err(filename_date.unwrap_err())
+    }
+
+    fn date_from_mtime(video: &Path) -> Result<Date> {
+        let modified = fs::metadata(video)
+            .and_then(|metadata| metadata.modified())
+            .wrap_err("failed to read modification time")?;
+        Date::from_timestamp(modified)
+    }
+
+    /// Date from the creation time of the `mvhd` atom of MP4 and MOV
+    /// videos.
+    pub(crate) fn date_from_metadata(video: &Path) -> Result<Date> {
+        // Seconds between 1904-01-01, the epoch of MP4 times, and
+        // 1970-01-01.
+        const MP4_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+        let creation_time = VideoOrganizer::read_header(video)?.moov.mvhd.creation_time;
+        if creation_time <= MP4_EPOCH_OFFSET {
+            bail!("mp4 creation time is missing");
+        }
+        Date::from_timestamp(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(creation_time - MP4_EPOCH_OFFSET),
+        )
+    }
+
+    fn date_from_filename(&self, video: &Path) -> Result<Date> {
         let file_name = video
             .file_name()
             .ok_or_else(|| eyre!("failed to read file name"))?
-            .to_str()
-            .ok_or_else(|| eyre!("failed to get date as string"))?;
+            .to_string_lossy();
 
         let captures = self
             .date_from_filename_regex
-            .captures(file_name)
-            .ok_or_else(|| eyre!("file name doesn't contain date in the format YYYYMMDD"))?;
-        let year: u16 = match captures.get(1) {
-            Some(y) => y.as_str().parse().unwrap(),
+            .captures(&file_name)
+            .ok_or(OrganizerError::FilenameNoDate)?;
+        let year: u16 = match captures.name("year") {
+            Some(y) => y.as_str().parse().wrap_err("invalid year in filename")?,
             None => return Err(eyre!("failed to retrieve year from filename")),
         };
-        let month: u8 = match captures.get(2) {
-            Some(m) => m.as_str().parse().unwrap(),
+        let month: u8 = match captures.name("month") {
+            Some(m) => m.as_str().parse().wrap_err("invalid month in filename")?,
             None => return Err(eyre!("failed retrieve month from filename")),
         };
-        Date::new(year, month)
+        Ok(Date::new(year, month)?)
     }
 
-    fn is_supported(extension: &str) -> bool {
-        for i in VideoOrganizer::SUPPORTED.iter() {
-            if extension.eq(*i) {
-                return true;
-            }
-        }
-        false
+    fn is_supported(extension: &OsStr) -> bool {
+        VideoOrganizer::SUPPORTED
+            .iter()
+            .any(|supported| extension.eq_ignore_ascii_case(supported))
     }
 }
 
 impl MediaTypeOrganizer for VideoOrganizer {
+    fn name(&self) -> &'static str {
+        "videos"
+    }
+
     fn should_organize(&self, item: &Path) -> bool {
-        let extension = item.extension().and_then(|e| e.to_str());
-        match extension {
-            Some(e) => VideoOrganizer::is_supported(e),
-            None => false,
+        item.extension().is_some_and(VideoOrganizer::is_supported)
+    }
+
+    fn date(&self, item: &Path) -> Option<Date> {
+        if self.respect_date_folders {
+            if let Some((folder_date, _)) = date_folder(item) {
+                return Some(folder_date);
+            }
         }
+        self.get_date(item).ok()
+    }
+
+    fn destination_name(&self, item: &Path) -> Result<OsString> {
+        if self.rename_by_date {
+            let date = self.date(item);
+            if date.is_some() || self.unknown_date_dir.is_none() {
+                return date_prefixed_name(item, date);
+            }
+        }
+        item.file_name()
+            .map(|name| name.to_os_string())
+            .ok_or_else(|| eyre!("failed to get file name"))
     }
 
     fn destination_dir(&self, item: &Path) -> Result<PathBuf> {
-        let video_date = self
-            .get_date(item)
-            .wrap_err("failed to generate destination dir")?;
-        Ok(self.dst_dir.join(video_date.get_year()))
+        let date_folder = if self.respect_date_folders {
+            date_folder(item)
+        } else {
+            None
+        };
+        let (video_date, folder) = match date_folder {
+            Some((folder_date, folder)) => (folder_date, Some(folder)),
+            None => match (self.get_date(item), &self.unknown_date_dir) {
+                (Ok(date), _) => (date, None),
+                (Err(_), Some(unknown_date_dir)) => {
+                    return Ok(self.dst_dir(item).join(unknown_date_dir))
+                }
+                (Err(e), None) => return Err(e.wrap_err("failed to generate destination dir")),
+            },
+        };
+
+        let mut dir = self.dst_dir(item).join(video_date.get_year());
+        if self.group_by_month {
+            dir = dir.join(video_date.format("%m - %B", self.locale));
+        }
+        if let Some(tiers) = &self.resolution_tiers {
+            dir = dir.join(self.resolution_dir(tiers, item));
+        }
+        if let Some(folder) = folder {
+            dir = dir.join(folder);
+        }
+        Ok(dir)
     }
 }
 
@@ -88,6 +351,9 @@ mod tests {
         for extension in VideoOrganizer::SUPPORTED.iter() {
             assert!(organizer.should_organize(&PathBuf::from(format!("file.{}", extension))));
         }
+        for extension in ["MP4", "Avi"] {
+            assert!(organizer.should_organize(&PathBuf::from(format!("file.{}", extension))));
+        }
     }
 
     #[test]
@@ -99,6 +365,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn destination_dir_by_resolution() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"))
+            .with_resolution_tiers(ResolutionTier::defaults());
+
+        for (video, tier) in [
+            ("20200829_4k.mp4", "4K"),
+            ("20200829_hd.mp4", "HD"),
+            ("20200829_sd.mp4", "SD"),
+            // An empty file, its resolution can't be read.
+            ("20200829_205420.mp4", "unknown"),
+        ] {
+            assert_eq!(
+                PathBuf::from("dst").join("2020").join(tier),
+                video_organizer
+                    .destination_dir(&fixtures.join(video))
+                    .unwrap()
+            );
+        }
+
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"))
+            .with_resolution_tiers(vec![ResolutionTier::new("FullHD", 1080)]);
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("unknown"),
+            video_organizer
+                .destination_dir(&fixtures.join("20200829_sd.mp4"))
+                .unwrap()
+        );
+
+        let video_organizer = video_organizer.with_unknown_placeholder("Other".to_owned());
+        assert_eq!(
+            PathBuf::from("dst").join("2020").join("Other"),
+            video_organizer
+                .destination_dir(&fixtures.join("20200829_sd.mp4"))
+                .unwrap()
+        );
+    }
+
     #[test]
     fn destination_dir() {
         let src = TempDir::new().unwrap();
@@ -125,5 +435,175 @@ mod tests {
                 .to_str()
                 .unwrap()
         );
+
+        let video_organizer = video_organizer.with_group_by_month(true);
+        assert_eq!(
+            dst.join("2020").join("08 - August"),
+            video_organizer.destination_dir(&video).unwrap()
+        );
+        let video_organizer = video_organizer.with_locale(Locale::Es);
+        assert_eq!(
+            dst.join("2020").join("08 - Agosto"),
+            video_organizer.destination_dir(&video).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_metadata() {
+        let video = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("holidays.mp4");
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"));
+
+        assert_eq!(
+            PathBuf::from("dst").join("2016"),
+            video_organizer.destination_dir(&video).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_mtime() {
+        let video = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("20200829_sd.mp4");
+        let src = TempDir::new().unwrap();
+        let dateless = src.path().join("birthday.mp4");
+        fs::copy(video, &dateless).unwrap();
+        // 2018-05-15T12:00:00Z
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_526_385_600);
+        filetime::set_file_mtime(&dateless, filetime::FileTime::from_system_time(modified))
+            .unwrap();
+
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"));
+        assert!(video_organizer.destination_dir(&dateless).is_err());
+
+        let video_organizer = video_organizer.with_mtime_fallback(true);
+        assert_eq!(
+            PathBuf::from("dst").join("2018"),
+            video_organizer.destination_dir(&dateless).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_of_unknown_date() {
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"))
+            .with_rename_by_date(true)
+            .with_unknown_date_dir(PathBuf::from("unknown-date"));
+        let dateless = PathBuf::from("birthday.mp4");
+
+        assert_eq!(
+            PathBuf::from("dst").join("unknown-date"),
+            video_organizer.destination_dir(&dateless).unwrap()
+        );
+        assert_eq!(
+            OsString::from("birthday.mp4"),
+            video_organizer.destination_name(&dateless).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_from_custom_filename_regex() {
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst")).with_filename_regex(
+            Regex::new(r"^clip-(?P<month>\d{2})-(?P<year>\d{4})-\d+\.mp4$").unwrap(),
+        );
+
+        assert_eq!(
+            PathBuf::from("dst").join("2017"),
+            video_organizer
+                .destination_dir(&PathBuf::from("clip-08-2017-001.mp4"))
+                .unwrap()
+        );
+        assert!(VideoOrganizer::new(PathBuf::from("dst"))
+            .destination_dir(&PathBuf::from("clip-08-2017-001.mp4"))
+            .is_err());
+    }
+
+    #[test]
+    fn destination_name_renamed_by_date() {
+        let video = PathBuf::from("VID-20200407-WA0001.mp4");
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst")).with_rename_by_date(true);
+
+        assert_eq!(
+            OsString::from("202004_VID-20200407-WA0001.mp4"),
+            video_organizer.destination_name(&video).unwrap()
+        );
+        assert_eq!(
+            OsString::from("VID-20200407-WA0001.mp4"),
+            VideoOrganizer::new(PathBuf::from("dst"))
+                .destination_name(&video)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_mov() {
+        let video = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("VID_20200829_120000.mov");
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"));
+
+        assert!(video_organizer.should_organize(&video));
+        assert_eq!(
+            PathBuf::from("dst").join("2020"),
+            video_organizer.destination_dir(&video).unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_dir_other_containers() {
+        let fixtures = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures");
+        let video_organizer = VideoOrganizer::new(PathBuf::from("dst"));
+
+        for (video, year) in [
+            ("VID_20190512_101010.mkv", "2019"),
+            ("20180304_090000.3gp", "2018"),
+        ] {
+            let video = fixtures.join(video);
+            assert!(video_organizer.should_organize(&video));
+            assert_eq!(
+                PathBuf::from("dst").join(year),
+                video_organizer.destination_dir(&video).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn destination_dir_upper_case_extension() {
+        let src = TempDir::new().unwrap();
+        let dst = TempDir::new().unwrap();
+
+        let video = PathBuf::from(file!())
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("fixtures")
+            .join("20200829_205420.mp4");
+        let upper_case = src.path().join("20200829_205420.MP4");
+        fs::copy(&video, &upper_case).unwrap();
+        let video_organizer = VideoOrganizer::new(dst.path().to_path_buf());
+
+        assert!(video_organizer.should_organize(&upper_case));
+        assert_eq!(
+            dst.path().join("2020"),
+            video_organizer.destination_dir(&upper_case).unwrap()
+        );
     }
 }
@@ -0,0 +1,98 @@
+use color_eyre::eyre::{Result, WrapErr};
+use filetime::FileTime;
+use image::codecs::jpeg::{JpegDecoder, JpegEncoder};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageEncoder};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+/// Quality the rotated JPEGs are encoded with.
+const JPEG_QUALITY: u8 = 92;
+
+/// Rotates and flips the pixels of a JPEG to the orientation its exif
+/// `Orientation` tag tells viewers to display it in, and resets the tag
+/// keeping the rest of the exif. The photo is left untouched, and false
+/// returned, when it has no orientation or it's already `1`.
+pub fn fix_orientation(path: &Path) -> Result<bool> {
+    let data = fs::read(path).wrap_err("failed to read photo")?;
+    let mut decoder = JpegDecoder::new(Cursor::new(&data)).wrap_err("failed to read jpeg")?;
+    let mut exif = match decoder.exif_metadata().wrap_err("failed to read exif")? {
+        Some(exif) => exif,
+        None => return Ok(false),
+    };
+    let orientation = match Orientation::remove_from_exif_chunk(&mut exif) {
+        Some(Orientation::NoTransforms) | None => return Ok(false),
+        Some(orientation) => orientation,
+    };
+    let mut image = DynamicImage::from_decoder(decoder).wrap_err("failed to decode jpeg")?;
+    image.apply_orientation(orientation);
+
+    let mut encoded = Vec::new();
+    let mut encoder = JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY);
+    encoder
+        .set_exif_metadata(exif)
+        .wrap_err("failed to keep exif")?;
+    image
+        .write_with_encoder(encoder)
+        .wrap_err("failed to encode jpeg")?;
+
+    // Written aside and renamed over the photo, so it's never left half
+    // written, keeping its times.
+    let metadata = fs::metadata(path).wrap_err("failed to read photo metadata")?;
+    let rotated = path.with_extension("rotated.tmp");
+    fs::write(&rotated, encoded).wrap_err("failed to write rotated photo")?;
+    filetime::set_file_times(
+        &rotated,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+    .wrap_err("failed to keep the times of the rotated photo")?;
+    fs::rename(&rotated, path).wrap_err("failed to replace photo with the rotated one")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn fix_rotated_jpeg() {
+        let dir = TempDir::new().unwrap();
+        let photo = dir.path().join("rotated.jpg");
+        fs::copy(
+            PathBuf::from(file!())
+                .parent()
+                .unwrap()
+                .join("fixtures")
+                .join("rotated.jpg"),
+            &photo,
+        )
+        .unwrap();
+        assert_eq!((16, 8), image::image_dimensions(&photo).unwrap());
+
+        assert!(fix_orientation(&photo).unwrap());
+
+        // Rotated 90 degrees clockwise, the red left half on top.
+        let image = image::open(&photo).unwrap().into_rgb8();
+        assert_eq!((8, 16), image.dimensions());
+        assert!(image.get_pixel(4, 2)[0] > 200);
+        assert!(image.get_pixel(4, 13)[2] > 200);
+        let file = fs::File::open(&photo).unwrap();
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::BufReader::new(file))
+            .unwrap();
+        let orientation = exif
+            .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+            .unwrap();
+        assert_eq!(Some(1), orientation.value.get_uint(0));
+        assert!(exif
+            .get_field(exif::Tag::DateTime, exif::In::PRIMARY)
+            .is_some());
+
+        // Already upright.
+        assert!(!fix_orientation(&photo).unwrap());
+    }
+}
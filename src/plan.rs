@@ -0,0 +1,39 @@
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where each file of a run goes, decided by [`crate::Organizer::plan`]
+/// without touching the files and carried out later by
+/// [`crate::Organizer::apply`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub moves: Vec<PlannedMove>,
+}
+
+/// A file of the [`Plan`] and its destination.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedMove {
+    /// Path of the file in the media source.
+    pub source: PathBuf,
+    /// Path the file is transferred to.
+    pub destination: PathBuf,
+    /// [`crate::MediaTypeOrganizer::name`] of the organizer that decided
+    /// the destination.
+    pub organizer: String,
+}
+
+impl Plan {
+    /// Reads a plan written by [`Plan::write`].
+    pub fn read(path: &Path) -> Result<Plan> {
+        let content =
+            fs::read_to_string(path).wrap_err_with(|| format!("failed to read plan {:?}", path))?;
+        serde_json::from_str(&content).wrap_err_with(|| format!("invalid plan {:?}", path))
+    }
+
+    /// Writes the plan as JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).wrap_err("failed to serialize plan")?;
+        fs::write(path, json).wrap_err_with(|| format!("failed to write plan to {:?}", path))
+    }
+}
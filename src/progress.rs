@@ -0,0 +1,62 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use log::info;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the progress is logged when it can't be drawn as a bar.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Progress of an organize run, drawn as a bar when the output is a
+/// terminal and logged every [`LOG_INTERVAL`] otherwise.
+pub(crate) struct Progress {
+    total: u64,
+    bar: Option<ProgressBar>,
+    logged: Mutex<(u64, Instant)>,
+}
+
+impl Progress {
+    pub(crate) fn new(total: u64) -> Progress {
+        let bar = io::stderr().is_terminal().then(|| {
+            let bar = ProgressBar::new(total);
+            bar.set_style(
+                ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} {wide_msg}")
+                    .expect("invalid progress template"),
+            );
+            bar
+        });
+        Progress {
+            total,
+            bar,
+            logged: Mutex::new((0, Instant::now())),
+        }
+    }
+
+    /// Counts one more file done, showing where it was organized.
+    pub(crate) fn file_done(&self, destination: Option<&Path>) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+            if let Some(destination) = destination {
+                bar.set_message(destination.display().to_string());
+            }
+            return;
+        }
+        let mut logged = self.logged.lock().expect("progress lock poisoned");
+        logged.0 += 1;
+        if logged.1.elapsed() >= LOG_INTERVAL {
+            info!("organized {}/{} files", logged.0, self.total);
+            logged.1 = Instant::now();
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        match &self.bar {
+            Some(bar) => bar.finish_and_clear(),
+            None => {
+                let logged = self.logged.lock().expect("progress lock poisoned");
+                info!("organized {}/{} files", logged.0, self.total);
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use assert_cmd::Command;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+/// Runs the organizer on a media source with a photo, returning its
+/// stdout and stderr.
+fn organize(args: &[&str]) -> (String, String) {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    fs::copy(
+        Path::new("src")
+            .join("fixtures")
+            .join("IMG-20200407-WA0004.jpg"),
+        src.path().join("IMG-20200407-WA0004.jpg"),
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("the-media-organizer")
+        .unwrap()
+        .env_remove("RUST_LOG")
+        .args(["-m", src.path().to_str().unwrap()])
+        .args(["-p", dst.path().to_str().unwrap()])
+        .arg("--no-load-default-config-file")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(dst
+        .path()
+        .join("2020")
+        .join("04 - April")
+        .join("IMG-20200407-WA0004.jpg")
+        .is_file());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn quiet() {
+    let (stdout, stderr) = organize(&[]);
+    assert!(stderr.contains("Media Organizer configuration loaded"));
+    assert!(stdout.starts_with("Done, moved: 1"));
+
+    for quiet in ["--quiet", "-q"] {
+        let (stdout, stderr) = organize(&[quiet]);
+        assert_eq!("", stderr);
+        assert!(stdout.starts_with("Done, moved: 1"));
+    }
+}
+
+#[test]
+fn organize_newest_first() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let journal = TempDir::new().unwrap();
+    let journal = journal.path().join("journal.log");
+    // Each in its own destination dir, the later the name the newer.
+    let names: Vec<_> = (1..=6)
+        .map(|month| format!("IMG-20200{}07-WA0004.jpg", month))
+        .collect();
+    for (i, name) in names.iter().enumerate() {
+        let photo = src.path().join(name);
+        fs::copy(
+            Path::new("src")
+                .join("fixtures")
+                .join("IMG-20200407-WA0004.jpg"),
+            &photo,
+        )
+        .unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000 + i as u64);
+        filetime::set_file_mtime(&photo, filetime::FileTime::from_system_time(modified)).unwrap();
+    }
+
+    // Without --jobs, which defaults to the number of CPUs otherwise.
+    Command::cargo_bin("the-media-organizer")
+        .unwrap()
+        .env_remove("RUST_LOG")
+        .args(["-m", src.path().to_str().unwrap()])
+        .args(["-p", dst.path().to_str().unwrap()])
+        .args(["--journal", journal.to_str().unwrap()])
+        .arg("--organize-newest-first")
+        .arg("--no-load-default-config-file")
+        .assert()
+        .success();
+
+    let moved: Vec<_> = fs::read_to_string(&journal)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (from, _) = line.split_once('\t').unwrap();
+            Path::new(from)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+    let newest_first: Vec<_> = names.into_iter().rev().collect();
+    assert_eq!(newest_first, moved);
+}
+
+#[test]
+fn log_file() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let logs = TempDir::new().unwrap();
+    let log_file = logs.path().join("organizer.log");
+    fs::write(src.path().join("broken.jpg"), "not a photo").unwrap();
+    let run = |args: &[&str]| {
+        Command::cargo_bin("the-media-organizer")
+            .unwrap()
+            .env_remove("RUST_LOG")
+            .args(["-m", src.path().to_str().unwrap()])
+            .args(["-p", dst.path().to_str().unwrap()])
+            .args(["--log-file", log_file.to_str().unwrap()])
+            .arg("--no-load-default-config-file")
+            .args(args)
+            .assert()
+            .success();
+        fs::read_to_string(&log_file).unwrap()
+    };
+
+    let log = run(&[]);
+    assert!(log.contains("failed to get destination dir"));
+    assert!(!log.contains("Media Organizer configuration loaded"));
+
+    // Appended, with the details of verbose mode.
+    let log = run(&["--verbose"]);
+    assert_eq!(2, log.matches("failed to get destination dir").count());
+    assert!(log.contains("Media Organizer configuration loaded"));
+}